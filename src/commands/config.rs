@@ -0,0 +1,584 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use chrono::Utc;
+use clap::{Args, Parser, Subcommand};
+use serde::Serialize;
+use toml::Value;
+use toml_edit::{DocumentMut, Item, TableLike};
+
+use crate::{
+    commands::{Result, RuntimeError, confirm},
+    config::{
+        Config, Profile,
+        layers::{self, ConfigSources, LoadedConfig, PROJECT_CONFIG_FILE_NAME},
+        migrate,
+        validate::{self, Kind, SCHEMA},
+    },
+};
+
+/// Editor used when neither `$VISUAL` nor `$EDITOR` is set.
+const DEFAULT_EDITOR: &str = "vi";
+
+#[derive(Debug, Parser)]
+pub struct ConfigCommand {
+    #[command(subcommand)]
+    pub action: ConfigAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigAction {
+    /// Print the configuration, or the profile selected with `--profile`
+    Show {
+        #[arg(long)]
+        /// Print the profile with all of its base profiles merged in
+        resolved: bool,
+
+        #[arg(long, conflicts_with = "resolved")]
+        /// Print every effective value along with the layer it came from
+        origins: bool,
+    },
+    /// Print the effective value of a key, for example `paths.models_dir`
+    Get { key: String },
+    /// Set the value of a key in the configuration file
+    Set {
+        key: String,
+        value: String,
+        #[command(flatten)]
+        target: TargetFile,
+    },
+    /// Remove a key from the configuration file
+    Unset {
+        key: String,
+        #[command(flatten)]
+        target: TargetFile,
+    },
+    /// Check all the configuration layers for errors
+    Validate,
+    /// Open the configuration file in `$VISUAL` or `$EDITOR`, and validate it afterwards
+    Edit {
+        #[command(flatten)]
+        target: TargetFile,
+    },
+    /// Upgrade the configuration file to the current schema version, keeping a backup of the original
+    Migrate {
+        #[command(flatten)]
+        target: TargetFile,
+    },
+    /// Print the path of the configuration file
+    Path {
+        #[command(flatten)]
+        target: TargetFile,
+    },
+}
+
+/// Selects the configuration file modified by the command.
+#[derive(Debug, Args)]
+pub struct TargetFile {
+    #[arg(long)]
+    /// Use the project configuration file (`.llama-mgr.toml`) instead of the user one
+    project: bool,
+}
+
+impl TargetFile {
+    /// Returns the path of the selected file. If there's no project configuration file,
+    /// the path of a new one in the current directory is returned.
+    fn path(&self, sources: &ConfigSources) -> PathBuf {
+        if !self.project {
+            return sources.user.clone();
+        }
+
+        sources
+            .project()
+            .unwrap_or_else(|| sources.project_search_start.join(PROJECT_CONFIG_FILE_NAME))
+    }
+}
+
+pub fn run(args: ConfigCommand, sources: &ConfigSources, profile_name: Option<&str>) -> Result<()> {
+    match args.action {
+        ConfigAction::Show { origins: true, .. } => {
+            show_origins(&layers::load(sources)?, profile_name);
+            Ok(())
+        }
+        ConfigAction::Show { resolved, .. } => {
+            show(&layers::load(sources)?.config, profile_name, resolved)
+        }
+        ConfigAction::Get { key } => get(&layers::load(sources)?, &key),
+        ConfigAction::Set { key, value, target } => set(&target.path(sources), &key, &value),
+        ConfigAction::Unset { key, target } => unset(&target.path(sources), &key),
+        ConfigAction::Validate => validate(sources),
+        ConfigAction::Edit { target } => edit(&target.path(sources)),
+        ConfigAction::Migrate { target } => migrate(&target.path(sources)),
+        ConfigAction::Path { target } => {
+            println!("{}", target.path(sources).display());
+            Ok(())
+        }
+    }
+}
+
+/// Prints the effective value of a key. Strings are printed without quotes.
+fn get(loaded: &LoadedConfig, key: &str) -> Result<()> {
+    find_kind(key)?;
+
+    let segments: Vec<&str> = key.split('.').collect();
+    let (last, parents) = segments
+        .split_last()
+        .expect("split always returns a segment");
+
+    let value = parents
+        .iter()
+        .try_fold(&loaded.table, |table, segment| match table.get(*segment) {
+            Some(Value::Table(nested)) => Some(nested),
+            _ => None,
+        })
+        .and_then(|table| table.get(*last))
+        .ok_or_else(|| {
+            RuntimeError::new(format!("Key `{}` is not set", key), exitcode::CONFIG as u8)
+        })?;
+
+    match value {
+        Value::String(value) => println!("{}", value),
+        value => println!("{}", value),
+    }
+
+    Ok(())
+}
+
+/// Sets the value of a key in the configuration file. Strings are accepted without quotes,
+/// other values are parsed as TOML.
+fn set(path: &Path, key: &str, value: &str) -> Result<()> {
+    find_kind(key)?;
+    let value = layers::parse_value(key, value);
+
+    let value: toml_edit::Value = value.to_string().parse().map_err(|e| {
+        RuntimeError::new(
+            format!("Failed to convert value of `{}`: {}", key, e),
+            exitcode::SOFTWARE as u8,
+        )
+    })?;
+
+    let mut document = read_file(path)?;
+    let segments: Vec<&str> = key.split('.').collect();
+    let (last, parents) = segments
+        .split_last()
+        .expect("split always returns a segment");
+
+    let mut current: &mut dyn TableLike = document.as_table_mut();
+    for segment in parents {
+        let entry = current.entry(segment).or_insert_with(|| {
+            // Parent tables are not written as empty `[section]` headers
+            let mut table = toml_edit::Table::new();
+            table.set_implicit(true);
+            Item::Table(table)
+        });
+        current = entry.as_table_like_mut().ok_or_else(|| {
+            RuntimeError::new(
+                format!("Cannot set `{}`, `{}` is not a table", key, segment),
+                exitcode::CONFIG as u8,
+            )
+        })?;
+    }
+
+    // Existing value is replaced in place, keeping its comments
+    match current.get_mut(last) {
+        Some(Item::Value(existing)) => {
+            let decor = existing.decor().clone();
+            *existing = value;
+            *existing.decor_mut() = decor;
+        }
+        _ => {
+            current.insert(last, Item::Value(value));
+        }
+    }
+
+    write_file(path, &document)?;
+    log::info!("Set `{}` in {}", key, path.display());
+    Ok(())
+}
+
+/// Removes a key from the configuration file.
+fn unset(path: &Path, key: &str) -> Result<()> {
+    find_kind(key)?;
+
+    let mut document = read_file(path)?;
+    let segments: Vec<&str> = key.split('.').collect();
+    let (last, parents) = segments
+        .split_last()
+        .expect("split always returns a segment");
+
+    let removed = parents
+        .iter()
+        .try_fold(
+            document.as_table_mut() as &mut dyn TableLike,
+            |table, segment| table.get_mut(segment)?.as_table_like_mut(),
+        )
+        .and_then(|table| table.remove(last));
+
+    if removed.is_none() {
+        return Err(RuntimeError::new(
+            format!("Key `{}` is not set in {}", key, path.display()),
+            exitcode::CONFIG as u8,
+        ));
+    }
+
+    write_file(path, &document)?;
+    log::info!("Removed `{}` from {}", key, path.display());
+    Ok(())
+}
+
+/// Prints all the problems found in the configuration layers.
+fn validate(sources: &ConfigSources) -> Result<()> {
+    for (path, version) in validate::outdated_files(sources) {
+        println!(
+            "{} uses schema version {}, run `llama-mgr config migrate` to upgrade it",
+            path.display(),
+            version
+        );
+    }
+
+    let diagnostics = validate::validate(sources);
+    if diagnostics.is_empty() {
+        println!("Configuration is valid");
+        return Ok(());
+    }
+
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
+
+    Err(RuntimeError::new(
+        format!(
+            "Found {} problem(s) in the configuration",
+            diagnostics.len()
+        ),
+        exitcode::CONFIG as u8,
+    ))
+}
+
+/// Opens the file in the editor until it's valid, or the user gives up.
+fn edit(path: &Path) -> Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| DEFAULT_EDITOR.to_string());
+    // Editor may be set with its arguments, like `code --wait`
+    let mut editor_args = editor.split_whitespace();
+    let editor_path = editor_args.next().unwrap_or(DEFAULT_EDITOR);
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    loop {
+        let status = Command::new(editor_path)
+            .args(editor_args.clone())
+            .arg(path)
+            .status()
+            .map_err(|e| {
+                RuntimeError::new(
+                    format!("Failed to run editor '{}': {}", editor, e),
+                    exitcode::UNAVAILABLE as u8,
+                )
+            })?;
+
+        if !status.success() {
+            return Err(RuntimeError::new(
+                format!("Editor '{}' failed with status: {}", editor, status),
+                exitcode::SOFTWARE as u8,
+            ));
+        }
+
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let diagnostics = validate::validate_file(path);
+        if diagnostics.is_empty() {
+            return Ok(());
+        }
+
+        for diagnostic in &diagnostics {
+            println!("{}", diagnostic);
+        }
+
+        if !confirm("Configuration file has errors. Edit it again?")? {
+            return Err(RuntimeError::new(
+                format!("Configuration file {} has errors", path.display()),
+                exitcode::CONFIG as u8,
+            ));
+        }
+    }
+}
+
+/// Upgrades the file to the current schema version. The original file is copied
+/// next to it, with the schema version and date in its name.
+fn migrate(path: &Path) -> Result<()> {
+    if !path.is_file() {
+        return Err(RuntimeError::new(
+            format!("Configuration file {} does not exist", path.display()),
+            exitcode::NOINPUT as u8,
+        ));
+    }
+
+    let mut document = read_document(path)?;
+    let version = migrate::migrate_document(&mut document)?;
+    if version == migrate::CURRENT_SCHEMA_VERSION {
+        println!(
+            "{} already uses the current schema version ({})",
+            path.display(),
+            version
+        );
+        return Ok(());
+    }
+
+    let backup_path = PathBuf::from(format!(
+        "{}.v{}-{}.bak",
+        path.display(),
+        version,
+        Utc::now().format("%Y%m%d-%H%M%S")
+    ));
+    std::fs::copy(path, &backup_path)?;
+    write_file(path, &document)?;
+
+    println!(
+        "Migrated {} from schema version {} to {}, original file has been saved as {}",
+        path.display(),
+        version,
+        migrate::CURRENT_SCHEMA_VERSION,
+        backup_path.display()
+    );
+    Ok(())
+}
+
+/// Returns the kind of the key, or an error if the key is not a part of configuration schema.
+fn find_kind(key: &str) -> Result<&'static Kind> {
+    SCHEMA.find(key).ok_or_else(|| {
+        RuntimeError::new(
+            format!("Unknown configuration key `{}`", key),
+            exitcode::USAGE as u8,
+        )
+    })
+}
+
+/// Reads the file modified by the command. Files using older schema versions must be
+/// migrated first, as writing them back would migrate them without a backup.
+fn read_file(path: &Path) -> Result<DocumentMut> {
+    if !path.exists() {
+        let mut document = DocumentMut::new();
+        document[migrate::SCHEMA_VERSION_KEY] =
+            toml_edit::value(i64::from(migrate::CURRENT_SCHEMA_VERSION));
+        return Ok(document);
+    }
+
+    let document = read_document(path)?;
+    let version = migrate::document_schema_version(&document)?;
+    if version < migrate::CURRENT_SCHEMA_VERSION {
+        return Err(RuntimeError::new(
+            format!(
+                "Configuration file {} uses schema version {}, run `llama-mgr config migrate` first",
+                path.display(),
+                version
+            ),
+            exitcode::CONFIG as u8,
+        ));
+    }
+
+    Ok(document)
+}
+
+/// Reads the file as TOML document, which keeps its comments and formatting when modified.
+fn read_document(path: &Path) -> Result<DocumentMut> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        RuntimeError::new(
+            format!("Failed to read config file {}: {}", path.display(), e),
+            exitcode::NOINPUT as u8,
+        )
+    })?;
+
+    content.parse().map_err(|e| {
+        RuntimeError::new(
+            format!("Failed to parse config file {}: {}", path.display(), e),
+            exitcode::CONFIG as u8,
+        )
+    })
+}
+
+/// Writes the file, if it passes the schema validation.
+fn write_file(path: &Path, document: &DocumentMut) -> Result<()> {
+    let content = document.to_string();
+    let problems = validate::check_document(&content);
+    if let Some((_, message)) = problems.first() {
+        return Err(RuntimeError::new(
+            format!("Invalid configuration: {}", message),
+            exitcode::CONFIG as u8,
+        ));
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+/// Prints all the values (or the values of selected profile) with their origins.
+fn show_origins(loaded: &LoadedConfig, profile_name: Option<&str>) {
+    let prefix = profile_name.map(|name| format!("profiles.{}.", name));
+
+    for (key, value, origin) in loaded.values_with_origins() {
+        if prefix
+            .as_ref()
+            .is_some_and(|prefix| !key.starts_with(prefix))
+        {
+            continue;
+        }
+
+        println!("{} = {}  # {}", key, value, origin);
+    }
+}
+
+fn show(config: &Config, profile_name: Option<&str>, resolved: bool) -> Result<()> {
+    let content = match (profile_name, resolved) {
+        (None, false) => to_toml(config)?,
+        (profile_name, resolved) => {
+            let name = profile_name.unwrap_or(&config.config.default_profile);
+            let profile = if resolved {
+                config.resolve_profile(name)?
+            } else {
+                config.get_profile(Some(name)).cloned().ok_or_else(|| {
+                    RuntimeError::new(
+                        format!("Profile '{}' not found in configuration", name),
+                        exitcode::CONFIG as u8,
+                    )
+                })?
+            };
+
+            profile_to_toml(name, &profile)?
+        }
+    };
+
+    print!("{}", content);
+    Ok(())
+}
+
+/// Serializes the profile as `[profiles.<name>]` section.
+fn profile_to_toml(name: &str, profile: &Profile) -> Result<String> {
+    let profiles = BTreeMap::from([(name, profile)]);
+    to_toml(&BTreeMap::from([("profiles", profiles)]))
+}
+
+fn to_toml(value: &impl Serialize) -> Result<String> {
+    toml::to_string(value).map_err(|e| {
+        RuntimeError::new(
+            format!("Failed to serialize configuration: {}", e),
+            exitcode::SOFTWARE as u8,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_set_and_unset() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("nested").join("config.toml");
+
+        set(&path, "profiles.cpu.branch", "123").unwrap();
+        set(&path, "profiles.cpu.cmake_args", r#"["-DGGML_CPU=ON"]"#).unwrap();
+        set(&path, "config.install_retention", "5").unwrap();
+
+        let table = read_file(&path).unwrap();
+        assert_eq!(table["profiles"]["cpu"]["branch"].as_str(), Some("123"));
+        assert_eq!(
+            table["profiles"]["cpu"]["cmake_args"].to_string().trim(),
+            r#"["-DGGML_CPU=ON"]"#
+        );
+        assert_eq!(table["config"]["install_retention"].as_integer(), Some(5));
+
+        assert!(set(&path, "profiles.cpu.unknown", "value").is_err());
+        assert!(set(&path, "config.install_retention", "many").is_err());
+        assert!(set(&path, "profiles.cpu.submodules", "always").is_err());
+
+        unset(&path, "profiles.cpu.branch").unwrap();
+        assert_eq!(
+            read_file(&path).unwrap()[migrate::SCHEMA_VERSION_KEY].as_integer(),
+            Some(migrate::CURRENT_SCHEMA_VERSION.into())
+        );
+        assert!(unset(&path, "profiles.cpu.branch").is_err());
+        assert!(
+            read_file(&path).unwrap()["profiles"]["cpu"]
+                .get("branch")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_set_keeps_comments() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            r#"schema_version = 2
+
+# Paths are relative to this file
+[paths]
+models_dir = "./models" # shared with other tools
+llama_dir = "./llama"
+
+[profiles.cpu]
+branch = "master"
+"#,
+        )
+        .unwrap();
+
+        set(&path, "paths.models_dir", "/data/models").unwrap();
+        set(&path, "profiles.vulkan.branch", "dev").unwrap();
+        unset(&path, "profiles.cpu.branch").unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            r#"schema_version = 2
+
+# Paths are relative to this file
+[paths]
+models_dir = "/data/models" # shared with other tools
+llama_dir = "./llama"
+
+[profiles.cpu]
+
+[profiles.vulkan]
+branch = "dev"
+"#
+        );
+    }
+
+    #[test]
+    fn test_migrate_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("config.toml");
+        let original = "[profile.cpu]\ncmake_args = [\"-DGGML_CPU=ON\"]\n";
+        std::fs::write(&path, original).unwrap();
+
+        assert!(set(&path, "config.install_retention", "5").is_err());
+        migrate(&path).unwrap();
+
+        let table = read_file(&path).unwrap();
+        assert!(table["profiles"].get("cpu").is_some());
+
+        let backups: Vec<_> = std::fs::read_dir(temp_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|entry| entry != &path)
+            .collect();
+        assert_eq!(backups.len(), 1);
+        assert!(backups[0].to_string_lossy().contains("config.toml.v1-"));
+        assert_eq!(std::fs::read_to_string(&backups[0]).unwrap(), original);
+
+        // Migrating current file does nothing
+        migrate(&path).unwrap();
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 2);
+    }
+}
@@ -1,27 +1,179 @@
-use clap::Parser;
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
 
 use crate::{
-    commands::Result,
+    commands::{Result, RuntimeError},
     config::{Config, Profile},
+    external_tools::uv::{OutputMode, VirtualEnvironment},
+    instance::Instance,
+    models::{ModelIndex, huggingface},
 };
 
+const CONVERT_SCRIPT_NAME: &str = "convert_hf_to_gguf.py";
+
 #[derive(Debug, Parser)]
 pub struct ConvertCommand {
     #[arg(long, short)]
-    /// Path to the directory with HuggingFace model
+    /// Checkpoint imported with `models import`, Hugging Face repository ID from the local cache,
+    /// or path to the directory with Hugging Face model
     pub input: String,
 
     #[arg(long, short)]
-    /// Path to the output GGUF file
-    pub output: String,
+    /// Path to the output GGUF file, `<models_dir>/<name>-<outtype>.gguf` by default
+    pub output: Option<PathBuf>,
+
+    #[arg(long, value_enum, default_value_t = OutType::F16)]
+    /// Data type of the output tensors
+    pub outtype: OutType,
+
+    #[arg(long)]
+    /// Convert only the vocabulary, without the model weights
+    pub vocab_only: bool,
+
+    #[arg(last = true)]
+    /// Additional arguments passed to `convert_hf_to_gguf.py`
+    pub extra_args: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutType {
+    F16,
+    Bf16,
+    #[value(name = "q8_0")]
+    Q8_0,
+}
+
+impl OutType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            OutType::F16 => "f16",
+            OutType::Bf16 => "bf16",
+            OutType::Q8_0 => "q8_0",
+        }
+    }
 }
 
-pub fn run(args: ConvertCommand, config: &Config, profile: &Profile) -> Result<()> {
+pub fn run(
+    args: ConvertCommand,
+    config: &Config,
+    _profile: &Profile,
+    instance: &Instance,
+) -> Result<()> {
+    let models_dir = &config.paths.models_dir;
+    let index = ModelIndex::refresh(models_dir)?;
+    let (name, input_path) = match index.find_checkpoint(&args.input) {
+        Some(checkpoint) => (checkpoint.name.clone(), checkpoint.path.clone()),
+        None => {
+            let path = huggingface::locate_checkpoint(
+                &args.input,
+                huggingface::hub_cache_dir().as_deref(),
+                None,
+            )?;
+            let checkpoint = huggingface::inspect_checkpoint(&path, None)?;
+            (checkpoint.name, path)
+        }
+    };
+
+    let output_path = match &args.output {
+        Some(output) => std::path::absolute(output)?,
+        None => std::path::absolute(models_dir.join(default_output_name(&name, args.outtype)))?,
+    };
+
+    let script_path = instance.repo_dir().join(CONVERT_SCRIPT_NAME);
+    if !script_path.exists() {
+        return Err(RuntimeError::new(
+            format!(
+                "{} not found in instance '{}', run `llama-mgr install` first",
+                CONVERT_SCRIPT_NAME, instance.name
+            ),
+            exitcode::UNAVAILABLE as u8,
+        ));
+    }
+
+    let venv = VirtualEnvironment::open(instance.venv_dir()).map_err(|e| {
+        RuntimeError::new(
+            format!(
+                "Python environment of instance '{}' is not set up, run `llama-mgr install` without `--ignore-python`: {}",
+                instance.name, e
+            ),
+            exitcode::UNAVAILABLE as u8,
+        )
+    })?;
+
+    if let Some(parent) = output_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // Output is written under a temporary name, so incomplete files never get indexed
+    let partial_path = output_path.with_extension("gguf.partial");
+
     log::info!(
-        "Convert command called with args: {:?}, config: {:?}, profile: {:?}",
-        args,
-        config,
-        profile
+        "Converting {} to {} ({})",
+        input_path.display(),
+        output_path.display(),
+        args.outtype.as_str()
     );
+
+    let mut script_args = vec![
+        input_path.into_os_string(),
+        "--outfile".into(),
+        partial_path.clone().into_os_string(),
+        "--outtype".into(),
+        args.outtype.as_str().into(),
+    ];
+    if args.vocab_only {
+        script_args.push("--vocab-only".into());
+    }
+    script_args.extend(args.extra_args.into_iter().map(Into::into));
+
+    // Output is streamed, so the progress of the conversion is shown as it goes
+    let output = venv
+        .run_python_script(&script_path, script_args, OutputMode::Stream)
+        .map_err(|e| {
+            RuntimeError::new(
+                format!("Failed to run {}: {}", CONVERT_SCRIPT_NAME, e),
+                exitcode::SOFTWARE as u8,
+            )
+        })?;
+    if !output.status.success() {
+        let _ = std::fs::remove_file(&partial_path);
+        return Err(RuntimeError::new(
+            format!("{} failed with {}", CONVERT_SCRIPT_NAME, output.status),
+            exitcode::SOFTWARE as u8,
+        ));
+    }
+
+    std::fs::rename(&partial_path, &output_path)?;
+    println!("Converted {} to {}", name, output_path.display());
+    if let Some(model) = ModelIndex::register(models_dir, &output_path)? {
+        println!(
+            "Registered model {}",
+            model.reference().unwrap_or_else(|| model.name())
+        );
+    }
     Ok(())
 }
+
+/// Default name of the output file, like `qwen3-8b-f16.gguf` for `Qwen/Qwen3-8B` checkpoint.
+fn default_output_name(name: &str, outtype: OutType) -> String {
+    let base_name = name.rsplit('/').next().unwrap_or(name).to_lowercase();
+    format!("{}-{}.gguf", base_name, outtype.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_output_name() {
+        assert_eq!(
+            default_output_name("Qwen/Qwen3-8B", OutType::Bf16),
+            "qwen3-8b-bf16.gguf"
+        );
+        assert_eq!(
+            default_output_name("local-model", OutType::Q8_0),
+            "local-model-q8_0.gguf"
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::{
+    commands::{Result, RuntimeError},
+    config::{Config, Profile},
+    external_tools::llama_imatrix::LlamaImatrix,
+    instance::Instance,
+    models::{imatrix_path, is_up_to_date, resolve_model_path},
+};
+
+const IMATRIX_BINARY_NAME: &str = "llama-imatrix";
+
+#[derive(Debug, Parser)]
+pub struct ImatrixCommand {
+    #[arg(long, short)]
+    /// Model to compute the importance matrix for - path, model reference (`qwen3:8b-bf16`), alias or name
+    pub model: String,
+
+    #[arg(long = "calibration", short = 'f')]
+    /// Calibration text file
+    pub calibration: PathBuf,
+
+    #[arg(long, short)]
+    /// Path to the output file, `<model>.imatrix` next to the model by default,
+    /// where `quantize` picks it up automatically
+    pub output: Option<PathBuf>,
+
+    #[arg(long)]
+    /// Compute the importance matrix even if the cached one is newer than the model and calibration file
+    pub force: bool,
+
+    #[arg(last = true)]
+    /// Additional arguments passed to `llama-imatrix`, like `--n-gpu-layers 99`
+    pub extra_args: Vec<String>,
+}
+
+pub fn run(
+    args: ImatrixCommand,
+    config: &Config,
+    _profile: &Profile,
+    instance: &Instance,
+) -> Result<()> {
+    let tool: LlamaImatrix = instance.tool(IMATRIX_BINARY_NAME)?;
+
+    if !args.calibration.is_file() {
+        return Err(RuntimeError::new(
+            format!(
+                "Calibration file {} does not exist",
+                args.calibration.display()
+            ),
+            exitcode::NOINPUT as u8,
+        ));
+    }
+
+    let model_path = resolve_model_path(config, &args.model)?;
+    let output_path = args
+        .output
+        .clone()
+        .unwrap_or_else(|| imatrix_path(&model_path));
+
+    if !args.force && is_up_to_date(&output_path, &[&model_path, &args.calibration]) {
+        println!("Importance matrix {} is up to date", output_path.display());
+        return Ok(());
+    }
+
+    if let Some(parent) = output_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // Matrix is written under a temporary name, so an interrupted run never replaces the cached one
+    let partial_path = output_path.with_extension("imatrix.partial");
+    log::info!(
+        "Computing importance matrix of {} from {}",
+        model_path.display(),
+        args.calibration.display()
+    );
+
+    let status = tool
+        .imatrix_command(
+            &model_path,
+            &args.calibration,
+            &partial_path,
+            &args.extra_args,
+        )
+        .status()
+        .map_err(|e| {
+            RuntimeError::new(
+                format!("Failed to run {} - {}", IMATRIX_BINARY_NAME, e),
+                exitcode::SOFTWARE as u8,
+            )
+        })?;
+    if !status.success() {
+        let _ = std::fs::remove_file(&partial_path);
+        return Err(RuntimeError::new(
+            format!("{} failed with {}", IMATRIX_BINARY_NAME, status),
+            exitcode::SOFTWARE as u8,
+        ));
+    }
+
+    std::fs::rename(&partial_path, &output_path)?;
+    println!("Importance matrix saved to {}", output_path.display());
+    Ok(())
+}
@@ -1,13 +1,29 @@
-use std::{path::Path, process::ExitCode};
+use std::{
+    collections::BTreeSet,
+    ffi::OsString,
+    fmt::Display,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
 
+use chrono::Utc;
 use clap::Parser;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 use crate::{
     commands::{Result, RuntimeError},
-    config::{Config, Profile},
+    config::{Config, Profile, SubmodulePolicy},
     external_tools::{
-        ExternalTool, cmake::CMake, git::Git, ninja::Ninja, uv::Uv, version::Version,
+        ExternalTool,
+        cmake::CMake,
+        git::{Git, GitRef},
+        ninja::Ninja,
+        uv::{OutputMode, Uv, VirtualEnvironment},
+        version::Version,
     },
+    instance::{InstallRecord, Instance, InstanceMetadata},
+    models::{find_files, store::hex_digest},
 };
 
 const RECOMMENDED_PYTHON_VERSION: Version = Version {
@@ -16,6 +32,13 @@ const RECOMMENDED_PYTHON_VERSION: Version = Version {
     patch: None,
 };
 
+const REQUIREMENTS_DIR_NAME: &str = "requirements";
+const GGUF_PACKAGE_DIR_NAME: &str = "gguf-py";
+const CONVERT_SCRIPT_NAME: &str = "convert_hf_to_gguf.py";
+
+const DEFAULT_CMAKE_GENERATOR: &str = "Ninja";
+const CMAKE_BUILD_CONFIG: &str = "Release";
+
 #[derive(Debug, Parser)]
 pub struct InstallCommand {
     #[arg(long)]
@@ -25,10 +48,269 @@ pub struct InstallCommand {
     #[arg(long, short = 'j')]
     /// Specify the amount of threads to use for building.
     pub parallel: Option<usize>,
+
+    #[arg(long = "ref")]
+    /// Commit SHA, tag or build number (`bNNNN`) to check out, overrides the profile setting
+    pub git_ref: Option<String>,
+
+    #[arg(long, conflicts_with_all = ["ignore_python", "parallel", "git_ref"])]
+    /// Switch back to the previous build instead of installing a new one
+    pub rollback: bool,
+}
+
+/// Single step of the installation pipeline.
+/// Stages are executed in the order of declaration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallStage {
+    Pull,
+    Prerequisites,
+    Configure,
+    Build,
+    Install,
+    Python,
+}
+
+impl InstallStage {
+    pub const ALL: [InstallStage; 6] = [
+        InstallStage::Pull,
+        InstallStage::Prerequisites,
+        InstallStage::Configure,
+        InstallStage::Build,
+        InstallStage::Install,
+        InstallStage::Python,
+    ];
+
+    /// Returns the list of stages that should be executed for given arguments.
+    fn for_args(args: &InstallCommand) -> Vec<InstallStage> {
+        Self::ALL
+            .into_iter()
+            .filter(|stage| !(args.ignore_python && *stage == InstallStage::Python))
+            .collect()
+    }
+}
+
+impl Display for InstallStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            InstallStage::Pull => "pull",
+            InstallStage::Prerequisites => "prerequisites",
+            InstallStage::Configure => "configure",
+            InstallStage::Build => "build",
+            InstallStage::Install => "install",
+            InstallStage::Python => "python",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Progress of the installation pipeline.
+/// Stored in `$LLAMA_INSTANCE_DIR`, so an interrupted installation can be resumed.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct InstallState {
+    pub completed_stages: Vec<InstallStage>,
+    /// ID of the build that is being installed, assigned during configuration.
+    pub pending_install: Option<String>,
+    /// Commit, tag or build number the installation has been started with.
+    #[serde(default)]
+    pub requested_ref: Option<String>,
+}
+
+impl InstallState {
+    /// Loads the state from file, or returns an empty state if the file does not exist.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        if !path.as_ref().exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(&path).map_err(|e| {
+            RuntimeError::new(
+                format!("Failed to read installation state: {}", e),
+                exitcode::IOERR as u8,
+            )
+        })?;
+
+        toml::from_str(&content).map_err(|e| {
+            RuntimeError::new(
+                format!(
+                    "Installation state file {} is corrupted: {}",
+                    path.as_ref().display(),
+                    e
+                ),
+                exitcode::DATAERR as u8,
+            )
+        })
+    }
+
+    fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let content = toml::to_string(self).map_err(|e| {
+            RuntimeError::new(
+                format!("Failed to serialize installation state: {}", e),
+                exitcode::SOFTWARE as u8,
+            )
+        })?;
+
+        std::fs::write(&path, content).map_err(|e| {
+            RuntimeError::new(
+                format!("Failed to write installation state: {}", e),
+                exitcode::IOERR as u8,
+            )
+        })
+    }
+
+    pub fn is_completed(&self, stage: InstallStage) -> bool {
+        self.completed_stages.contains(&stage)
+    }
+
+    fn mark_completed(&mut self, stage: InstallStage) {
+        if !self.is_completed(stage) {
+            self.completed_stages.push(stage);
+        }
+    }
+
+    /// Returns `true` if all of the provided stages have been completed.
+    pub fn is_finished(&self, stages: &[InstallStage]) -> bool {
+        stages.iter().all(|stage| self.is_completed(*stage))
+    }
+
+    /// Returns the first of the provided stages that has not been completed yet.
+    pub fn next_stage(&self, stages: &[InstallStage]) -> Option<InstallStage> {
+        stages
+            .iter()
+            .find(|stage| !self.is_completed(**stage))
+            .copied()
+    }
+}
+
+pub fn run(
+    args: InstallCommand,
+    config: &Config,
+    profile_name: &str,
+    profile: &Profile,
+    instance: &Instance,
+) -> Result<()> {
+    if args.rollback {
+        return rollback(instance);
+    }
+
+    log::info!("Installing llama.cpp instance '{}'...", instance.name);
+
+    std::fs::create_dir_all(&instance.path).map_err(|e| {
+        RuntimeError::new(
+            format!(
+                "Failed to create instance directory {}: {}",
+                instance.path.display(),
+                e
+            ),
+            exitcode::CANTCREAT as u8,
+        )
+    })?;
+
+    let mut metadata = instance.load_metadata()?.unwrap_or_default();
+    metadata.profile = profile_name.to_string();
+    instance.save_metadata(&metadata)?;
+
+    let git_ref: Option<GitRef> = args
+        .git_ref
+        .as_deref()
+        .or(profile.git_ref.as_deref())
+        .map(str::parse)
+        .transpose()?;
+
+    let stages = InstallStage::for_args(&args);
+
+    run_stages(
+        &stages,
+        instance.install_state_path(),
+        git_ref.as_ref(),
+        |stage, state| run_stage(stage, state, &args, profile, git_ref.as_ref(), instance),
+    )?;
+
+    prune_installs(instance, config.config.install_retention)?;
+
+    log::info!(
+        "llama.cpp has been installed in {}",
+        instance.install_dir().display()
+    );
+    Ok(())
 }
 
-pub fn run(_args: InstallCommand, _config: &Config, _profile: &Profile) -> Result<()> {
-    todo!()
+/// Runs the stages that have not been completed yet, saving the progress after each one.
+/// If all the stages have been completed previously, or the interrupted installation has been
+/// started with other git ref, the pipeline is started from scratch.
+fn run_stages(
+    stages: &[InstallStage],
+    state_path: impl AsRef<Path>,
+    git_ref: Option<&GitRef>,
+    mut run_stage: impl FnMut(InstallStage, &mut InstallState) -> Result<()>,
+) -> Result<()> {
+    let mut state = InstallState::load(&state_path)?;
+    let requested_ref = git_ref.map(GitRef::to_string);
+
+    if state.is_finished(stages) {
+        state = InstallState::default();
+    } else if state.requested_ref != requested_ref && !state.completed_stages.is_empty() {
+        log::info!(
+            "Interrupted installation has been started with ref {}, starting over...",
+            state.requested_ref.as_deref().unwrap_or("latest")
+        );
+        state = InstallState::default();
+    } else if !state.completed_stages.is_empty() {
+        log::info!("Resuming previously interrupted installation...");
+    }
+    state.requested_ref = requested_ref;
+
+    for stage in stages {
+        if state.is_completed(*stage) {
+            log::info!("Stage '{}' has already been completed, skipping.", stage);
+            continue;
+        }
+
+        log::info!("Running stage '{}'...", stage);
+        run_stage(*stage, &mut state)?;
+
+        state.mark_completed(*stage);
+        state.save(&state_path)?;
+    }
+
+    Ok(())
+}
+
+fn run_stage(
+    stage: InstallStage,
+    state: &mut InstallState,
+    args: &InstallCommand,
+    profile: &Profile,
+    git_ref: Option<&GitRef>,
+    instance: &Instance,
+) -> Result<()> {
+    match stage {
+        InstallStage::Pull => {
+            let git = get_git()?;
+            pull_or_update_source_code(&git, profile, git_ref, instance.repo_dir())
+        }
+        InstallStage::Prerequisites => get_prerequisites(args, profile).map(|_| ()),
+        InstallStage::Configure => {
+            // Each build gets a new install directory, so the current one stays untouched
+            // until the new build is fully installed
+            let install_id = new_install_id(instance)?;
+            generate_cmake_build_files(profile, instance, &install_id)?;
+            state.pending_install = Some(install_id);
+            Ok(())
+        }
+        InstallStage::Build => build_llama_cpp(args, instance),
+        InstallStage::Install => {
+            let install_id = match &state.pending_install {
+                Some(id) => id.clone(),
+                None => new_install_id(instance)?,
+            };
+            install_llama_cpp(instance, &install_id)?;
+            record_installed_build(instance, &install_id)?;
+            state.pending_install = None;
+            Ok(())
+        }
+        InstallStage::Python => setup_python_environment(instance),
+    }
 }
 
 fn get_git() -> Result<Git> {
@@ -106,11 +388,27 @@ fn install_python_with_uv(uv: &Uv) -> Result<()> {
         })
 }
 
-fn get_prerequisites(args: &InstallCommand) -> Result<(CMake, Ninja, Option<Uv>)> {
+/// Returns CMake generator used by the profile.
+fn cmake_generator(profile: &Profile) -> &str {
+    profile
+        .cmake_generator
+        .as_deref()
+        .unwrap_or(DEFAULT_CMAKE_GENERATOR)
+}
+
+fn get_prerequisites(
+    args: &InstallCommand,
+    profile: &Profile,
+) -> Result<(CMake, Option<Ninja>, Option<Uv>)> {
     log::info!("Verifying prerequisites presence...");
 
     let cmake = get_cmake()?;
-    let ninja = get_ninja()?;
+    // Both `Ninja` and `Ninja Multi-Config` generators need it, others bring their own build tool
+    let ninja = if cmake_generator(profile).starts_with("Ninja") {
+        Some(get_ninja()?)
+    } else {
+        None
+    };
 
     let uv = if args.ignore_python {
         log::info!("Skipping Python setup as requested.");
@@ -127,64 +425,830 @@ fn get_prerequisites(args: &InstallCommand) -> Result<(CMake, Ninja, Option<Uv>)
 
 fn pull_or_update_source_code(
     git: &Git,
-    repo_url: &str,
-    branch: Option<&str>,
+    profile: &Profile,
+    git_ref: Option<&GitRef>,
     target_path: impl AsRef<Path>,
 ) -> Result<()> {
+    let repo_url = profile.repo_url();
+    let branch = profile.branch();
+
     if target_path.as_ref().exists() {
         log::info!(
             "Repository already exists at {}. Updating...",
             target_path.as_ref().display()
         );
-        git.pull(&target_path).map_err(|e| RuntimeError {
-            message: format!("Failed to pull repository: {}", e),
-            exit_code: ExitCode::from(exitcode::IOERR as u8),
-        })?;
+
+        // Repository URL and branch may have been changed in the profile since last update
+        git.set_remote(&target_path, "origin", repo_url)
+            .map_err(|e| RuntimeError {
+                message: format!("Failed to set repository URL: {}", e),
+                exit_code: ExitCode::from(exitcode::IOERR as u8),
+            })?;
+
+        if git_ref.is_none() {
+            git.fetch(&target_path, "origin")
+                .map_err(|e| RuntimeError {
+                    message: format!("Failed to fetch repository: {}", e),
+                    exit_code: ExitCode::from(exitcode::IOERR as u8),
+                })?;
+
+            // Previously pinned repository is in detached HEAD state,
+            // so the branch has to be checked out before pulling
+            git.checkout_branch(&target_path, branch)
+                .map_err(|e| RuntimeError {
+                    message: format!("Failed to check out branch {}: {}", branch, e),
+                    exit_code: ExitCode::from(exitcode::IOERR as u8),
+                })?;
+
+            git.pull(&target_path).map_err(|e| RuntimeError {
+                message: format!("Failed to pull repository: {}", e),
+                exit_code: ExitCode::from(exitcode::IOERR as u8),
+            })?;
+        }
     } else {
         log::info!(
-            "Cloning repository from {} to {}",
+            "Cloning repository from {} (branch {}) to {}",
             repo_url,
+            branch,
             target_path.as_ref().display()
         );
-        git.clone(
-            &target_path,
-            repo_url,
-            branch.map(|b| b.as_ref() as &std::ffi::OsStr),
-        )
-        .map_err(|e| RuntimeError {
-            message: format!("Failed to clone repository: {}", e),
-            exit_code: ExitCode::from(exitcode::CANTCREAT as u8),
-        })?;
+        git.clone(&target_path, repo_url, Some(branch))
+            .map_err(|e| RuntimeError {
+                message: format!("Failed to clone repository: {}", e),
+                exit_code: ExitCode::from(exitcode::CANTCREAT as u8),
+            })?;
+    }
+
+    for (name, url) in &profile.remotes {
+        log::info!("Fetching remote {} from {}...", name, url);
+        git.set_remote(&target_path, name, url)
+            .and_then(|_| git.fetch(&target_path, name))
+            .map_err(|e| RuntimeError {
+                message: format!("Failed to fetch remote {}: {}", name, e),
+                exit_code: ExitCode::from(exitcode::IOERR as u8),
+            })?;
+    }
+
+    if let Some(git_ref) = git_ref {
+        log::info!("Checking out pinned revision {}...", git_ref);
+        let remotes: Vec<&str> = std::iter::once("origin")
+            .chain(profile.remotes.keys().map(String::as_str))
+            .collect();
+        git.checkout_ref(&target_path, git_ref, &remotes)
+            .map_err(|e| RuntimeError {
+                message: format!("Failed to check out {}: {}", git_ref, e),
+                exit_code: ExitCode::from(exitcode::DATAERR as u8),
+            })?;
+    }
+
+    if profile.submodules() == SubmodulePolicy::Skip {
+        log::info!("Skipping submodules update as configured in profile.");
+        return Ok(());
     }
 
     // Initialize and update submodules
     log::info!("Updating submodules...");
-    git.update_submodules(&target_path)
-        .map_err(|e| RuntimeError {
-            message: format!("Failed to update submodules: {}", e),
-            exit_code: ExitCode::from(exitcode::IOERR as u8),
-        })?;
+    git.update_submodules(
+        &target_path,
+        profile.submodules() == SubmodulePolicy::Remote,
+    )
+    .map_err(|e| RuntimeError {
+        message: format!("Failed to update submodules: {}", e),
+        exit_code: ExitCode::from(exitcode::IOERR as u8),
+    })?;
 
     Ok(())
 }
 
 fn generate_cmake_build_files(
-    _args: &InstallCommand,
-    _instance_path: impl AsRef<Path>,
+    profile: &Profile,
+    instance: &Instance,
+    install_id: &str,
 ) -> Result<()> {
-    todo!()
+    let cmake = get_cmake()?;
+
+    let source_dir = instance.repo_dir();
+    let build_dir = instance.build_dir();
+    let install_dir = instance.versioned_install_dir(install_id);
+    let generator = PathBuf::from(cmake_generator(profile));
+
+    let cmake_args: Vec<PathBuf> =
+        std::iter::once(format!("-DCMAKE_BUILD_TYPE={}", CMAKE_BUILD_CONFIG))
+            .chain(profile.cmake_args.iter().cloned())
+            .map(PathBuf::from)
+            .collect();
+
+    log::info!("Generating build files in {}...", build_dir.display());
+
+    let status = cmake
+        .generate(
+            &source_dir,
+            &build_dir,
+            &generator,
+            Some(&install_dir),
+            Some(&cmake_args.iter().collect::<Vec<_>>()),
+        )
+        .map_err(|e| {
+            RuntimeError::new(format!("Failed to run CMake: {}", e), exitcode::OSERR as u8)
+        })?;
+
+    if !status.success() {
+        return Err(RuntimeError::new(
+            format!("CMake configuration failed with status: {}", status),
+            exitcode::SOFTWARE as u8,
+        ));
+    }
+
+    Ok(())
 }
 
-fn build_and_install_llama_cpp(
-    _args: &InstallCommand,
-    _instance_path: impl AsRef<Path>,
-) -> Result<()> {
-    todo!()
+fn build_llama_cpp(args: &InstallCommand, instance: &Instance) -> Result<()> {
+    let cmake = get_cmake()?;
+
+    let build_dir = instance.build_dir();
+    let build_args: Vec<PathBuf> = match args.parallel {
+        Some(threads) => vec![
+            PathBuf::from("--parallel"),
+            PathBuf::from(threads.to_string()),
+        ],
+        None => vec![PathBuf::from("--parallel")],
+    };
+
+    log::info!("Building llama.cpp in {}...", build_dir.display());
+
+    let status = cmake
+        .build(
+            &build_dir,
+            &PathBuf::from(CMAKE_BUILD_CONFIG),
+            Some(&build_args.iter().collect::<Vec<_>>()),
+        )
+        .map_err(|e| {
+            RuntimeError::new(format!("Failed to run CMake: {}", e), exitcode::OSERR as u8)
+        })?;
+
+    if !status.success() {
+        return Err(RuntimeError::new(
+            format!("llama.cpp build failed with status: {}", status),
+            exitcode::SOFTWARE as u8,
+        ));
+    }
+
+    Ok(())
+}
+
+fn install_llama_cpp(instance: &Instance, install_id: &str) -> Result<()> {
+    let cmake = get_cmake()?;
+
+    let build_dir = instance.build_dir();
+    let install_dir = instance.versioned_install_dir(install_id);
+    let install_args = [PathBuf::from("--prefix"), install_dir.clone()];
+
+    log::info!("Installing llama.cpp to {}...", install_dir.display());
+
+    let status = cmake
+        .install(
+            &build_dir,
+            &PathBuf::from(CMAKE_BUILD_CONFIG),
+            Some(&install_args.iter().collect::<Vec<_>>()),
+        )
+        .map_err(|e| {
+            RuntimeError::new(format!("Failed to run CMake: {}", e), exitcode::OSERR as u8)
+        })?;
+
+    if !status.success() {
+        return Err(RuntimeError::new(
+            format!("llama.cpp installation failed with status: {}", status),
+            exitcode::SOFTWARE as u8,
+        ));
+    }
+
+    Ok(())
+}
+
+/// Creates an unique, chronologically sortable ID for a new build of checked out commit.
+fn new_install_id(instance: &Instance) -> Result<String> {
+    let git = get_git()?;
+    let commit = git.head_commit(instance.repo_dir())?;
+
+    Ok(format!(
+        "{}-{}",
+        Utc::now().format("%Y%m%d-%H%M%S"),
+        commit.chars().take(10).collect::<String>()
+    ))
+}
+
+/// Saves the installed build in instance metadata and makes it the current one.
+fn record_installed_build(instance: &Instance, install_id: &str) -> Result<()> {
+    let git = get_git()?;
+    let record = InstallRecord {
+        id: install_id.to_string(),
+        commit: git.head_commit(instance.repo_dir())?,
+        built_at: Utc::now(),
+    };
+
+    let mut metadata: InstanceMetadata = instance.load_metadata()?.unwrap_or_default();
+    metadata.installs.retain(|install| install.id != record.id);
+    metadata.installs.push(record.clone());
+    set_current_build(instance, &mut metadata, &record)
 }
 
-fn setup_python_environment(
-    _args: &InstallCommand,
-    _instance_path: impl AsRef<Path>,
+fn set_current_build(
+    instance: &Instance,
+    metadata: &mut InstanceMetadata,
+    record: &InstallRecord,
 ) -> Result<()> {
-    todo!()
+    instance.set_current_install(&record.id)?;
+
+    metadata.commit = Some(record.commit.clone());
+    metadata.built_at = Some(record.built_at);
+    instance.save_metadata(metadata)?;
+
+    log::info!(
+        "Current build of instance '{}' is {} (commit {})",
+        instance.name,
+        record.id,
+        record.commit
+    );
+    Ok(())
+}
+
+/// Switches the instance to the build preceding the current one.
+fn rollback(instance: &Instance) -> Result<()> {
+    let current_id = instance.current_install_id()?.ok_or_else(|| {
+        RuntimeError::new(
+            format!("Instance '{}' has no installed build", instance.name),
+            exitcode::NOINPUT as u8,
+        )
+    })?;
+
+    let mut metadata = instance.load_metadata()?.unwrap_or_default();
+    let previous = find_previous_build(&metadata.installs, &current_id, |id| {
+        instance.versioned_install_dir(id).is_dir()
+    })
+    .cloned()
+    .ok_or_else(|| {
+        RuntimeError::new(
+            format!(
+                "There's no build older than {} to roll back to in instance '{}'",
+                current_id, instance.name
+            ),
+            exitcode::UNAVAILABLE as u8,
+        )
+    })?;
+
+    log::info!("Rolling back from {} to {}...", current_id, previous.id);
+    set_current_build(instance, &mut metadata, &previous)
+}
+
+/// Returns the newest of the builds older than the current one.
+fn find_previous_build<'a>(
+    installs: &'a [InstallRecord],
+    current_id: &str,
+    exists: impl Fn(&str) -> bool,
+) -> Option<&'a InstallRecord> {
+    installs
+        .iter()
+        .filter(|install| install.id.as_str() < current_id && exists(&install.id))
+        .max_by(|a, b| a.id.cmp(&b.id))
+}
+
+/// Removes the oldest builds, keeping `retention` newest ones and the current one.
+fn prune_installs(instance: &Instance, retention: usize) -> Result<()> {
+    let current_id = instance.current_install_id()?;
+    let mut metadata = instance.load_metadata()?.unwrap_or_default();
+
+    let (kept, removed) = split_installs_by_retention(
+        std::mem::take(&mut metadata.installs),
+        current_id.as_deref(),
+        retention,
+    );
+    metadata.installs = kept;
+
+    for install in &removed {
+        let install_dir = instance.versioned_install_dir(&install.id);
+        log::info!("Removing old build {}...", install.id);
+        if install_dir.exists() {
+            std::fs::remove_dir_all(&install_dir).map_err(|e| {
+                RuntimeError::new(
+                    format!(
+                        "Failed to remove old build {}: {}",
+                        install_dir.display(),
+                        e
+                    ),
+                    exitcode::IOERR as u8,
+                )
+            })?;
+        }
+    }
+
+    instance.save_metadata(&metadata)
+}
+
+/// Splits the builds into kept and removed ones. At least one build is always kept.
+fn split_installs_by_retention(
+    mut installs: Vec<InstallRecord>,
+    current_id: Option<&str>,
+    retention: usize,
+) -> (Vec<InstallRecord>, Vec<InstallRecord>) {
+    installs.sort_by(|a, b| b.id.cmp(&a.id));
+
+    let (kept, removed): (Vec<_>, Vec<_>) =
+        installs
+            .into_iter()
+            .enumerate()
+            .partition(|(index, install)| {
+                *index < retention.max(1) || Some(install.id.as_str()) == current_id
+            });
+
+    let mut kept: Vec<InstallRecord> = kept.into_iter().map(|(_, install)| install).collect();
+    kept.reverse();
+
+    (
+        kept,
+        removed.into_iter().map(|(_, install)| install).collect(),
+    )
+}
+
+fn setup_python_environment(instance: &Instance) -> Result<()> {
+    let venv_path = instance.venv_dir();
+    let uv = get_uv()?;
+
+    let venv = if venv_path.join("pyvenv.cfg").exists() {
+        log::info!(
+            "Virtual environment already exists at {}, reusing it.",
+            venv_path.display()
+        );
+        VirtualEnvironment::open(&venv_path)
+    } else {
+        log::info!("Creating virtual environment in {}...", venv_path.display());
+        uv.create_venv(&venv_path, RECOMMENDED_PYTHON_VERSION)
+    }
+    .map_err(|e| RuntimeError {
+        message: format!("Could not create virtual environment - {}", e),
+        exit_code: ExitCode::from(exitcode::SOFTWARE as u8),
+    })?;
+    log::info!(
+        "Virtual environment uses Python {}.",
+        venv.python_instance.version
+    );
+
+    let requirement_files = find_requirement_files(&instance.repo_dir())?;
+    let digest = requirements_digest(&instance.repo_dir(), &requirement_files)?;
+    let mut metadata = instance.load_metadata()?.unwrap_or_default();
+
+    if metadata.python_requirements.as_deref() == Some(digest.as_str())
+        && instance.python_lock_path().exists()
+    {
+        log::info!("Python requirements have not changed, skipping their installation.");
+        return Ok(());
+    }
+
+    log::info!(
+        "Installing Python requirements from {} file(s)...",
+        requirement_files.len()
+    );
+    let python_error = |action: &str, e: std::io::Error| RuntimeError {
+        message: format!("Could not {} - {}", action, e),
+        exit_code: ExitCode::from(exitcode::SOFTWARE as u8),
+    };
+    venv.install_requirements(&uv, &requirement_files)
+        .map_err(|e| python_error("install Python requirements", e))?;
+
+    // Conversion scripts should use the gguf package matching the checked out sources
+    let gguf_path = instance.repo_dir().join(GGUF_PACKAGE_DIR_NAME);
+    if gguf_path.is_dir() {
+        venv.install_packages(&uv, &[gguf_path.to_string_lossy()])
+            .map_err(|e| python_error("install gguf package", e))?;
+    }
+
+    let lock = venv
+        .freeze(&uv)
+        .map_err(|e| python_error("list installed Python packages", e))?;
+    std::fs::write(instance.python_lock_path(), lock)?;
+
+    verify_python_environment(instance, &venv)?;
+
+    metadata.python_requirements = Some(digest);
+    instance.save_metadata(&metadata)
+}
+
+/// Checks that `gguf` package can be imported and the conversion script can be called.
+fn verify_python_environment(instance: &Instance, venv: &VirtualEnvironment) -> Result<()> {
+    log::info!("Verifying Python environment...");
+
+    let convert_script = instance.repo_dir().join(CONVERT_SCRIPT_NAME);
+    let checks: [(&str, Vec<OsString>); 2] = [
+        ("import gguf", vec!["-c".into(), "import gguf".into()]),
+        (
+            CONVERT_SCRIPT_NAME,
+            vec![convert_script.into_os_string(), "--help".into()],
+        ),
+    ];
+
+    for (name, args) in checks {
+        let output = venv
+            .run_python(args, OutputMode::Capture)
+            .map_err(|e| RuntimeError {
+                message: format!("Could not run Python - {}", e),
+                exit_code: ExitCode::from(exitcode::SOFTWARE as u8),
+            })?;
+
+        if !output.status.success() {
+            return Err(RuntimeError::new(
+                format!(
+                    "Python environment verification failed, `{}` finished with {}:\n{}",
+                    name,
+                    output.status,
+                    output.stderr.trim_end()
+                ),
+                exitcode::SOFTWARE as u8,
+            ));
+        }
+    }
+
+    log::info!("Python environment is ready.");
+    Ok(())
+}
+
+/// Returns the `requirements/*.txt` files of llama.cpp repository, sorted by name.
+fn find_requirement_files(repo_dir: &Path) -> Result<Vec<PathBuf>> {
+    let requirements_dir = repo_dir.join(REQUIREMENTS_DIR_NAME);
+    if !requirements_dir.is_dir() {
+        return Err(RuntimeError::new(
+            format!(
+                "Python requirements directory {} does not exist",
+                requirements_dir.display()
+            ),
+            exitcode::NOINPUT as u8,
+        ));
+    }
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(&requirements_dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|extension| extension == "txt") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Digest of the Python sources the environment is installed from, used to detect their changes.
+/// Covers the requirement files, the files they include with `-r` or `-c`, and the `gguf-py` package.
+fn requirements_digest(repo_dir: &Path, requirement_files: &[PathBuf]) -> Result<String> {
+    let repo_dir = std::fs::canonicalize(repo_dir)?;
+    let mut inputs = BTreeSet::new();
+    let mut pending = requirement_files
+        .iter()
+        .map(std::fs::canonicalize)
+        .collect::<std::io::Result<Vec<_>>>()?;
+    while let Some(file) = pending.pop() {
+        if inputs.contains(&file) {
+            continue;
+        }
+        pending.extend(requirement_includes(&file)?);
+        inputs.insert(file);
+    }
+
+    // `gguf` package is installed from the sources, not pinned in the requirements
+    let is_cache = |path: &Path| {
+        path.components().any(|component| {
+            let name = component.as_os_str().to_string_lossy();
+            name.starts_with('.') || name == "__pycache__"
+        })
+    };
+    let gguf_dir = repo_dir.join(GGUF_PACKAGE_DIR_NAME);
+    inputs.extend(
+        find_files(&gguf_dir)?
+            .into_iter()
+            .filter(|path| !is_cache(path.strip_prefix(&gguf_dir).unwrap_or(path))),
+    );
+
+    let mut hasher = Sha256::new();
+    for file in inputs {
+        let name = file.strip_prefix(&repo_dir).unwrap_or(&file);
+        hasher.update(name.as_os_str().as_encoded_bytes());
+        hasher.update([0]);
+        hasher.update(std::fs::read(&file)?);
+        hasher.update([0]);
+    }
+    Ok(hex_digest(hasher))
+}
+
+/// Returns the files included by the requirement file with `-r` and `-c` options.
+/// Included paths are relative to the including file.
+fn requirement_includes(file: &Path) -> Result<Vec<PathBuf>> {
+    const INCLUDE_OPTIONS: [&str; 4] = ["-r", "--requirement", "-c", "--constraint"];
+
+    let base_dir = file.parent().unwrap_or(Path::new("."));
+    std::fs::read_to_string(file)?
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            INCLUDE_OPTIONS.iter().find_map(|option| {
+                let rest = line.strip_prefix(option)?;
+                let rest = rest.strip_prefix('=').unwrap_or(rest);
+                rest.split_whitespace().next()
+            })
+        })
+        .map(|include| {
+            std::fs::canonicalize(base_dir.join(include)).map_err(|e| {
+                RuntimeError::new(
+                    format!(
+                        "Cannot find {} included by {}: {}",
+                        include,
+                        file.display(),
+                        e
+                    ),
+                    exitcode::NOINPUT as u8,
+                )
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_stages_for_args() {
+        let args = InstallCommand {
+            ignore_python: false,
+            parallel: None,
+            git_ref: None,
+            rollback: false,
+        };
+        assert_eq!(InstallStage::for_args(&args), InstallStage::ALL.to_vec());
+
+        let args = InstallCommand {
+            ignore_python: true,
+            parallel: None,
+            git_ref: None,
+            rollback: false,
+        };
+        let stages = InstallStage::for_args(&args);
+        assert!(!stages.contains(&InstallStage::Python));
+        assert_eq!(stages.len(), InstallStage::ALL.len() - 1);
+    }
+
+    #[test]
+    fn test_install_state_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_path = temp_dir.path().join("install_state.toml");
+
+        let mut state = InstallState::default();
+        state.mark_completed(InstallStage::Pull);
+        state.mark_completed(InstallStage::Prerequisites);
+        state.mark_completed(InstallStage::Pull);
+        state.save(&state_path).unwrap();
+
+        let loaded = InstallState::load(&state_path).unwrap();
+        assert_eq!(loaded, state);
+        assert_eq!(loaded.completed_stages.len(), 2);
+    }
+
+    #[test]
+    fn test_install_state_missing_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let state = InstallState::load(temp_dir.path().join("install_state.toml")).unwrap();
+        assert_eq!(state, InstallState::default());
+    }
+
+    #[test]
+    fn test_run_stages_resumes_from_failed_stage() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_path = temp_dir.path().join("install_state.toml");
+        let stages = InstallStage::ALL.to_vec();
+
+        let mut executed = Vec::new();
+        let result = run_stages(&stages, &state_path, None, |stage, _| {
+            executed.push(stage);
+            if stage == InstallStage::Build {
+                Err(RuntimeError::new("build failed".to_string(), 1))
+            } else {
+                Ok(())
+            }
+        });
+        assert!(result.is_err());
+        assert_eq!(executed.last(), Some(&InstallStage::Build));
+
+        let mut resumed = Vec::new();
+        run_stages(&stages, &state_path, None, |stage, _| {
+            resumed.push(stage);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(
+            resumed,
+            vec![
+                InstallStage::Build,
+                InstallStage::Install,
+                InstallStage::Python
+            ]
+        );
+    }
+
+    #[test]
+    fn test_run_stages_restarts_after_ref_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_path = temp_dir.path().join("install_state.toml");
+        let stages = InstallStage::ALL.to_vec();
+        let old_ref: GitRef = "b4000".parse().unwrap();
+        let new_ref: GitRef = "b5000".parse().unwrap();
+
+        let failing = |stage, _: &mut InstallState| {
+            if stage == InstallStage::Build {
+                Err(RuntimeError::new("build failed".to_string(), 1))
+            } else {
+                Ok(())
+            }
+        };
+        assert!(run_stages(&stages, &state_path, Some(&old_ref), failing).is_err());
+
+        let mut executed = Vec::new();
+        run_stages(&stages, &state_path, Some(&new_ref), |stage, _| {
+            executed.push(stage);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(executed, stages);
+    }
+
+    #[test]
+    fn test_run_stages_restarts_finished_installation() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_path = temp_dir.path().join("install_state.toml");
+        let stages = InstallStage::ALL.to_vec();
+
+        run_stages(&stages, &state_path, None, |_, _| Ok(())).unwrap();
+
+        let mut executed = Vec::new();
+        run_stages(&stages, &state_path, None, |stage, _| {
+            executed.push(stage);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(executed, stages);
+    }
+
+    #[test]
+    fn test_run_stages_runs_only_python_after_ignored_python() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_path = temp_dir.path().join("install_state.toml");
+
+        let without_python = InstallStage::for_args(&InstallCommand {
+            ignore_python: true,
+            parallel: None,
+            git_ref: None,
+            rollback: false,
+        });
+        run_stages(&without_python, &state_path, None, |_, _| Ok(())).unwrap();
+
+        let mut executed = Vec::new();
+        run_stages(&InstallStage::ALL, &state_path, None, |stage, _| {
+            executed.push(stage);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(executed, vec![InstallStage::Python]);
+    }
+
+    #[test]
+    fn test_run_stages_keeps_pending_install_on_resume() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_path = temp_dir.path().join("install_state.toml");
+        let stages = InstallStage::ALL.to_vec();
+
+        let result = run_stages(&stages, &state_path, None, |stage, state| match stage {
+            InstallStage::Configure => {
+                state.pending_install = Some("20250101-000000-abcdef".to_string());
+                Ok(())
+            }
+            InstallStage::Build => Err(RuntimeError::new("build failed".to_string(), 1)),
+            _ => Ok(()),
+        });
+        assert!(result.is_err());
+
+        let mut pending_install = None;
+        run_stages(&stages, &state_path, None, |stage, state| {
+            if stage == InstallStage::Install {
+                pending_install = state.pending_install.clone();
+            }
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(pending_install, Some("20250101-000000-abcdef".to_string()));
+    }
+
+    fn install_record(id: &str) -> InstallRecord {
+        InstallRecord {
+            id: id.to_string(),
+            commit: id.to_string(),
+            built_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_find_previous_build() {
+        let installs = vec![
+            install_record("20250101-000000-a"),
+            install_record("20250301-000000-c"),
+            install_record("20250201-000000-b"),
+        ];
+
+        let previous = find_previous_build(&installs, "20250301-000000-c", |_| true);
+        assert_eq!(previous.unwrap().id, "20250201-000000-b");
+
+        let previous = find_previous_build(&installs, "20250301-000000-c", |id| {
+            id != "20250201-000000-b"
+        });
+        assert_eq!(previous.unwrap().id, "20250101-000000-a");
+
+        assert!(find_previous_build(&installs, "20250101-000000-a", |_| true).is_none());
+    }
+
+    #[test]
+    fn test_split_installs_by_retention() {
+        let installs = vec![
+            install_record("20250101-000000-a"),
+            install_record("20250201-000000-b"),
+            install_record("20250301-000000-c"),
+            install_record("20250401-000000-d"),
+        ];
+
+        let (kept, removed) =
+            split_installs_by_retention(installs.clone(), Some("20250401-000000-d"), 2);
+        let kept: Vec<&str> = kept.iter().map(|i| i.id.as_str()).collect();
+        let removed: Vec<&str> = removed.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(kept, vec!["20250301-000000-c", "20250401-000000-d"]);
+        assert_eq!(removed, vec!["20250201-000000-b", "20250101-000000-a"]);
+
+        // Rolled back build is never removed
+        let (kept, _) = split_installs_by_retention(installs.clone(), Some("20250101-000000-a"), 1);
+        let kept: Vec<&str> = kept.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(kept, vec!["20250101-000000-a", "20250401-000000-d"]);
+
+        // Zero retention still keeps the newest build
+        let (kept, removed) = split_installs_by_retention(installs, None, 0);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(removed.len(), 3);
+    }
+
+    #[test]
+    fn test_requirements_digest_detects_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo_dir = temp_dir.path();
+        let requirements_dir = repo_dir.join(REQUIREMENTS_DIR_NAME);
+        let gguf_dir = repo_dir.join(GGUF_PACKAGE_DIR_NAME).join("gguf");
+        std::fs::create_dir(&requirements_dir).unwrap();
+        std::fs::create_dir_all(gguf_dir.join("__pycache__")).unwrap();
+        std::fs::write(requirements_dir.join("b.txt"), "-r ../requirements.txt\n").unwrap();
+        std::fs::write(requirements_dir.join("a.txt"), "-r ./b.txt\n").unwrap();
+        std::fs::write(requirements_dir.join("README.md"), "not requirements").unwrap();
+        std::fs::write(
+            repo_dir.join("requirements.txt"),
+            "-r requirements/a.txt\nnumpy\n",
+        )
+        .unwrap();
+        std::fs::write(gguf_dir.join("constants.py"), "VERSION = 1\n").unwrap();
+
+        let files = find_requirement_files(repo_dir).unwrap();
+        assert_eq!(
+            files,
+            vec![
+                requirements_dir.join("a.txt"),
+                requirements_dir.join("b.txt")
+            ]
+        );
+
+        let digest = requirements_digest(repo_dir, &files).unwrap();
+        assert_eq!(requirements_digest(repo_dir, &files).unwrap(), digest);
+
+        // Python cache is not a part of the sources
+        std::fs::write(gguf_dir.join("__pycache__").join("constants.pyc"), "cache").unwrap();
+        assert_eq!(requirements_digest(repo_dir, &files).unwrap(), digest);
+
+        // Included file outside of the requirements directory
+        std::fs::write(
+            repo_dir.join("requirements.txt"),
+            "-r requirements/a.txt\nnumpy~=1.26\n",
+        )
+        .unwrap();
+        let included_changed = requirements_digest(repo_dir, &files).unwrap();
+        assert_ne!(included_changed, digest);
+
+        std::fs::write(gguf_dir.join("constants.py"), "VERSION = 2\n").unwrap();
+        assert_ne!(
+            requirements_digest(repo_dir, &files).unwrap(),
+            included_changed
+        );
+
+        std::fs::write(requirements_dir.join("b.txt"), "-r missing.txt\n").unwrap();
+        assert!(requirements_digest(repo_dir, &files).is_err());
+
+        assert!(find_requirement_files(&requirements_dir).is_err());
+    }
 }
@@ -0,0 +1,214 @@
+use clap::{Parser, Subcommand};
+
+use crate::{
+    commands::{
+        Result, RuntimeError, confirm,
+        install::{InstallStage, InstallState},
+    },
+    config::Config,
+    instance::Instance,
+};
+
+#[derive(Debug, Parser)]
+pub struct InstanceCommand {
+    #[command(subcommand)]
+    pub action: InstanceAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum InstanceAction {
+    /// List all llama.cpp instances
+    List,
+    /// Set the default instance
+    Use {
+        /// Name of the instance
+        name: String,
+    },
+    /// Remove an instance with all of its files
+    Remove {
+        /// Name of the instance
+        name: String,
+
+        #[arg(long, short)]
+        /// Force removal without confirmation
+        force: bool,
+    },
+}
+
+pub fn run(args: InstanceCommand, config: &Config) -> Result<()> {
+    let llama_dir = &config.paths.llama_dir;
+
+    match args.action {
+        InstanceAction::List => list_instances(config),
+        InstanceAction::Use { name } => {
+            let instance = get_existing_instance(config, &name)?;
+            Instance::set_default(llama_dir, Some(&instance.name))?;
+            log::info!("Instance '{}' is now the default one.", instance.name);
+            Ok(())
+        }
+        InstanceAction::Remove { name, force } => {
+            let instance = get_existing_instance(config, &name)?;
+            remove_instance(config, &instance, force)
+        }
+    }
+}
+
+/// Removes the instance directory, asking for confirmation unless `force` is set.
+pub fn remove_instance(config: &Config, instance: &Instance, force: bool) -> Result<()> {
+    if !force
+        && !confirm(&format!(
+            "Remove instance '{}' from {}?",
+            instance.name,
+            instance.path.display()
+        ))?
+    {
+        log::info!("Aborted.");
+        return Ok(());
+    }
+
+    instance.remove()?;
+
+    let llama_dir = &config.paths.llama_dir;
+    if Instance::get_default(llama_dir)?.as_deref() == Some(instance.name.as_str()) {
+        Instance::set_default(llama_dir, None)?;
+    }
+
+    log::info!("Instance '{}' has been removed.", instance.name);
+    Ok(())
+}
+
+fn get_existing_instance(config: &Config, name: &str) -> Result<Instance> {
+    let instance = Instance::new(&config.paths.llama_dir, name)?;
+
+    if !instance.exists() {
+        return Err(RuntimeError::new(
+            format!("Instance '{}' does not exist", name),
+            exitcode::NOINPUT as u8,
+        ));
+    }
+
+    Ok(instance)
+}
+
+fn list_instances(config: &Config) -> Result<()> {
+    let instances = Instance::list(&config.paths.llama_dir)?;
+
+    if instances.is_empty() {
+        println!("No instances found in {}", config.paths.llama_dir.display());
+        return Ok(());
+    }
+
+    let default_instance = Instance::get_default(&config.paths.llama_dir)?;
+
+    println!(
+        "  {:<20} {:<12} {:<12} {:<20} STATE",
+        "NAME", "PROFILE", "COMMIT", "BUILT"
+    );
+
+    for instance in instances {
+        let metadata = instance.load_metadata()?.unwrap_or_default();
+        let marker = if default_instance.as_deref() == Some(instance.name.as_str()) {
+            "*"
+        } else {
+            " "
+        };
+        let commit = metadata
+            .commit
+            .map(|c| c.chars().take(10).collect())
+            .unwrap_or_else(|| "-".to_string());
+        let built_at = metadata
+            .built_at
+            .map(|date| {
+                date.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_else(|| "-".to_string());
+        let profile = if metadata.profile.is_empty() {
+            "-".to_string()
+        } else {
+            metadata.profile
+        };
+
+        println!(
+            "{} {:<20} {:<12} {:<12} {:<20} {}",
+            marker,
+            instance.name,
+            profile,
+            commit,
+            built_at,
+            describe_install_state(
+                &InstallState::load(instance.install_state_path())?,
+                instance.install_dir().exists()
+            )
+        );
+    }
+
+    Ok(())
+}
+
+/// Describes the installation of the instance. Re-installation starts the pipeline from scratch,
+/// so with the previous build still in place, the instance is installed while it's in progress.
+fn describe_install_state(state: &InstallState, installed: bool) -> String {
+    let without_python: Vec<InstallStage> = InstallStage::ALL
+        .into_iter()
+        .filter(|stage| *stage != InstallStage::Python)
+        .collect();
+
+    if state.is_finished(&InstallStage::ALL) {
+        return "installed".to_string();
+    }
+    if state.is_finished(&without_python) {
+        return "installed (without Python)".to_string();
+    }
+
+    match (state.next_stage(&InstallStage::ALL), installed) {
+        (Some(stage), true) if !state.completed_stages.is_empty() => {
+            format!("installed (upgrade in progress, next stage: {})", stage)
+        }
+        (_, true) => "installed".to_string(),
+        (_, false) if state.completed_stages.is_empty() => "not installed".to_string(),
+        (Some(stage), false) => format!("incomplete (next stage: {})", stage),
+        (None, false) => "installed".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_install_state() {
+        let state = |completed: &[InstallStage]| InstallState {
+            completed_stages: completed.to_vec(),
+            ..Default::default()
+        };
+        let built = [
+            InstallStage::Pull,
+            InstallStage::Prerequisites,
+            InstallStage::Configure,
+            InstallStage::Build,
+        ];
+
+        assert_eq!(describe_install_state(&state(&[]), false), "not installed");
+        assert_eq!(
+            describe_install_state(&state(&built), false),
+            "incomplete (next stage: install)"
+        );
+        assert_eq!(
+            describe_install_state(&state(&InstallStage::ALL), true),
+            "installed"
+        );
+        assert_eq!(
+            describe_install_state(&state(&InstallStage::ALL[..5]), true),
+            "installed (without Python)"
+        );
+
+        // Re-installation over a working build
+        assert_eq!(describe_install_state(&state(&[]), true), "installed");
+        assert_eq!(
+            describe_install_state(&state(&built), true),
+            "installed (upgrade in progress, next stage: install)"
+        );
+    }
+}
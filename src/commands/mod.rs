@@ -1,34 +1,25 @@
+pub mod config;
 pub mod convert;
 pub mod daemon;
+pub mod imatrix;
 pub mod install;
+pub mod instance;
+pub mod models;
 pub mod quantize;
 pub mod server;
 pub mod uninstall;
 
-use std::fmt::Display;
-use std::process::ExitCode;
+use std::io::{BufRead, Write};
 
-use thiserror::Error;
+pub use crate::error::{Result, RuntimeError};
 
-#[derive(Debug, Error)]
-pub struct RuntimeError {
-    pub message: String,
-    pub exit_code: ExitCode,
-}
+/// Asks the user a yes/no question on standard input. Defaults to "no".
+pub fn confirm(question: &str) -> Result<bool> {
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush()?;
 
-impl RuntimeError {
-    pub fn new(message: String, exit_code: u8) -> Self {
-        Self {
-            message,
-            exit_code: ExitCode::from(exit_code),
-        }
-    }
-}
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
 
-impl Display for RuntimeError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.message)
-    }
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
 }
-
-pub type Result<T> = std::result::Result<T, RuntimeError>;
@@ -0,0 +1,377 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::{
+    commands::{Result, RuntimeError},
+    config::Config,
+    models::{
+        ModelIndex, format_parameter_count,
+        gguf::GgufFile,
+        huggingface::{self, CheckpointEntry},
+        imatrix_path,
+        store::BlobStore,
+    },
+};
+
+#[derive(Debug, Parser)]
+pub struct ModelsCommand {
+    #[command(subcommand)]
+    pub action: ModelsAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ModelsAction {
+    /// List all the models in the models directory
+    List,
+    /// Print the details and metadata of a model
+    Show {
+        /// Model reference (`qwen3:8b-q4_k_m`), alias, name, or path relative to the models directory
+        name: String,
+    },
+    /// Import a Hugging Face checkpoint from the local cache or a directory, to be converted with `convert`
+    Import {
+        /// Repository ID in Hugging Face cache (`Qwen/Qwen3-8B`), cached repository directory,
+        /// snapshot directory or any directory with `config.json`
+        source: String,
+        /// Name of the checkpoint in the index, repository ID or directory name by default
+        #[arg(long)]
+        name: Option<String>,
+        /// Ref name or commit hash of the snapshot, `main` by default
+        #[arg(long)]
+        revision: Option<String>,
+        /// Hugging Face hub cache directory, `$HF_HUB_CACHE` or `~/.cache/huggingface/hub` by default
+        #[arg(long)]
+        cache_dir: Option<PathBuf>,
+    },
+    /// Move models into the content-addressed blob store, deduplicating identical files
+    Store {
+        /// Model references, aliases or names to store, all the models if none are given
+        names: Vec<String>,
+    },
+    /// Remove manifests of deleted models and blobs not referenced by any model
+    Gc {
+        /// Only print what would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Re-hash all the blobs in the store and report corrupted or missing ones
+    Verify,
+}
+
+pub fn run(args: ModelsCommand, config: &Config) -> Result<()> {
+    let mut index = ModelIndex::refresh(&config.paths.models_dir)?;
+
+    match args.action {
+        ModelsAction::List => list_models(&index, config),
+        ModelsAction::Show { name } => show_model(&index, config, &name),
+        ModelsAction::Import {
+            source,
+            name,
+            revision,
+            cache_dir,
+        } => import_checkpoint(
+            &mut index,
+            config,
+            &source,
+            name.as_deref(),
+            revision.as_deref(),
+            cache_dir.or_else(huggingface::hub_cache_dir),
+        ),
+        ModelsAction::Store { names } => store_models(&index, config, &names),
+        ModelsAction::Gc { dry_run } => collect_garbage(config, dry_run),
+        ModelsAction::Verify => verify_store(config),
+    }
+}
+
+fn list_models(index: &ModelIndex, config: &Config) -> Result<()> {
+    if index.models.is_empty() && index.checkpoints.is_empty() {
+        println!("No models found in {}", config.paths.models_dir.display());
+        return Ok(());
+    }
+
+    println!(
+        "{:<32} {:<32} {:<12} {:>8} {:<10} {:>8} {:>10}",
+        "NAME", "REFERENCE", "ARCH", "PARAMS", "QUANT", "CONTEXT", "SIZE"
+    );
+
+    for model in &index.models {
+        println!(
+            "{:<32} {:<32} {:<12} {:>8} {:<10} {:>8} {:>10}",
+            model.name(),
+            model.reference().unwrap_or_else(|| "-".to_string()),
+            model.architecture.as_deref().unwrap_or("-"),
+            format_parameter_count(model.parameter_count),
+            model.quant_type.as_deref().unwrap_or("-"),
+            model
+                .context_length
+                .map(|length| length.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            format_size(model.size)
+        );
+    }
+
+    if !index.checkpoints.is_empty() {
+        println!();
+        println!(
+            "{:<32} {:<32} {:<10} {:<12} {:>10}",
+            "CHECKPOINT", "ARCH", "DTYPE", "REVISION", "SIZE"
+        );
+        for checkpoint in &index.checkpoints {
+            println!(
+                "{:<32} {:<32} {:<10} {:<12} {:>10}",
+                checkpoint.name,
+                checkpoint.architecture.as_deref().unwrap_or("-"),
+                checkpoint.dtype.as_deref().unwrap_or("-"),
+                checkpoint
+                    .revision
+                    .as_deref()
+                    .map(|revision| &revision[..revision.len().min(12)])
+                    .unwrap_or("-"),
+                format_size(checkpoint.size)
+            );
+        }
+    }
+
+    if !config.models.aliases.is_empty() {
+        println!();
+        println!("{:<32} TARGET", "ALIAS");
+        for (alias, target) in &config.models.aliases {
+            println!("{:<32} {}", alias, target);
+        }
+    }
+
+    Ok(())
+}
+
+fn show_model(index: &ModelIndex, config: &Config, name: &str) -> Result<()> {
+    if let Some(checkpoint) = index.find_checkpoint(name) {
+        show_checkpoint(checkpoint);
+        return Ok(());
+    }
+
+    let model = index.resolve(name, &config.models.aliases)?;
+    let path = config.paths.models_dir.join(&model.path);
+
+    println!("Name:         {}", model.name());
+    println!(
+        "Reference:    {}",
+        model.reference().unwrap_or_else(|| "-".to_string())
+    );
+    println!("Path:         {}", path.display());
+    println!(
+        "Architecture: {}",
+        model.architecture.as_deref().unwrap_or("-")
+    );
+    println!(
+        "Parameters:   {} ({})",
+        format_parameter_count(model.parameter_count),
+        model.parameter_count
+    );
+    println!(
+        "Quantization: {}",
+        model.quant_type.as_deref().unwrap_or("-")
+    );
+    println!(
+        "Context:      {}",
+        model
+            .context_length
+            .map(|length| length.to_string())
+            .unwrap_or_else(|| "-".to_string())
+    );
+    println!("Tensors:      {}", model.tensor_count);
+    println!(
+        "Size:         {} ({} bytes)",
+        format_size(model.size),
+        model.size
+    );
+    println!(
+        "Modified:     {}",
+        model
+            .modified
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M")
+    );
+
+    let imatrix = imatrix_path(&path);
+    if imatrix.is_file() {
+        println!("Imatrix:      {}", imatrix.display());
+    }
+
+    let gguf = GgufFile::open(&path)?;
+    println!();
+    println!("Metadata (GGUF v{}):", gguf.version);
+    for (key, value) in &gguf.metadata {
+        println!("  {} = {}", key, value);
+    }
+
+    Ok(())
+}
+
+fn show_checkpoint(checkpoint: &CheckpointEntry) {
+    println!("Checkpoint:   {}", checkpoint.name);
+    println!("Path:         {}", checkpoint.path.display());
+    println!(
+        "Repository:   {}",
+        checkpoint.repository.as_deref().unwrap_or("-")
+    );
+    println!(
+        "Revision:     {}",
+        checkpoint.revision.as_deref().unwrap_or("-")
+    );
+    println!(
+        "Architecture: {}",
+        checkpoint.architecture.as_deref().unwrap_or("-")
+    );
+    println!(
+        "Model type:   {}",
+        checkpoint.model_type.as_deref().unwrap_or("-")
+    );
+    println!(
+        "Data type:    {}",
+        checkpoint.dtype.as_deref().unwrap_or("-")
+    );
+    println!(
+        "Size:         {} ({} bytes)",
+        format_size(checkpoint.size),
+        checkpoint.size
+    );
+    println!(
+        "Imported:     {}",
+        checkpoint
+            .imported
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M")
+    );
+}
+
+fn import_checkpoint(
+    index: &mut ModelIndex,
+    config: &Config,
+    source: &str,
+    name: Option<&str>,
+    revision: Option<&str>,
+    cache_dir: Option<PathBuf>,
+) -> Result<()> {
+    let path = huggingface::locate_checkpoint(source, cache_dir.as_deref(), revision)?;
+    log::info!("Importing checkpoint from {}", path.display());
+    let checkpoint = huggingface::inspect_checkpoint(&path, name)?;
+
+    println!(
+        "Imported {} ({}, {}, {})",
+        checkpoint.name,
+        checkpoint
+            .architecture
+            .as_deref()
+            .unwrap_or("unknown architecture"),
+        checkpoint.dtype.as_deref().unwrap_or("unknown dtype"),
+        format_size(checkpoint.size)
+    );
+    if index.register_checkpoint(checkpoint) {
+        log::info!("Replaced previously imported checkpoint with the same name");
+    }
+    index.save(&config.paths.models_dir)
+}
+
+fn store_models(index: &ModelIndex, config: &Config, names: &[String]) -> Result<()> {
+    let models = if names.is_empty() {
+        index.models.iter().collect()
+    } else {
+        names
+            .iter()
+            .map(|name| index.resolve(name, &config.models.aliases))
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    let store = BlobStore::new(&config.paths.models_dir);
+    let mut saved = 0;
+    for model in models {
+        log::info!("Storing model {}", model.name());
+        let stored = store.add(&model.name())?;
+        if stored.deduplicated {
+            saved += model.size;
+            println!(
+                "{}: deduplicated with blob sha256-{}",
+                model.name(),
+                stored.digest
+            );
+        } else {
+            println!("{}: stored as blob sha256-{}", model.name(), stored.digest);
+        }
+    }
+
+    if saved > 0 {
+        println!("Saved {} by deduplication", format_size(saved));
+    }
+    Ok(())
+}
+
+fn collect_garbage(config: &Config, dry_run: bool) -> Result<()> {
+    let store = BlobStore::new(&config.paths.models_dir);
+    let garbage = store.collect_garbage(dry_run)?;
+    let action = if dry_run { "Would remove" } else { "Removed" };
+
+    for name in &garbage.manifests {
+        println!("{} manifest of deleted model {}", action, name);
+    }
+    for (digest, size) in &garbage.blobs {
+        println!("{} blob sha256-{} ({})", action, digest, format_size(*size));
+    }
+
+    let freed: u64 = garbage.blobs.iter().map(|(_, size)| size).sum();
+    println!(
+        "{} {} manifest(s) and {} blob(s), {} freed",
+        action,
+        garbage.manifests.len(),
+        garbage.blobs.len(),
+        format_size(freed)
+    );
+    Ok(())
+}
+
+fn verify_store(config: &Config) -> Result<()> {
+    let store = BlobStore::new(&config.paths.models_dir);
+    let problems = store.verify()?;
+    if problems.is_empty() {
+        println!("All blobs are valid");
+        return Ok(());
+    }
+
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    Err(RuntimeError::new(
+        format!("Found {} problem(s) in the blob store", problems.len()),
+        exitcode::DATAERR as u8,
+    ))
+}
+
+/// Formats file size in binary units, like `4.7 GiB`.
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut value = size as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", size, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(4_920_000_000), "4.6 GiB");
+    }
+}
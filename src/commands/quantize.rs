@@ -1,31 +1,617 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
 use clap::Parser;
 
 use crate::{
-    commands::Result,
+    commands::{Result, RuntimeError, models::format_size},
     config::{Config, Profile},
+    external_tools::llama_quantize::{LlamaQuantize, QuantType},
+    instance::Instance,
+    models::{ModelIndex, imatrix_path, is_up_to_date, resolve_model_path},
 };
 
+const QUANTIZE_BINARY_NAME: &str = "llama-quantize";
+
+/// Presets available without any configuration, can be overridden in `[models.quant_presets]`.
+const BUILTIN_PRESETS: &[(&str, &[&str])] =
+    &[("standard", &["Q8_0", "Q6_K", "Q5_K_M", "Q4_K_M", "IQ4_XS"])];
+
+/// Suffixes of unquantized models, stripped from the model name along with quantization types.
+const UNQUANTIZED_SUFFIXES: [&str; 3] = ["f32", "f16", "bf16"];
+
+/// Number of the last lines of `llama-quantize` output printed when a parallel job fails.
+const FAILURE_OUTPUT_LINES: usize = 10;
+
 #[derive(Debug, Parser)]
 pub struct QuantizeCommand {
     #[arg(long, short)]
-    /// Path to the input GGUF file
-    pub input: String,
+    /// Input GGUF file - path, model reference (`qwen3:8b-bf16`), alias or name.
+    /// Without it, available quantization types and presets are listed.
+    pub input: Option<String>,
 
     #[arg(long, short)]
-    /// Path to the output GGUF file
-    pub output: String,
+    /// Path to the output GGUF file, `<model>-<quant>.gguf` next to the input by default.
+    /// Can be used only with a single quantization type.
+    pub output: Option<PathBuf>,
+
+    #[arg(long, short, value_delimiter = ',')]
+    /// Quantization types, names (like `Q4_K_M`) or numeric IDs, separated with commas
+    pub quant: Vec<String>,
+
+    #[arg(long)]
+    /// Named list of quantization types, built-in (`standard`) or defined in `[models.quant_presets]`
+    pub preset: Option<String>,
+
+    #[arg(long, short, default_value_t = 1)]
+    /// Number of quantizations running at the same time
+    pub jobs: usize,
 
     #[arg(long, short)]
-    /// Quantization type.
-    pub quant: String,
+    /// Quantize even if the output is newer than the input
+    pub force: bool,
+
+    #[arg(long)]
+    /// Importance matrix used for all the quantization types. By default, the matrix cached next to
+    /// the input by `imatrix` command is used for `IQ` and low-bit K quantization types
+    pub imatrix: Option<PathBuf>,
+
+    #[arg(last = true)]
+    /// Additional arguments passed to `llama-quantize`, like `--allow-requantize`
+    pub extra_args: Vec<String>,
+}
+
+/// Single quantization of the batch.
+#[derive(Debug)]
+struct QuantizeJob {
+    quant: String,
+    output: PathBuf,
+    imatrix: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+enum JobStatus {
+    Done,
+    /// Output is newer than the input.
+    UpToDate,
+    Failed(String),
+}
+
+#[derive(Debug)]
+struct JobResult {
+    status: JobStatus,
+    duration: Duration,
+}
+
+pub fn run(
+    args: QuantizeCommand,
+    config: &Config,
+    _profile: &Profile,
+    instance: &Instance,
+) -> Result<()> {
+    let tool: LlamaQuantize = instance.tool(QUANTIZE_BINARY_NAME)?;
+    let quant_types = tool.quant_types().map_err(|e| {
+        RuntimeError::new(
+            format!("Could not read quantization types - {}", e),
+            exitcode::SOFTWARE as u8,
+        )
+    })?;
+    let presets = presets(config);
+
+    let Some(input) = &args.input else {
+        if !args.quant.is_empty() || args.preset.is_some() || args.output.is_some() {
+            return Err(RuntimeError::new(
+                "--input is required to quantize a model".to_string(),
+                exitcode::USAGE as u8,
+            ));
+        }
+        list_quant_types(&quant_types, &presets);
+        return Ok(());
+    };
+
+    // Types are validated before the input is resolved, so typos are reported immediately
+    let quants = selected_quant_types(&quant_types, &presets, &args.quant, args.preset.as_deref())?;
+    if args.output.is_some() && quants.len() > 1 {
+        return Err(RuntimeError::new(
+            "--output can be used only with a single quantization type".to_string(),
+            exitcode::USAGE as u8,
+        ));
+    }
+    if args.jobs == 0 {
+        return Err(RuntimeError::new(
+            "--jobs must be at least 1".to_string(),
+            exitcode::USAGE as u8,
+        ));
+    }
+
+    let input_path = resolve_model_path(config, input)?;
+    log::info!("Using input model: {}", input_path.display());
+
+    if let Some(imatrix) = &args.imatrix
+        && !imatrix.is_file()
+    {
+        return Err(RuntimeError::new(
+            format!("Importance matrix {} does not exist", imatrix.display()),
+            exitcode::NOINPUT as u8,
+        ));
+    }
+    let cached_imatrix = Some(imatrix_path(&input_path)).filter(|path| path.is_file());
+
+    let jobs: Vec<QuantizeJob> = quants
+        .iter()
+        .map(|quant| {
+            let output = match &args.output {
+                Some(output) => output.clone(),
+                None => default_output_path(&input_path, &quant.name, &quant_types),
+            };
+            let imatrix = match &args.imatrix {
+                Some(imatrix) => Some(imatrix.clone()),
+                None if quant.needs_imatrix() => {
+                    if cached_imatrix.is_none() {
+                        log::warn!(
+                            "{} should be quantized with an importance matrix, create it with `llama-mgr imatrix`",
+                            quant.name
+                        );
+                    }
+                    cached_imatrix.clone()
+                }
+                None => None,
+            };
+            QuantizeJob {
+                quant: quant.name.clone(),
+                output,
+                imatrix,
+            }
+        })
+        .collect();
+    if let Some(job) = jobs.iter().find(|job| job.output == input_path) {
+        return Err(RuntimeError::new(
+            format!(
+                "Output file of {} must be different from the input file",
+                job.quant
+            ),
+            exitcode::USAGE as u8,
+        ));
+    }
+
+    let results = run_jobs(&tool, &input_path, &jobs, &args);
+
+    for (job, result) in jobs.iter().zip(&results) {
+        if matches!(result.status, JobStatus::Done)
+            && let Some(model) = ModelIndex::register(&config.paths.models_dir, &job.output)?
+        {
+            log::info!(
+                "Registered model {}",
+                model.reference().unwrap_or_else(|| model.name())
+            );
+        }
+    }
+
+    print_summary(&jobs, &results);
+
+    let failed = results
+        .iter()
+        .filter(|result| matches!(result.status, JobStatus::Failed(_)))
+        .count();
+    if failed > 0 {
+        return Err(RuntimeError::new(
+            format!("{} of {} quantization(s) failed", failed, jobs.len()),
+            exitcode::SOFTWARE as u8,
+        ));
+    }
+    Ok(())
+}
+
+/// Returns built-in presets merged with the ones from configuration.
+fn presets(config: &Config) -> BTreeMap<String, Vec<String>> {
+    let mut presets: BTreeMap<String, Vec<String>> = BUILTIN_PRESETS
+        .iter()
+        .map(|(name, quants)| {
+            (
+                name.to_string(),
+                quants.iter().map(|quant| quant.to_string()).collect(),
+            )
+        })
+        .collect();
+    presets.extend(config.models.quant_presets.clone());
+    presets
+}
+
+/// Formats bits per weight reported by `llama-quantize`, or nominal ones marked with `~`.
+fn format_bits_per_weight(quant_type: &QuantType) -> String {
+    if let Some(bits) = quant_type.bits_per_weight {
+        format!("{:.2}", bits)
+    } else if let Some(bits) = quant_type.nominal_bits_per_weight() {
+        format!("~{:.2}", bits)
+    } else {
+        "-".to_string()
+    }
+}
+
+fn list_quant_types(quant_types: &[QuantType], presets: &BTreeMap<String, Vec<String>>) {
+    println!("{:>4} {:<12} {:>6} DESCRIPTION", "ID", "NAME", "BPW");
+    for quant_type in quant_types {
+        println!(
+            "{:>4} {:<12} {:>6} {}",
+            quant_type
+                .id
+                .map(|id| id.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            quant_type.name,
+            format_bits_per_weight(quant_type),
+            quant_type.description
+        );
+    }
+
+    println!();
+    println!("{:<17} TYPES", "PRESET");
+    for (name, quants) in presets {
+        println!("{:<17} {}", name, quants.join(", "));
+    }
+}
+
+/// Returns the quantization types selected with `--quant` and `--preset`, without duplicates.
+fn selected_quant_types<'a>(
+    quant_types: &'a [QuantType],
+    presets: &BTreeMap<String, Vec<String>>,
+    quants: &[String],
+    preset: Option<&str>,
+) -> Result<Vec<&'a QuantType>> {
+    let mut names: Vec<&String> = quants.iter().collect();
+    if let Some(preset) = preset {
+        let preset_quants = presets.get(preset).ok_or_else(|| {
+            RuntimeError::new(
+                format!(
+                    "Unknown preset '{}', available presets: {}",
+                    preset,
+                    presets.keys().cloned().collect::<Vec<_>>().join(", ")
+                ),
+                exitcode::USAGE as u8,
+            )
+        })?;
+        names.extend(preset_quants);
+    }
+
+    if names.is_empty() {
+        return Err(RuntimeError::new(
+            "--quant or --preset is required, run `llama-mgr quantize` without arguments to list the available types"
+                .to_string(),
+            exitcode::USAGE as u8,
+        ));
+    }
+
+    let mut selected: Vec<&QuantType> = Vec::new();
+    for name in names {
+        let quant_type = find_quant_type(quant_types, name)?;
+        if !selected.iter().any(|other| other.name == quant_type.name) {
+            selected.push(quant_type);
+        }
+    }
+    Ok(selected)
 }
 
-pub fn run(args: QuantizeCommand, config: &Config, profile: &Profile) -> Result<()> {
+/// Finds the quantization type by its name or ID. Aliases are resolved to the types they point to.
+fn find_quant_type<'a>(quant_types: &'a [QuantType], name: &str) -> Result<&'a QuantType> {
+    let quant_type = quant_types
+        .iter()
+        .find(|quant_type| quant_type.matches(name))
+        .ok_or_else(|| {
+            RuntimeError::new(
+                format!(
+                    "Unknown quantization type '{}', available types: {}",
+                    name,
+                    quant_types
+                        .iter()
+                        .map(|quant_type| quant_type.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                exitcode::USAGE as u8,
+            )
+        })?;
+
+    Ok(quant_type
+        .alias_for()
+        .and_then(|target| quant_types.iter().find(|other| other.name == target))
+        .unwrap_or(quant_type))
+}
+
+/// Default output path, `<model>-<quant>.gguf` next to the input. Model name is the input file name
+/// without the quantization suffix, so `qwen3-8b-bf16.gguf` is quantized to `qwen3-8b-q4_k_m.gguf`.
+fn default_output_path(input_path: &Path, quant: &str, quant_types: &[QuantType]) -> PathBuf {
+    let stem = input_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let model = match stem.rsplit_once('-') {
+        Some((model, suffix))
+            if UNQUANTIZED_SUFFIXES
+                .iter()
+                .any(|unquantized| unquantized.eq_ignore_ascii_case(suffix))
+                || quant_types
+                    .iter()
+                    .any(|quant_type| quant_type.name.eq_ignore_ascii_case(suffix)) =>
+        {
+            model
+        }
+        _ => &stem,
+    };
+
+    input_path.with_file_name(format!("{}-{}.gguf", model, quant.to_lowercase()))
+}
+
+/// Runs the jobs with at most `args.jobs` of them at the same time. With a single job at a time,
+/// output of `llama-quantize` is passed to the terminal, otherwise it's captured and printed on failure.
+fn run_jobs(
+    tool: &LlamaQuantize,
+    input_path: &Path,
+    jobs: &[QuantizeJob],
+    args: &QuantizeCommand,
+) -> Vec<JobResult> {
+    let next_job = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<JobResult>>> =
+        Mutex::new(std::iter::repeat_with(|| None).take(jobs.len()).collect());
+    let capture_output = args.jobs > 1;
+
+    std::thread::scope(|scope| {
+        for _ in 0..args.jobs.min(jobs.len()) {
+            scope.spawn(|| {
+                loop {
+                    let index = next_job.fetch_add(1, Ordering::SeqCst);
+                    let Some(job) = jobs.get(index) else {
+                        break;
+                    };
+
+                    let result = run_job(tool, input_path, job, args, capture_output);
+                    results.lock().unwrap()[index] = Some(result);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every job is run"))
+        .collect()
+}
+
+fn run_job(
+    tool: &LlamaQuantize,
+    input_path: &Path,
+    job: &QuantizeJob,
+    args: &QuantizeCommand,
+    capture_output: bool,
+) -> JobResult {
+    let start = Instant::now();
+    let result = |status| JobResult {
+        status,
+        duration: start.elapsed(),
+    };
+
+    let mut inputs = vec![input_path];
+    inputs.extend(job.imatrix.as_deref());
+    if !args.force && is_up_to_date(&job.output, &inputs) {
+        log::info!(
+            "{} is up to date, skipping {}",
+            job.output.display(),
+            job.quant
+        );
+        return result(JobStatus::UpToDate);
+    }
+
+    if let Some(parent) = job.output.parent()
+        && let Err(e) = std::fs::create_dir_all(parent)
+    {
+        return result(JobStatus::Failed(e.to_string()));
+    }
+
+    // Output is written under a temporary name, so incomplete files never get indexed
+    let partial_path = job.output.with_extension("gguf.partial");
     log::info!(
-        "Quantize command called with args: {:?}, config: {:?}, profile: {:?}",
-        args,
-        config,
-        profile
+        "Quantizing {} to {} ({})",
+        input_path.display(),
+        job.output.display(),
+        job.quant
     );
-    Ok(())
+    if let Some(imatrix) = &job.imatrix {
+        log::info!("Using importance matrix {}", imatrix.display());
+    }
+
+    let mut command = tool.quantize_command(
+        input_path,
+        &partial_path,
+        &job.quant,
+        job.imatrix.as_deref(),
+        &args.extra_args,
+    );
+    let outcome = if capture_output {
+        command.output().map(|output| {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let lines: Vec<&str> = stderr.lines().collect();
+            let tail = lines[lines.len().saturating_sub(FAILURE_OUTPUT_LINES)..].join("\n");
+            (output.status, tail)
+        })
+    } else {
+        command.status().map(|status| (status, String::new()))
+    };
+
+    let status = match outcome {
+        Ok((status, _)) if status.success() => match std::fs::rename(&partial_path, &job.output) {
+            Ok(()) => JobStatus::Done,
+            Err(e) => JobStatus::Failed(e.to_string()),
+        },
+        Ok((status, output)) => {
+            if !output.is_empty() {
+                log::error!(
+                    "{} output for {}:\n{}",
+                    QUANTIZE_BINARY_NAME,
+                    job.quant,
+                    output
+                );
+            }
+            JobStatus::Failed(format!("{} failed with {}", QUANTIZE_BINARY_NAME, status))
+        }
+        Err(e) => JobStatus::Failed(format!("Failed to run {} - {}", QUANTIZE_BINARY_NAME, e)),
+    };
+
+    if !matches!(status, JobStatus::Done) {
+        let _ = std::fs::remove_file(&partial_path);
+    }
+    result(status)
+}
+
+fn print_summary(jobs: &[QuantizeJob], results: &[JobResult]) {
+    println!();
+    println!(
+        "{:<12} {:<10} {:>10} {:>10} OUTPUT",
+        "QUANT", "STATUS", "SIZE", "TIME"
+    );
+
+    for (job, result) in jobs.iter().zip(results) {
+        let status = match &result.status {
+            JobStatus::Done => "done",
+            JobStatus::UpToDate => "up to date",
+            JobStatus::Failed(_) => "failed",
+        };
+        let size = match result.status {
+            JobStatus::Failed(_) => "-".to_string(),
+            _ => std::fs::metadata(&job.output)
+                .map(|metadata| format_size(metadata.len()))
+                .unwrap_or_else(|_| "-".to_string()),
+        };
+        let time = match result.status {
+            JobStatus::Done => format_duration(result.duration),
+            _ => "-".to_string(),
+        };
+
+        println!(
+            "{:<12} {:<10} {:>10} {:>10} {}",
+            job.quant,
+            status,
+            size,
+            time,
+            job.output.display()
+        );
+        if let JobStatus::Failed(error) = &result.status {
+            println!("  {}", error);
+        }
+    }
+}
+
+/// Formats duration like `12.3s` or `4m 05s`.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs_f64();
+    if seconds < 60.0 {
+        format!("{:.1}s", seconds)
+    } else {
+        let seconds = duration.as_secs();
+        format!("{}m {:02}s", seconds / 60, seconds % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external_tools::llama_quantize::parse_quant_types;
+
+    fn quant_types() -> Vec<QuantType> {
+        parse_quant_types(
+            "Allowed quantization types:
+   7  or  Q8_0    :  7.96G, +0.0026 ppl @ Llama-3-8B
+  15  or  Q4_K    : alias for Q4_K_M
+  14  or  Q4_K_S  :  4.37G, +0.2689 ppl @ Llama-3-8B
+  15  or  Q4_K_M  :  4.58G, +0.1754 ppl @ Llama-3-8B
+",
+        )
+    }
+
+    #[test]
+    fn test_find_quant_type() {
+        let quant_types = quant_types();
+
+        assert_eq!(
+            find_quant_type(&quant_types, "q4_k_s").unwrap().name,
+            "Q4_K_S"
+        );
+        assert_eq!(
+            find_quant_type(&quant_types, "Q4_K").unwrap().name,
+            "Q4_K_M"
+        );
+        assert_eq!(find_quant_type(&quant_types, "15").unwrap().name, "Q4_K_M");
+        assert!(find_quant_type(&quant_types, "Q4_K_X").is_err());
+    }
+
+    #[test]
+    fn test_selected_quant_types() {
+        let quant_types = quant_types();
+        let presets = BTreeMap::from([(
+            "small".to_string(),
+            vec!["Q4_K_M".to_string(), "Q4_K_S".to_string()],
+        )]);
+
+        let names = |quants: &[&str], preset| -> Vec<String> {
+            let quants: Vec<String> = quants.iter().map(|quant| quant.to_string()).collect();
+            selected_quant_types(&quant_types, &presets, &quants, preset)
+                .unwrap()
+                .into_iter()
+                .map(|quant_type| quant_type.name.clone())
+                .collect()
+        };
+
+        assert_eq!(names(&["q8_0", "Q4_K"], None), vec!["Q8_0", "Q4_K_M"]);
+        assert_eq!(
+            names(&["Q8_0", "Q4_K"], Some("small")),
+            vec!["Q8_0", "Q4_K_M", "Q4_K_S"]
+        );
+        assert!(selected_quant_types(&quant_types, &presets, &[], None).is_err());
+        assert!(selected_quant_types(&quant_types, &presets, &[], Some("missing")).is_err());
+    }
+
+    #[test]
+    fn test_default_output_path() {
+        let quant_types = quant_types();
+        let output = |input: &str| {
+            default_output_path(Path::new(input), "Q4_K_M", &quant_types)
+                .to_string_lossy()
+                .to_string()
+        };
+
+        assert_eq!(
+            output("models/qwen3-8b-bf16.gguf"),
+            "models/qwen3-8b-q4_k_m.gguf"
+        );
+        assert_eq!(
+            output("models/qwen3-8b-q8_0.gguf"),
+            "models/qwen3-8b-q4_k_m.gguf"
+        );
+        assert_eq!(
+            output("models/qwen3-8b.gguf"),
+            "models/qwen3-8b-q4_k_m.gguf"
+        );
+    }
+
+    #[test]
+    fn test_format_bits_per_weight() {
+        let quant_types = parse_quant_types(
+            "Allowed quantization types:
+  19  or  IQ2_XXS :  2.06 bpw quantization
+   7  or  Q8_0    :  7.96G, +0.0026 ppl @ Llama-3-8B
+  15  or  Q4_K_M  :  4.58G, +0.1754 ppl @ Llama-3-8B
+",
+        );
+        let formatted: Vec<String> = quant_types.iter().map(format_bits_per_weight).collect();
+        assert_eq!(formatted, vec!["2.06", "~8.50", "-"]);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(12_345)), "12.3s");
+        assert_eq!(format_duration(Duration::from_secs(245)), "4m 05s");
+    }
 }
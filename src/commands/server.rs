@@ -1,19 +1,52 @@
-use clap::Parser;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{Local, Utc};
+use clap::{Args, Parser, Subcommand};
 
 use crate::{
-    commands::Result,
+    commands::{Result, RuntimeError},
     config::{Config, Profile},
+    external_tools::llama_server::{LlamaServer, ServerOptions},
+    instance::Instance,
+    models::resolve_model_path,
+    server::{
+        health::{HealthMonitor, HealthState},
+        logs,
+        ports::{self, PortSelection},
+        process::{self, exit_code},
+        restart::{DEFAULT_MAX_RESTARTS, RestartPolicy, RestartRecord, RestartState},
+        state::{ServerRegistry, ServerState, ServerStatus, validate_id},
+        supervisor,
+    },
 };
 
+const SERVER_BINARY_NAME: &str = "llama-server";
+/// ID of the detached server without a model.
+const DEFAULT_SERVER_ID: &str = "server";
+/// Amount of the latest restarts shown by `server list`.
+const LISTED_RESTARTS: usize = 3;
+
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct ServerCommand {
+    #[command(subcommand)]
+    pub action: Option<ServerAction>,
+
+    #[command(flatten)]
+    pub server: ServerArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct ServerArgs {
     #[arg(long, short)]
-    /// Model to use
+    /// Model to use - path to GGUF file, model reference (`qwen3:8b-q4_k_m`), alias or name
     pub model: Option<String>,
 
-    #[arg(long, default_value_t = 8080)]
-    /// Port to listen on
-    pub port: u16,
+    #[arg(long, default_value_t = PortSelection::Auto)]
+    /// Port to listen on, or `auto` to pick a free one from `server.port_range`
+    pub port: PortSelection,
 
     #[arg(long, default_value = "127.0.0.1")]
     /// Address to bind to
@@ -26,14 +59,632 @@ pub struct ServerCommand {
     #[arg(long, short)]
     /// Number of layers to offload to GPU
     pub gpu_layers: Option<u32>,
+
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    /// Additional arguments passed to `llama-server`, like `--flash-attn on`
+    pub extra_args: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct WaitArgs {
+    #[arg(long, short)]
+    /// Wait until the model is loaded and the server is ready to accept requests
+    pub wait: bool,
+
+    #[arg(long, default_value_t = 300, requires = "wait")]
+    /// Time in seconds to wait for the server to get ready
+    pub timeout: u64,
+}
+
+#[derive(Debug, Args)]
+pub struct RestartArgs {
+    #[arg(long, value_enum, default_value_t = RestartPolicy::Never)]
+    /// What to do when the server exits by itself
+    pub restart: RestartPolicy,
+
+    #[arg(long, default_value_t = DEFAULT_MAX_RESTARTS)]
+    /// Maximum amount of restarts in a row with `on-failure` policy
+    pub max_restarts: u32,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ServerAction {
+    /// Start llama-server in the background
+    Start {
+        #[arg(long, short)]
+        /// ID of the server, derived from the model name by default
+        name: Option<String>,
+
+        #[command(flatten)]
+        wait: WaitArgs,
+
+        #[command(flatten)]
+        restart: RestartArgs,
+
+        #[command(flatten)]
+        server: ServerArgs,
+    },
+    /// List the servers started in the background
+    List,
+    /// Stop the server started in the background
+    Stop {
+        /// ID of the server
+        id: String,
+    },
+    /// Restart the server started in the background, with the same arguments
+    Restart {
+        /// ID of the server
+        id: String,
+
+        #[command(flatten)]
+        wait: WaitArgs,
+    },
+    /// Print the log of the server started in the background
+    Logs {
+        /// ID of the server
+        id: String,
+
+        #[arg(long, short)]
+        /// Keep printing the log as it's written, until the server stops
+        follow: bool,
+
+        #[arg(long, short = 'n', default_value_t = 50)]
+        /// Amount of the last lines to print
+        lines: usize,
+    },
+    /// Supervise the server, used internally by `server start`
+    #[command(hide = true)]
+    Supervise {
+        #[arg(long)]
+        runtime_dir: PathBuf,
+
+        id: String,
+    },
+}
+
+impl ServerCommand {
+    /// Returns the runtime directory and the ID of the server if this is the supervisor process.
+    /// Supervisor gets everything it needs from the state file, so it does not load the configuration.
+    pub fn supervised_server(&self) -> Option<(&Path, &str)> {
+        match &self.action {
+            Some(ServerAction::Supervise { runtime_dir, id }) => Some((runtime_dir, id)),
+            _ => None,
+        }
+    }
+}
+
+pub fn run(
+    args: ServerCommand,
+    config: &Config,
+    _profile: &Profile,
+    instance: &Instance,
+) -> Result<()> {
+    let registry = ServerRegistry::new(&config.paths.runtime_dir);
+
+    match args.action {
+        None => run_foreground(args.server, config, &registry, instance),
+        Some(ServerAction::Start {
+            name,
+            wait,
+            restart,
+            server,
+        }) => start(&registry, name, wait, restart, server, config, instance),
+        Some(ServerAction::List) => list(&registry),
+        Some(ServerAction::Stop { id }) => stop(&registry, &id),
+        Some(ServerAction::Restart { id, wait }) => restart(&registry, &id, wait),
+        Some(ServerAction::Logs { id, follow, lines }) => show_logs(&registry, &id, follow, lines),
+        Some(ServerAction::Supervise { runtime_dir, id }) => supervise(&runtime_dir, &id),
+    }
+}
+
+/// Runs the supervisor of a detached server.
+pub fn supervise(runtime_dir: &Path, id: &str) -> Result<()> {
+    require_detached_support()?;
+    supervisor::run(&ServerRegistry::new(runtime_dir), id)
+}
+
+/// Fails on platforms without detached servers - supervisor relies on unix sessions and signals
+/// to run the server in the background and to stop it.
+fn require_detached_support() -> Result<()> {
+    if cfg!(unix) {
+        return Ok(());
+    }
+    Err(RuntimeError::new(
+        "Detached servers are only supported on unix, run `llama-mgr server` in the foreground instead"
+            .to_string(),
+        exitcode::UNAVAILABLE as u8,
+    ))
+}
+
+/// Resolves the model, allocates the port and maps the arguments to `llama-server` options.
+fn server_options(
+    args: ServerArgs,
+    config: &Config,
+    registry: &ServerRegistry,
+) -> Result<ServerOptions> {
+    let model = match &args.model {
+        Some(model) => Some(std::path::absolute(resolve_model_path(config, model)?)?),
+        None => None,
+    };
+    if let Some(model) = &model {
+        log::info!("Using model: {}", model.display());
+    }
+
+    let port = ports::allocate(registry, &args.address, args.port, config.server.port_range)?;
+
+    Ok(ServerOptions {
+        model,
+        address: args.address,
+        port,
+        ctx_size: args.ctx_size,
+        gpu_layers: args.gpu_layers,
+        extra_args: args.extra_args,
+    })
 }
 
-pub fn run(args: ServerCommand, config: &Config, profile: &Profile) -> Result<()> {
+fn run_foreground(
+    args: ServerArgs,
+    config: &Config,
+    registry: &ServerRegistry,
+    instance: &Instance,
+) -> Result<()> {
+    let tool: LlamaServer = instance.tool(SERVER_BINARY_NAME)?;
+    clean_stale(registry)?;
+
+    // Server is registered like the detached ones, so it's listed and its port is not picked again
+    let model = args.model.clone();
+    let lock = registry.lock()?;
+    let options = server_options(args, config, registry)?;
+    let own_pid = std::process::id();
+    let mut state = ServerState {
+        id: registry.unique_id(&default_id(options.model.as_deref()))?,
+        status: ServerStatus::Starting,
+        instance: instance.name.clone(),
+        model,
+        address: options.address.clone(),
+        port: options.port,
+        binary: std::path::absolute(tool.path())?,
+        args: options.args(),
+        working_dir: std::env::current_dir()?,
+        supervisor_pid: own_pid,
+        supervisor_start_time: process::start_time(own_pid),
+        server_pid: None,
+        server_start_time: None,
+        started_at: Utc::now(),
+        exit_code: None,
+        health: None,
+        restart: RestartState::default(),
+        foreground: true,
+    };
+    registry.save(&state)?;
+    drop(lock);
+
     log::info!(
-        "Server command called with args: {:?}, config: {:?}, profile: {:?}",
-        args,
-        config,
-        profile
+        "Starting {} of instance '{}' on {}:{} as server '{}'",
+        SERVER_BINARY_NAME,
+        instance.name,
+        options.address,
+        options.port,
+        state.id
+    );
+
+    let mut command = tool.server_command(&options);
+    process::isolate_signals(&mut command);
+
+    let mut child = command.spawn().map_err(|e| {
+        let _ = registry.remove(&state.id);
+        RuntimeError::new(
+            format!("Failed to run {} - {}", SERVER_BINARY_NAME, e),
+            exitcode::SOFTWARE as u8,
+        )
+    })?;
+
+    state.status = ServerStatus::Running;
+    state.server_pid = Some(child.id());
+    state.server_start_time = process::start_time(child.id());
+    if let Err(e) = registry.save(&state) {
+        log::warn!("Failed to record server '{}': {}", state.id, e);
+    }
+
+    let status = process::wait_forwarding_signals(&mut child);
+    if let Err(e) = registry.remove(&state.id) {
+        log::warn!(
+            "Failed to remove server '{}' from the list: {}",
+            state.id,
+            e
+        );
+    }
+    let status = status?;
+    match exit_code(status) {
+        0 => Ok(()),
+        code => Err(RuntimeError::new(
+            format!("{} exited with {}", SERVER_BINARY_NAME, status),
+            code,
+        )),
+    }
+}
+
+fn start(
+    registry: &ServerRegistry,
+    name: Option<String>,
+    wait: WaitArgs,
+    restart: RestartArgs,
+    args: ServerArgs,
+    config: &Config,
+    instance: &Instance,
+) -> Result<()> {
+    require_detached_support()?;
+    clean_stale(registry)?;
+
+    let tool: LlamaServer = instance.tool(SERVER_BINARY_NAME)?;
+    let binary = std::path::absolute(tool.path())?;
+    let model = args.model.clone();
+    // ID and port are reserved by saving the state before the lock is released
+    let lock = registry.lock()?;
+    let options = server_options(args, config, registry)?;
+
+    let id = match name {
+        Some(name) => {
+            validate_id(&name)?;
+            if let Some(existing) = registry.load(&name)?
+                && existing.status.is_active()
+            {
+                return Err(RuntimeError::new(
+                    format!(
+                        "Server '{}' is already running, stop it first or choose other name",
+                        name
+                    ),
+                    exitcode::USAGE as u8,
+                ));
+            }
+            name
+        }
+        None => registry.unique_id(&default_id(options.model.as_deref()))?,
+    };
+
+    let mut state = ServerState {
+        id,
+        status: ServerStatus::Starting,
+        instance: instance.name.clone(),
+        model,
+        address: options.address.clone(),
+        port: options.port,
+        binary,
+        args: options.args(),
+        working_dir: std::env::current_dir()?,
+        supervisor_pid: 0,
+        supervisor_start_time: None,
+        server_pid: None,
+        server_start_time: None,
+        started_at: Utc::now(),
+        exit_code: None,
+        health: None,
+        restart: RestartState::new(restart.restart, restart.max_restarts),
+        foreground: false,
+    };
+    supervisor::reserve(registry, &mut state)?;
+    drop(lock);
+
+    let state = supervisor::spawn(registry, state)?;
+    println!(
+        "Started server '{}' on http://{}:{} (PID {})",
+        state.id,
+        state.address,
+        state.port,
+        state.server_pid.unwrap_or(state.supervisor_pid)
+    );
+    println!("Logs: {}", registry.log_path(&state.id).display());
+    wait_until_ready(registry, &state, &wait)
+}
+
+/// Waits for the server to get ready, if requested. Fails with the tail of the server log
+/// if the server exits or does not get ready in time.
+fn wait_until_ready(registry: &ServerRegistry, state: &ServerState, wait: &WaitArgs) -> Result<()> {
+    if !wait.wait {
+        return Ok(());
+    }
+
+    println!("Waiting for server '{}' to get ready...", state.id);
+    let monitor = HealthMonitor::new(&state.address, state.port, state.live_server_pid());
+    let report = monitor.wait_until_ready(Duration::from_secs(wait.timeout));
+    let problem = match report.state {
+        HealthState::Ready => {
+            println!("Server '{}' is ready", state.id);
+            return Ok(());
+        }
+        HealthState::Exited => "exited before getting ready".to_string(),
+        HealthState::Loading | HealthState::Error => {
+            let mut problem = format!("did not get ready in {}s", wait.timeout);
+            if let Some(message) = report.message {
+                problem.push_str(&format!(" ({})", message));
+            }
+            problem
+        }
+    };
+
+    Err(supervisor::failure_error(
+        registry,
+        &state.id,
+        &problem,
+        exitcode::UNAVAILABLE as u8,
+    ))
+}
+
+/// Default ID of the server, like `qwen3-8b-q4_k_m` for `Qwen3-8B-Q4_K_M.gguf`.
+fn default_id(model: Option<&Path>) -> String {
+    model
+        .and_then(Path::file_stem)
+        .map(|stem| {
+            stem.to_string_lossy()
+                .to_lowercase()
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                        c
+                    } else {
+                        '-'
+                    }
+                })
+                .collect::<String>()
+        })
+        .filter(|id| validate_id(id).is_ok())
+        .unwrap_or_else(|| DEFAULT_SERVER_ID.to_string())
+}
+
+/// Removes the entries of servers that died without their supervisor noticing.
+/// Error of the commands that only work with detached servers.
+fn foreground_error(state: &ServerState) -> RuntimeError {
+    RuntimeError::new(
+        format!(
+            "Server '{}' runs in the foreground (PID {}), manage it in its terminal or stop it with `llama-mgr server stop {}`",
+            state.id, state.supervisor_pid, state.id
+        ),
+        exitcode::USAGE as u8,
+    )
+}
+
+fn clean_stale(registry: &ServerRegistry) -> Result<()> {
+    for state in registry.clean_stale()? {
+        log::warn!(
+            "Removed stale entry of server '{}', its supervisor (PID {}) is gone",
+            state.id,
+            state.supervisor_pid
+        );
+    }
+    Ok(())
+}
+
+fn list(registry: &ServerRegistry) -> Result<()> {
+    clean_stale(registry)?;
+
+    let states = registry.list()?;
+    if states.is_empty() {
+        println!("No servers running");
+        return Ok(());
+    }
+
+    println!(
+        "{:<24} {:<12} {:>8} {:<22} {:<12} {:<10} {:>8} MODEL",
+        "ID", "STATUS", "PID", "ADDRESS", "INSTANCE", "UPTIME", "RESTARTS"
+    );
+    for state in states {
+        let status = match (state.status, state.exit_code, state.health) {
+            (ServerStatus::Exited, Some(code), _) => format!("exited({})", code),
+            (ServerStatus::Failed, Some(code), _) => format!("failed({})", code),
+            (ServerStatus::Running, _, _) if state.foreground => "foreground".to_string(),
+            (ServerStatus::Running, _, Some(health)) => health.to_string(),
+            (status, _, _) => status.to_string(),
+        };
+        let pid = state
+            .server_pid
+            .map(|pid| pid.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        let uptime = if state.status.is_active() {
+            format_uptime(Utc::now() - state.started_at)
+        } else {
+            "-".to_string()
+        };
+
+        println!(
+            "{:<24} {:<12} {:>8} {:<22} {:<12} {:<10} {:>8} {}",
+            state.id,
+            status,
+            pid,
+            format!("{}:{}", state.address, state.port),
+            state.instance,
+            uptime,
+            state.restart.count,
+            state.model.as_deref().unwrap_or("-")
+        );
+        if !state.restart.history.is_empty() {
+            println!("  restarts: {}", format_restarts(&state.restart.history));
+        }
+    }
+    Ok(())
+}
+
+/// Formats the latest restarts like `14:02:11 (exit 137), 14:02:13 (exit 137)`, the latest one last.
+fn format_restarts(history: &[RestartRecord]) -> String {
+    let mut restarts: Vec<String> = history
+        .iter()
+        .rev()
+        .take(LISTED_RESTARTS)
+        .map(|record| {
+            format!(
+                "{} (exit {})",
+                record.at.with_timezone(&Local).format("%H:%M:%S"),
+                record.exit_code
+            )
+        })
+        .collect();
+    restarts.reverse();
+
+    let older = history.len().saturating_sub(LISTED_RESTARTS);
+    if older > 0 {
+        restarts.insert(0, format!("{} earlier", older));
+    }
+    restarts.join(", ")
+}
+
+/// Formats uptime like `45s`, `12m 05s` or `3h 07m`.
+fn format_uptime(uptime: chrono::TimeDelta) -> String {
+    let seconds = uptime.num_seconds().max(0);
+    if seconds < 60 {
+        format!("{}s", seconds)
+    } else if seconds < 3600 {
+        format!("{}m {:02}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60)
+    }
+}
+
+fn stop(registry: &ServerRegistry, id: &str) -> Result<()> {
+    require_detached_support()?;
+    let state = registry.get(id)?;
+    if state.status.is_active() && !supervisor::stop(&state) {
+        log::warn!(
+            "Server '{}' did not stop in {}s and has been killed",
+            id,
+            supervisor::STOP_TIMEOUT.as_secs()
+        );
+    }
+
+    registry.remove(id)?;
+    println!("Stopped server '{}'", id);
+    Ok(())
+}
+
+fn restart(registry: &ServerRegistry, id: &str, wait: WaitArgs) -> Result<()> {
+    require_detached_support()?;
+    let mut state = registry.get(id)?;
+    if state.foreground {
+        return Err(foreground_error(&state));
+    }
+    if state.status.is_active() && !supervisor::stop(&state) {
+        log::warn!(
+            "Server '{}' did not stop in {}s and has been killed",
+            id,
+            supervisor::STOP_TIMEOUT.as_secs()
+        );
+    }
+
+    // Port could have been taken while the server was down
+    let lock = registry.lock()?;
+    if let Some(owner) = ports::port_owner(registry, &state.address, state.port, Some(id))? {
+        // Entry is kept with its arguments and restart history, so it can be restarted later.
+        // Killed supervisor could not record that the server has stopped.
+        let mut current = registry.load(id)?.unwrap_or(state);
+        if current.status.is_active() {
+            current.status = ServerStatus::Stopped;
+            current.server_pid = None;
+            current.server_start_time = None;
+            current.health = None;
+            registry.save(&current)?;
+        }
+        return Err(RuntimeError::new(
+            format!(
+                "Cannot restart server '{}', port {} is now used by {}, restart it again once the port is free",
+                id, current.port, owner
+            ),
+            exitcode::UNAVAILABLE as u8,
+        ));
+    }
+
+    supervisor::reserve(registry, &mut state)?;
+    drop(lock);
+
+    let state = supervisor::spawn(registry, state)?;
+    println!(
+        "Restarted server '{}' on http://{}:{} (PID {})",
+        state.id,
+        state.address,
+        state.port,
+        state.server_pid.unwrap_or(state.supervisor_pid)
     );
+    wait_until_ready(registry, &state, &wait)
+}
+
+fn show_logs(registry: &ServerRegistry, id: &str, follow: bool, lines: usize) -> Result<()> {
+    let state = registry.get(id)?;
+    if state.foreground {
+        return Err(foreground_error(&state));
+    }
+    let log_path = registry.log_path(&state.id);
+    if !log_path.exists() {
+        return Err(RuntimeError::new(
+            format!("Server '{}' has no log yet", id),
+            exitcode::NOINPUT as u8,
+        ));
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    for line in logs::tail(&log_path, lines)? {
+        writeln!(stdout, "{}", line)?;
+    }
+
+    if follow {
+        // Log is followed as long as the same server keeps running
+        let is_running = || {
+            registry.load(id).ok().flatten().is_some_and(|current| {
+                current.status.is_active()
+                    && current.supervisor_pid == state.supervisor_pid
+                    && !current.is_stale()
+            })
+        };
+        logs::follow(&log_path, &mut stdout, is_running)?;
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_id() {
+        assert_eq!(
+            default_id(Some(Path::new("/models/Qwen3-8B-Q4_K_M.gguf"))),
+            "qwen3-8b-q4_k_m"
+        );
+        assert_eq!(
+            default_id(Some(Path::new("/models/model name+v2.gguf"))),
+            "model-name-v2"
+        );
+        assert_eq!(default_id(None), "server");
+    }
+
+    #[test]
+    fn test_format_uptime() {
+        assert_eq!(format_uptime(chrono::TimeDelta::seconds(45)), "45s");
+        assert_eq!(format_uptime(chrono::TimeDelta::seconds(725)), "12m 05s");
+        assert_eq!(format_uptime(chrono::TimeDelta::seconds(11220)), "3h 07m");
+    }
+
+    #[test]
+    fn test_format_restarts() {
+        let record = |time: &str, exit_code| RestartRecord {
+            at: chrono::NaiveTime::parse_from_str(time, "%H:%M:%S")
+                .map(|time| Local::now().with_time(time).unwrap().with_timezone(&Utc))
+                .unwrap(),
+            exit_code,
+        };
+
+        let history = vec![record("14:02:11", 137), record("14:02:13", 1)];
+        assert_eq!(
+            format_restarts(&history),
+            "14:02:11 (exit 137), 14:02:13 (exit 1)"
+        );
+
+        let history = vec![
+            record("14:02:11", 137),
+            record("14:02:13", 137),
+            record("14:02:17", 137),
+            record("14:02:25", 0),
+            record("14:02:41", 2),
+        ];
+        assert_eq!(
+            format_restarts(&history),
+            "2 earlier, 14:02:17 (exit 137), 14:02:25 (exit 0), 14:02:41 (exit 2)"
+        );
+    }
+}
@@ -1,8 +1,9 @@
 use clap::Parser;
 
 use crate::{
-    commands::Result,
-    config::{Config, Profile},
+    commands::{Result, RuntimeError, instance::remove_instance},
+    config::Config,
+    instance::Instance,
 };
 
 #[derive(Debug, Parser)]
@@ -12,12 +13,13 @@ pub struct UninstallCommand {
     pub force: bool,
 }
 
-pub fn run(args: UninstallCommand, config: &Config, profile: &Profile) -> Result<()> {
-    log::info!(
-        "Uninstall command called with args: {:?}, config: {:?}, profile: {:?}",
-        args,
-        config,
-        profile
-    );
-    Ok(())
+pub fn run(args: UninstallCommand, config: &Config, instance: &Instance) -> Result<()> {
+    if !instance.exists() {
+        return Err(RuntimeError::new(
+            format!("Instance '{}' is not installed", instance.name),
+            exitcode::NOINPUT as u8,
+        ));
+    }
+
+    remove_instance(config, instance, args.force)
 }
@@ -0,0 +1,595 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::{Component, Path, PathBuf};
+
+use toml::{Table, Value};
+
+use crate::config::validate::{Kind, SCHEMA};
+use crate::config::{Config, migrate};
+use crate::error::{Result, RuntimeError};
+
+pub const SYSTEM_CONFIG_PATH: &str = "/etc/llama-mgr/config.toml";
+pub const PROJECT_CONFIG_FILE_NAME: &str = ".llama-mgr.toml";
+pub const ENV_PREFIX: &str = "LLAMA_MGR_";
+/// Separates the key path segments in environment variable names,
+/// single underscore is a part of the key name (`LLAMA_MGR_PATHS__LLAMA_DIR`).
+const ENV_KEY_SEPARATOR: &str = "__";
+
+/// Place where the configuration value came from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigOrigin {
+    Default,
+    System(PathBuf),
+    User(PathBuf),
+    Project(PathBuf),
+    Environment(String),
+}
+
+impl ConfigOrigin {
+    /// Path of the file the value has been loaded from, if any.
+    pub fn file(&self) -> Option<&Path> {
+        match self {
+            ConfigOrigin::System(path) | ConfigOrigin::User(path) | ConfigOrigin::Project(path) => {
+                Some(path)
+            }
+            ConfigOrigin::Default | ConfigOrigin::Environment(_) => None,
+        }
+    }
+}
+
+impl Display for ConfigOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigOrigin::Default => write!(f, "default"),
+            ConfigOrigin::System(path) => write!(f, "system ({})", path.display()),
+            ConfigOrigin::User(path) => write!(f, "user ({})", path.display()),
+            ConfigOrigin::Project(path) => write!(f, "project ({})", path.display()),
+            ConfigOrigin::Environment(name) => write!(f, "environment ({})", name),
+        }
+    }
+}
+
+/// Locations of all the configuration layers, from the lowest to the highest priority.
+#[derive(Debug, Clone)]
+pub struct ConfigSources {
+    pub system: PathBuf,
+    pub user: PathBuf,
+    /// Directory where the lookup of project configuration starts.
+    pub project_search_start: PathBuf,
+    pub env: Vec<(String, String)>,
+}
+
+impl ConfigSources {
+    /// Default sources for given user configuration file.
+    pub fn new(user: PathBuf) -> Self {
+        Self {
+            system: PathBuf::from(SYSTEM_CONFIG_PATH),
+            user,
+            project_search_start: std::env::current_dir().unwrap_or_default(),
+            env: std::env::vars().collect(),
+        }
+    }
+
+    /// Returns the nearest project configuration file in the search start directory
+    /// or any of its parents, unless it's also used as user configuration.
+    pub fn project(&self) -> Option<PathBuf> {
+        self.project_search_start
+            .ancestors()
+            .map(|dir| dir.join(PROJECT_CONFIG_FILE_NAME))
+            .find(|path| path.is_file())
+            .filter(|path| !is_same_file(path, &self.user))
+    }
+
+    /// Returns `true` if any of the configuration files exists.
+    pub fn any_file_exists(&self) -> bool {
+        self.system.is_file() || self.user.is_file() || self.project().is_some()
+    }
+
+    /// Returns the existing configuration files, from the lowest to the highest priority.
+    pub fn files(&self) -> Vec<ConfigOrigin> {
+        [
+            Some(ConfigOrigin::System(self.system.clone())),
+            Some(ConfigOrigin::User(self.user.clone())),
+            self.project().map(ConfigOrigin::Project),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|origin| origin.file().is_some_and(Path::is_file))
+        .collect()
+    }
+
+    /// Returns the configuration values set with environment variables,
+    /// as (variable name, key path, value) tuples.
+    pub fn env_values(&self) -> Vec<(&str, Vec<String>, Value)> {
+        self.env
+            .iter()
+            .filter_map(|(name, value)| {
+                let key = name.strip_prefix(ENV_PREFIX)?;
+                let path: Vec<String> = key
+                    .split(ENV_KEY_SEPARATOR)
+                    .map(|segment| segment.to_lowercase())
+                    .collect();
+                if path.iter().any(String::is_empty) {
+                    return None;
+                }
+
+                let value = parse_value(&path.join("."), value);
+                Some((name.as_str(), path, value))
+            })
+            .collect()
+    }
+}
+
+/// Configuration merged from all the layers.
+#[derive(Debug)]
+pub struct LoadedConfig {
+    pub config: Config,
+    /// Merged configuration in raw form.
+    pub table: Table,
+    /// Origin of every value, keyed by dotted path (`paths.llama_dir`).
+    pub origins: BTreeMap<String, ConfigOrigin>,
+}
+
+impl LoadedConfig {
+    /// Returns all the effective values with their origins, sorted by key.
+    pub fn values_with_origins(&self) -> Vec<(String, &Value, &ConfigOrigin)> {
+        let mut values = Vec::new();
+        flatten(&self.table, "", &mut values);
+
+        values
+            .into_iter()
+            .map(|(key, value)| {
+                let origin = self.origins.get(&key).unwrap_or(&ConfigOrigin::Default);
+                (key, value, origin)
+            })
+            .collect()
+    }
+}
+
+/// Loads and merges the configuration layers: built-in defaults, system file, user file,
+/// project file and environment variables.
+pub fn load(sources: &ConfigSources) -> Result<LoadedConfig> {
+    let mut table = match Value::try_from(Config::default()) {
+        Ok(Value::Table(table)) => table,
+        _ => {
+            return Err(RuntimeError::new(
+                "Failed to serialize default config".to_string(),
+                exitcode::SOFTWARE as u8,
+            ));
+        }
+    };
+    let mut origins = BTreeMap::new();
+
+    for origin in sources.files() {
+        let path = origin.file().expect("file origin always has a path");
+        log::debug!("Loading configuration layer: {}", origin);
+        let mut layer = read_table(path)?;
+        let version = migrate::migrate(&mut layer).map_err(|e| {
+            RuntimeError::new(
+                format!("Invalid config file {}: {}", path.display(), e.message),
+                exitcode::CONFIG as u8,
+            )
+        })?;
+        // Migration in memory is lossless, `config validate` suggests upgrading the file
+        if version < migrate::CURRENT_SCHEMA_VERSION {
+            log::info!(
+                "Config file {} uses schema version {}, run `llama-mgr config migrate` to upgrade it",
+                path.display(),
+                version
+            );
+        }
+        merge(&mut table, layer, &origin, "", &mut origins);
+    }
+
+    for (name, path, value) in sources.env_values() {
+        let layer = env_layer(&path, value);
+        merge(
+            &mut table,
+            layer,
+            &ConfigOrigin::Environment(name.to_string()),
+            "",
+            &mut origins,
+        );
+    }
+
+    let mut config: Config = Value::Table(table.clone()).try_into().map_err(|e| {
+        RuntimeError::new(
+            format!("Invalid configuration: {}", e),
+            exitcode::CONFIG as u8,
+        )
+    })?;
+    resolve_paths(&mut config, &origins, sources)?;
+
+    Ok(LoadedConfig {
+        config,
+        table,
+        origins,
+    })
+}
+
+/// Expands `~` and environment variables in all the paths, and makes the relative ones
+/// absolute. Paths from configuration files are relative to the directory of the file,
+/// paths from environment variables are relative to the current directory, and the
+/// built-in defaults are relative to the user configuration file.
+fn resolve_paths(
+    config: &mut Config,
+    origins: &BTreeMap<String, ConfigOrigin>,
+    sources: &ConfigSources,
+) -> Result<()> {
+    let user_dir = sources.user.parent().unwrap_or(Path::new(""));
+
+    for (key, path) in config.paths.entries_mut() {
+        let key = join_key("paths", key);
+        let origin = origins.get(&key).unwrap_or(&ConfigOrigin::Default);
+        let base_dir = match origin {
+            ConfigOrigin::Environment(_) => sources.project_search_start.as_path(),
+            origin => origin.file().and_then(Path::parent).unwrap_or(user_dir),
+        };
+
+        let expanded = expand_path(path, sources).map_err(|e| {
+            RuntimeError::new(
+                format!("Failed to expand {} set in {}: {}", key, origin, e),
+                exitcode::CONFIG as u8,
+            )
+        })?;
+        *path = normalize_path(&base_dir.join(expanded));
+    }
+
+    Ok(())
+}
+
+/// Expands `~` and `$VAR`/`${VAR}` using the variables from configuration sources.
+fn expand_path(path: &Path, sources: &ConfigSources) -> std::result::Result<PathBuf, String> {
+    let lookup = |name: &str| {
+        sources
+            .env
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+    };
+
+    let path = path.to_string_lossy();
+    shellexpand::full_with_context(
+        &path,
+        || lookup("HOME"),
+        |name| lookup(name).map(Some).ok_or(std::env::VarError::NotPresent),
+    )
+    .map(|expanded| PathBuf::from(expanded.as_ref()))
+    .map_err(|e| e.to_string())
+}
+
+/// Removes `.` and `..` components without touching the file system,
+/// so the paths that don't exist yet can be normalized too.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push(component);
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+fn is_same_file(first: &Path, second: &Path) -> bool {
+    match (first.canonicalize(), second.canonicalize()) {
+        (Ok(first), Ok(second)) => first == second,
+        _ => false,
+    }
+}
+
+pub fn read_table(path: &Path) -> Result<Table> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        RuntimeError::new(
+            format!("Failed to read config file {}: {}", path.display(), e),
+            exitcode::NOINPUT as u8,
+        )
+    })?;
+
+    content.parse::<Table>().map_err(|e| {
+        RuntimeError::new(
+            format!("Failed to parse config file {}: {}", path.display(), e),
+            exitcode::CONFIG as u8,
+        )
+    })
+}
+
+/// Merges `layer` into `base`. Tables are merged recursively, all other values
+/// (including arrays) replace the previous ones.
+fn merge(
+    base: &mut Table,
+    layer: Table,
+    origin: &ConfigOrigin,
+    prefix: &str,
+    origins: &mut BTreeMap<String, ConfigOrigin>,
+) {
+    for (key, value) in layer {
+        let path = join_key(prefix, &key);
+
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base_table)), Value::Table(layer_table)) => {
+                merge(base_table, layer_table, origin, &path, origins);
+            }
+            (_, Value::Table(layer_table)) => {
+                remove_origins(origins, &path);
+                let mut table = Table::new();
+                merge(&mut table, layer_table, origin, &path, origins);
+                base.insert(key, Value::Table(table));
+            }
+            (_, value) => {
+                remove_origins(origins, &path);
+                origins.insert(path, origin.clone());
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Removes the origins of the value at `path` and all the values nested in it.
+fn remove_origins(origins: &mut BTreeMap<String, ConfigOrigin>, path: &str) {
+    let nested_prefix = format!("{}.", path);
+    origins.retain(|key, _| key != path && !key.starts_with(&nested_prefix));
+}
+
+pub fn join_key(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+/// Values from environment variables and command line are parsed as TOML values
+/// if possible (numbers, booleans, arrays), and treated as plain strings otherwise.
+/// Values of string keys from the schema are always kept as strings, so `branch = 1234` stays a string.
+pub fn parse_value(key: &str, value: &str) -> Value {
+    if let Some(Kind::String | Kind::Enum(_)) = SCHEMA.find(key) {
+        return Value::String(value.to_string());
+    }
+
+    format!("value = {}", value)
+        .parse::<Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+/// Builds a nested table with a single value at given path.
+pub fn env_layer(path: &[String], value: Value) -> Table {
+    let mut value = value;
+    for key in path.iter().skip(1).rev() {
+        let mut table = Table::new();
+        table.insert(key.clone(), value);
+        value = Value::Table(table);
+    }
+
+    let mut table = Table::new();
+    table.insert(path[0].clone(), value);
+    table
+}
+
+fn flatten<'a>(table: &'a Table, prefix: &str, values: &mut Vec<(String, &'a Value)>) {
+    for (key, value) in table {
+        let path = join_key(prefix, key);
+        match value {
+            Value::Table(nested) => flatten(nested, &path, values),
+            value => values.push((path, value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn sources(temp_dir: &TempDir, env: &[(&str, &str)]) -> ConfigSources {
+        let project_dir = temp_dir.path().join("project").join("nested");
+        std::fs::create_dir_all(&project_dir).unwrap();
+
+        ConfigSources {
+            system: temp_dir.path().join("system.toml"),
+            user: temp_dir.path().join("user.toml"),
+            project_search_start: project_dir,
+            env: env
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_load_defaults_only() {
+        let temp_dir = TempDir::new().unwrap();
+        let sources = sources(&temp_dir, &[]);
+        assert!(!sources.any_file_exists());
+
+        let loaded = load(&sources).unwrap();
+        assert_eq!(loaded.config.config.default_profile, "vulkan");
+        assert!(loaded.origins.is_empty());
+    }
+
+    #[test]
+    fn test_layers_priority() {
+        let temp_dir = TempDir::new().unwrap();
+        let sources = sources(&temp_dir, &[("LLAMA_MGR_CONFIG__INSTALL_RETENTION", "7")]);
+
+        std::fs::write(
+            &sources.system,
+            r#"
+            [config]
+            default_profile = "cpu"
+            install_retention = 2
+
+            [paths]
+            llama_dir = "/srv/llama"
+            models_dir = "/srv/models"
+            "#,
+        )
+        .unwrap();
+        std::fs::write(
+            &sources.user,
+            r#"
+            [paths]
+            models_dir = "/home/user/models"
+            "#,
+        )
+        .unwrap();
+        let project_config = temp_dir
+            .path()
+            .join("project")
+            .join(PROJECT_CONFIG_FILE_NAME);
+        std::fs::write(
+            &project_config,
+            r#"
+            [profiles.cpu]
+            cmake_args = ["-DGGML_CPU=ON", "-DGGML_NATIVE=OFF"]
+            "#,
+        )
+        .unwrap();
+
+        let loaded = load(&sources).unwrap();
+        let config = &loaded.config;
+        assert_eq!(config.config.default_profile, "cpu");
+        assert_eq!(config.config.install_retention, 7);
+        assert_eq!(config.paths.llama_dir, PathBuf::from("/srv/llama"));
+        assert_eq!(config.paths.models_dir, PathBuf::from("/home/user/models"));
+        assert_eq!(
+            config.profiles["cpu"].cmake_args,
+            vec!["-DGGML_CPU=ON", "-DGGML_NATIVE=OFF"]
+        );
+        assert!(config.profiles.contains_key("vulkan"));
+
+        assert_eq!(
+            loaded.origins["config.default_profile"],
+            ConfigOrigin::System(sources.system.clone())
+        );
+        assert_eq!(
+            loaded.origins["paths.models_dir"],
+            ConfigOrigin::User(sources.user.clone())
+        );
+        assert_eq!(
+            loaded.origins["profiles.cpu.cmake_args"],
+            ConfigOrigin::Project(project_config)
+        );
+        assert_eq!(
+            loaded.origins["config.install_retention"],
+            ConfigOrigin::Environment("LLAMA_MGR_CONFIG__INSTALL_RETENTION".to_string())
+        );
+
+        let values = loaded.values_with_origins();
+        let (_, _, origin) = values
+            .iter()
+            .find(|(key, _, _)| key == "profiles.vulkan.cmake_args")
+            .unwrap();
+        assert_eq!(**origin, ConfigOrigin::Default);
+    }
+
+    #[test]
+    fn test_env_values() {
+        assert_eq!(
+            parse_value("config.install_retention", "12"),
+            Value::Integer(12)
+        );
+        assert_eq!(parse_value("unknown", "true"), Value::Boolean(true));
+        assert_eq!(
+            parse_value("paths.models_dir", "/some/path"),
+            Value::String("/some/path".to_string())
+        );
+        assert_eq!(
+            parse_value("profiles.cpu.cmake_args", r#"["-DGGML_CPU=ON"]"#),
+            Value::Array(vec![Value::String("-DGGML_CPU=ON".to_string())])
+        );
+        // String keys stay strings, even if they look like other values
+        assert_eq!(
+            parse_value("profiles.cpu.branch", "1234"),
+            Value::String("1234".to_string())
+        );
+        assert_eq!(
+            parse_value("profiles.cpu.ref", "true"),
+            Value::String("true".to_string())
+        );
+
+        let temp_dir = TempDir::new().unwrap();
+        let sources = sources(
+            &temp_dir,
+            &[
+                ("LLAMA_MGR_PROFILES__CPU__BRANCH", "dev"),
+                ("LLAMA_MGR_PROFILES__X__BRANCH", "1234"),
+                ("LLAMA_MGR_", "ignored"),
+                ("OTHER_VARIABLE", "ignored"),
+            ],
+        );
+        let loaded = load(&sources).unwrap();
+        assert_eq!(loaded.config.profiles["cpu"].branch(), "dev");
+        assert_eq!(loaded.config.profiles["x"].branch(), "1234");
+    }
+
+    #[test]
+    fn test_invalid_layer() {
+        let temp_dir = TempDir::new().unwrap();
+        let sources = sources(&temp_dir, &[]);
+        std::fs::write(&sources.user, "[paths]\nmodels_dir = = ./models\n").unwrap();
+
+        let error = load(&sources).unwrap_err();
+        assert!(error.message.contains("user.toml"), "{}", error.message);
+    }
+
+    #[test]
+    fn test_resolve_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        let sources = sources(
+            &temp_dir,
+            &[
+                ("HOME", "/home/user"),
+                ("MODELS_ROOT", "/data"),
+                ("LLAMA_MGR_PATHS__MODELS_DIR", "$MODELS_ROOT/../models"),
+            ],
+        );
+        let loaded = load(&sources).unwrap();
+        assert_eq!(loaded.config.paths.llama_dir, temp_dir.path().join("llama"));
+        assert_eq!(loaded.config.paths.models_dir, PathBuf::from("/models"));
+
+        let project_dir = temp_dir.path().join("project");
+        std::fs::write(
+            project_dir.join(PROJECT_CONFIG_FILE_NAME),
+            "[paths]\nllama_dir = \"./instances\"\nmodels_dir = \"~/models\"\n",
+        )
+        .unwrap();
+        let sources = ConfigSources {
+            env: vec![
+                ("HOME".to_string(), "/home/user".to_string()),
+                (
+                    "LLAMA_MGR_PATHS__MODELS_DIR".to_string(),
+                    "models".to_string(),
+                ),
+            ],
+            ..sources
+        };
+        let loaded = load(&sources).unwrap();
+        assert_eq!(loaded.config.paths.llama_dir, project_dir.join("instances"));
+        assert_eq!(
+            loaded.config.paths.models_dir,
+            sources.project_search_start.join("models")
+        );
+
+        let sources = ConfigSources {
+            env: vec![(
+                "LLAMA_MGR_PATHS__MODELS_DIR".to_string(),
+                "$MISSING".to_string(),
+            )],
+            ..sources
+        };
+        let error = load(&sources).unwrap_err();
+        assert!(
+            error.message.contains("paths.models_dir"),
+            "{}",
+            error.message
+        );
+    }
+}
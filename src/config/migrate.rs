@@ -0,0 +1,223 @@
+use toml::Table;
+use toml_edit::{DocumentMut, Item};
+
+use crate::error::{Result, RuntimeError};
+
+pub const SCHEMA_VERSION_KEY: &str = "schema_version";
+/// Version of the configuration format used by this build.
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+/// Files without `schema_version` key have been created before the format was versioned.
+const UNVERSIONED_SCHEMA_VERSION: u32 = 1;
+
+/// Migrations edit the TOML document, so the comments and formatting of migrated files are kept.
+type Migration = fn(&mut DocumentMut) -> Result<()>;
+
+/// Migrations upgrading the configuration by a single version,
+/// the first one upgrades `UNVERSIONED_SCHEMA_VERSION` to the next one.
+const MIGRATIONS: &[Migration] = &[rename_profile_section];
+
+/// Returns the schema version of the configuration.
+pub fn schema_version(table: &Table) -> Result<u32> {
+    check_version(
+        table
+            .get(SCHEMA_VERSION_KEY)
+            .map(|value| value.as_integer()),
+    )
+}
+
+/// Returns the schema version of the configuration document.
+pub fn document_schema_version(document: &DocumentMut) -> Result<u32> {
+    check_version(document.get(SCHEMA_VERSION_KEY).map(Item::as_integer))
+}
+
+/// Validates the value of `schema_version` key - `None` if the key is missing,
+/// `Some(None)` if it's not an integer.
+fn check_version(value: Option<Option<i64>>) -> Result<u32> {
+    let version = match value {
+        None => return Ok(UNVERSIONED_SCHEMA_VERSION),
+        Some(version) => version.and_then(|version| u32::try_from(version).ok()),
+    };
+
+    match version {
+        Some(version) if version >= UNVERSIONED_SCHEMA_VERSION => {
+            if version > CURRENT_SCHEMA_VERSION {
+                return Err(RuntimeError::new(
+                    format!(
+                        "Configuration schema version {} is not supported, this version of llama-mgr supports up to {}",
+                        version, CURRENT_SCHEMA_VERSION
+                    ),
+                    exitcode::CONFIG as u8,
+                ));
+            }
+            Ok(version)
+        }
+        _ => Err(RuntimeError::new(
+            format!("`{}` must be a positive integer", SCHEMA_VERSION_KEY),
+            exitcode::CONFIG as u8,
+        )),
+    }
+}
+
+/// Upgrades the configuration document to the current schema version.
+/// Returns the version the configuration had before the migration.
+pub fn migrate_document(document: &mut DocumentMut) -> Result<u32> {
+    let version = document_schema_version(document)?;
+
+    for (index, migration) in MIGRATIONS
+        .iter()
+        .enumerate()
+        .skip((version - UNVERSIONED_SCHEMA_VERSION) as usize)
+    {
+        log::debug!(
+            "Migrating configuration from schema version {}",
+            index as u32 + UNVERSIONED_SCHEMA_VERSION
+        );
+        migration(document)?;
+    }
+
+    document[SCHEMA_VERSION_KEY] = toml_edit::value(i64::from(CURRENT_SCHEMA_VERSION));
+    Ok(version)
+}
+
+/// Upgrades the configuration loaded in memory to the current schema version.
+/// Returns the version the configuration had before the migration.
+pub fn migrate(table: &mut Table) -> Result<u32> {
+    let version = schema_version(table)?;
+    if version == CURRENT_SCHEMA_VERSION {
+        return Ok(version);
+    }
+
+    let invalid = |e: &dyn std::fmt::Display| {
+        RuntimeError::new(
+            format!("Failed to migrate configuration: {}", e),
+            exitcode::SOFTWARE as u8,
+        )
+    };
+    let mut document: DocumentMut = toml::to_string(table)
+        .map_err(|e| invalid(&e))?
+        .parse()
+        .map_err(|e| invalid(&e))?;
+    migrate_document(&mut document)?;
+    *table = document.to_string().parse().map_err(|e| invalid(&e))?;
+    Ok(version)
+}
+
+/// Version 1 has been documented with `[profile.<name>]` sections, while only
+/// `[profiles.<name>]` were actually read.
+fn rename_profile_section(document: &mut DocumentMut) -> Result<()> {
+    let Some(old_profiles) = document.remove("profile") else {
+        return Ok(());
+    };
+
+    let Ok(old_profiles) = old_profiles.into_table() else {
+        return Err(RuntimeError::new(
+            "`profile` must be a table of profiles".to_string(),
+            exitcode::CONFIG as u8,
+        ));
+    };
+
+    let profiles = document.entry("profiles").or_insert_with(|| {
+        let mut profiles = toml_edit::Table::new();
+        profiles.set_implicit(true);
+        Item::Table(profiles)
+    });
+    let Some(profiles) = profiles.as_table_like_mut() else {
+        return Err(RuntimeError::new(
+            "`profiles` must be a table of profiles".to_string(),
+            exitcode::CONFIG as u8,
+        ));
+    };
+
+    for (name, profile) in old_profiles {
+        if profiles.contains_key(&name) {
+            return Err(RuntimeError::new(
+                format!(
+                    "Profile '{}' is defined in both [profile.{}] and [profiles.{}] sections",
+                    name, name, name
+                ),
+                exitcode::CONFIG as u8,
+            ));
+        }
+        profiles.insert(&name, profile);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_version() {
+        assert_eq!(schema_version(&Table::new()).unwrap(), 1);
+        assert_eq!(
+            schema_version(&"schema_version = 2".parse().unwrap()).unwrap(),
+            2
+        );
+        assert!(schema_version(&"schema_version = 0".parse().unwrap()).is_err());
+        assert!(schema_version(&"schema_version = \"2\"".parse().unwrap()).is_err());
+        assert!(schema_version(&"schema_version = 99".parse().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_migrate_unversioned() {
+        let mut table: Table = r#"
+            [profile.cpu]
+            cmake_args = ["-DGGML_CPU=ON"]
+
+            [profiles.vulkan]
+            cmake_args = ["-DGGML_VULKAN=ON"]
+            "#
+        .parse()
+        .unwrap();
+
+        assert_eq!(migrate(&mut table).unwrap(), 1);
+        assert!(table.get("profile").is_none());
+        assert!(table["profiles"].get("cpu").is_some());
+        assert!(table["profiles"].get("vulkan").is_some());
+        assert_eq!(
+            table[SCHEMA_VERSION_KEY].as_integer(),
+            Some(CURRENT_SCHEMA_VERSION.into())
+        );
+
+        // Current configuration is not modified
+        let migrated = table.clone();
+        assert_eq!(migrate(&mut table).unwrap(), CURRENT_SCHEMA_VERSION);
+        assert_eq!(table, migrated);
+    }
+
+    #[test]
+    fn test_migrate_document_keeps_comments() {
+        let mut document: DocumentMut = r#"# Settings of llama-mgr
+[config]
+default_profile = "cpu" # used when no profile is given
+
+# CPU-only build
+[profile.cpu]
+cmake_args = ["-DGGML_CPU=ON"]
+"#
+        .parse()
+        .unwrap();
+
+        assert_eq!(migrate_document(&mut document).unwrap(), 1);
+        assert_eq!(
+            document.to_string(),
+            r#"schema_version = 2
+# Settings of llama-mgr
+[config]
+default_profile = "cpu" # used when no profile is given
+
+# CPU-only build
+[profiles.cpu]
+cmake_args = ["-DGGML_CPU=ON"]
+"#
+        );
+    }
+
+    #[test]
+    fn test_migrate_conflicting_profiles() {
+        let mut table: Table = "[profile.cpu]\n[profiles.cpu]\n".parse().unwrap();
+        assert!(migrate(&mut table).is_err());
+    }
+}
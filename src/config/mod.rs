@@ -0,0 +1,444 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use crate::error::{Result, RuntimeError};
+
+pub mod layers;
+pub mod migrate;
+pub mod validate;
+
+pub const DEFAULT_REPO_URL: &str = "https://github.com/ggml-org/llama.cpp.git";
+pub const DEFAULT_BRANCH: &str = "master";
+
+/// Configuration structure for llama-mgr
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Config {
+    /// Version of the configuration format, older files are migrated when loaded
+    #[serde(default = "default_schema_version")]
+    pub schema_version: u32,
+    pub config: ConfigSection,
+    pub paths: PathsSection,
+    #[serde(default)]
+    pub models: ModelsSection,
+    #[serde(default)]
+    pub server: ServerSection,
+    pub profiles: HashMap<String, Profile>,
+}
+
+/// Configuration section
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfigSection {
+    pub default_profile: String,
+    /// Amount of previous llama.cpp builds kept for rollback, including the current one
+    #[serde(default = "default_install_retention")]
+    pub install_retention: usize,
+}
+
+fn default_schema_version() -> u32 {
+    migrate::CURRENT_SCHEMA_VERSION
+}
+
+fn default_install_retention() -> usize {
+    3
+}
+
+/// Paths section
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PathsSection {
+    pub llama_dir: PathBuf,
+    pub models_dir: PathBuf,
+    /// Directory with the state and logs of detached servers
+    #[serde(default = "default_runtime_dir")]
+    pub runtime_dir: PathBuf,
+}
+
+/// Servers don't survive reboot, so their state goes to `$XDG_RUNTIME_DIR`, cleared on logout,
+/// when it's available.
+fn default_runtime_dir() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| PathBuf::from(dir).join("llama-mgr"))
+        .unwrap_or_else(|| PathBuf::from("./run"))
+}
+
+impl PathsSection {
+    /// All the paths with their keys, used to resolve them after loading.
+    pub fn entries_mut(&mut self) -> [(&'static str, &mut PathBuf); 3] {
+        [
+            ("llama_dir", &mut self.llama_dir),
+            ("models_dir", &mut self.models_dir),
+            ("runtime_dir", &mut self.runtime_dir),
+        ]
+    }
+}
+
+/// Models section
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ModelsSection {
+    /// Alternative names of the models, mapped to model references (`coder = "qwen3:8b-q4_k_m"`)
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+    /// Named lists of quantization types for `quantize --preset`, in addition to the built-in ones
+    #[serde(default)]
+    pub quant_presets: BTreeMap<String, Vec<String>>,
+}
+
+/// Server section
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ServerSection {
+    /// Ports `server --port auto` picks from
+    #[serde(default)]
+    pub port_range: PortRange,
+}
+
+/// Inclusive range of ports, written like `8080-8180`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl PortRange {
+    pub fn ports(&self) -> std::ops::RangeInclusive<u16> {
+        self.start..=self.end
+    }
+}
+
+impl Default for PortRange {
+    fn default() -> Self {
+        Self {
+            start: 8080,
+            end: 8180,
+        }
+    }
+}
+
+impl TryFrom<String> for PortRange {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        let invalid = || format!("invalid port range `{}`, expected `start-end`", value);
+        let (start, end) = value.split_once('-').ok_or_else(invalid)?;
+        let start: u16 = start.trim().parse().map_err(|_| invalid())?;
+        let end: u16 = end.trim().parse().map_err(|_| invalid())?;
+        if start == 0 || start > end {
+            return Err(invalid());
+        }
+        Ok(Self { start, end })
+    }
+}
+
+impl From<PortRange> for String {
+    fn from(range: PortRange) -> Self {
+        format!("{}-{}", range.start, range.end)
+    }
+}
+
+/// Profile configuration
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    /// Profiles this one is based on, merged in order of appearance
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extends: Vec<String>,
+    #[serde(default)]
+    pub cmake_args: Vec<String>,
+    /// CMake arguments inherited from base profiles that should be dropped.
+    /// Entries without `=` remove all the definitions of given variable (`-DGGML_LTO`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove_cmake_args: Vec<String>,
+    pub cmake_generator: Option<String>,
+    /// URL of llama.cpp repository (or its fork)
+    pub repo_url: Option<String>,
+    /// Branch to check out
+    pub branch: Option<String>,
+    /// Commit SHA, tag or build number (`bNNNN`) the instance should be pinned to
+    #[serde(rename = "ref")]
+    pub git_ref: Option<String>,
+    /// Additional remotes (name -> URL) fetched alongside `origin`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub remotes: BTreeMap<String, String>,
+    /// How the submodules should be updated
+    pub submodules: Option<SubmodulePolicy>,
+}
+
+/// Submodule update policy
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubmodulePolicy {
+    /// Check out the submodule commits recorded in the repository
+    Pinned,
+    /// Update the submodules to the latest commit of their tracked branch
+    #[default]
+    Remote,
+    /// Do not touch the submodules
+    Skip,
+}
+
+impl Profile {
+    pub fn repo_url(&self) -> &str {
+        self.repo_url.as_deref().unwrap_or(DEFAULT_REPO_URL)
+    }
+
+    pub fn branch(&self) -> &str {
+        self.branch.as_deref().unwrap_or(DEFAULT_BRANCH)
+    }
+
+    pub fn submodules(&self) -> SubmodulePolicy {
+        self.submodules.unwrap_or_default()
+    }
+
+    /// Applies `other` on top of this profile.
+    /// CMake arguments are appended (after removing the ones listed in `remove_cmake_args`),
+    /// remotes are merged, and all the other values set in `other` override the current ones.
+    fn merge(&mut self, other: &Profile) {
+        self.cmake_args
+            .retain(|arg| !other.remove_cmake_args.iter().any(|r| removes_arg(r, arg)));
+        self.cmake_args.extend(other.cmake_args.iter().cloned());
+
+        self.remotes.extend(
+            other
+                .remotes
+                .iter()
+                .map(|(name, url)| (name.clone(), url.clone())),
+        );
+
+        if other.cmake_generator.is_some() {
+            self.cmake_generator = other.cmake_generator.clone();
+        }
+        if other.repo_url.is_some() {
+            self.repo_url = other.repo_url.clone();
+        }
+        if other.branch.is_some() {
+            self.branch = other.branch.clone();
+        }
+        if other.git_ref.is_some() {
+            self.git_ref = other.git_ref.clone();
+        }
+        if other.submodules.is_some() {
+            self.submodules = other.submodules;
+        }
+    }
+}
+
+/// Returns `true` if `removal` entry matches CMake argument `arg`.
+fn removes_arg(removal: &str, arg: &str) -> bool {
+    if removal == arg {
+        return true;
+    }
+
+    !removal.contains('=')
+        && arg
+            .strip_prefix(removal)
+            .is_some_and(|rest| rest.starts_with('=') || rest.starts_with(':'))
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            schema_version: default_schema_version(),
+            config: ConfigSection {
+                default_profile: "vulkan".to_string(),
+                install_retention: default_install_retention(),
+            },
+            paths: PathsSection {
+                llama_dir: PathBuf::from("./llama"),
+                models_dir: PathBuf::from("./models"),
+                runtime_dir: default_runtime_dir(),
+            },
+            models: ModelsSection::default(),
+            server: ServerSection::default(),
+            profiles: {
+                let mut profiles = HashMap::new();
+
+                // Default CPU profile
+                profiles.insert(
+                    "cpu".to_string(),
+                    Profile {
+                        cmake_args: vec!["-DGGML_CPU=ON".to_string()],
+                        ..Default::default()
+                    },
+                );
+
+                // Default Vulkan profile
+                profiles.insert(
+                    "vulkan".to_string(),
+                    Profile {
+                        cmake_args: vec!["-DGGML_VULKAN=ON".to_string()],
+                        ..Default::default()
+                    },
+                );
+
+                profiles
+            },
+        }
+    }
+}
+
+impl Config {
+    /// Get a profile by name, or return the default profile
+    pub fn get_profile(&self, name: Option<&str>) -> Option<&Profile> {
+        match name {
+            Some(n) => self.profiles.get(n),
+            None => self.profiles.get(&self.config.default_profile),
+        }
+    }
+
+    /// Get a profile by name with all of its base profiles merged in.
+    pub fn resolve_profile(&self, name: &str) -> Result<Profile> {
+        self.resolve_profile_chain(name, &mut Vec::new())
+    }
+
+    fn resolve_profile_chain(&self, name: &str, chain: &mut Vec<String>) -> Result<Profile> {
+        if chain.iter().any(|n| n == name) {
+            chain.push(name.to_string());
+            return Err(RuntimeError::new(
+                format!("Profile inheritance cycle: {}", chain.join(" -> ")),
+                exitcode::CONFIG as u8,
+            ));
+        }
+
+        let profile = self.profiles.get(name).ok_or_else(|| {
+            let message = match chain.last() {
+                Some(child) => format!("Profile '{}' extends unknown profile '{}'", child, name),
+                None => format!("Profile '{}' not found in configuration", name),
+            };
+            RuntimeError::new(message, exitcode::CONFIG as u8)
+        })?;
+
+        chain.push(name.to_string());
+        let mut resolved = Profile::default();
+        for base in &profile.extends {
+            let base = self.resolve_profile_chain(base, chain)?;
+            resolved.merge(&base);
+        }
+        chain.pop();
+
+        resolved.merge(profile);
+        Ok(resolved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_source_defaults() {
+        let profile: Profile = toml::from_str(r#"cmake_args = ["-DGGML_CPU=ON"]"#).unwrap();
+        assert_eq!(profile.repo_url(), DEFAULT_REPO_URL);
+        assert_eq!(profile.branch(), DEFAULT_BRANCH);
+        assert!(profile.remotes.is_empty());
+        assert_eq!(profile.submodules(), SubmodulePolicy::Remote);
+    }
+
+    #[test]
+    fn test_profile_source_settings() {
+        let profile: Profile = toml::from_str(
+            r#"
+            cmake_args = []
+            repo_url = "https://example.com/fork/llama.cpp.git"
+            branch = "internal"
+            submodules = "skip"
+
+            [remotes]
+            upstream = "https://github.com/ggml-org/llama.cpp.git"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(profile.repo_url(), "https://example.com/fork/llama.cpp.git");
+        assert_eq!(profile.branch(), "internal");
+        assert_eq!(profile.submodules(), SubmodulePolicy::Skip);
+        assert_eq!(
+            profile.remotes.get("upstream").map(String::as_str),
+            Some("https://github.com/ggml-org/llama.cpp.git")
+        );
+    }
+
+    fn config_with_profiles(profiles: &str) -> Config {
+        Config {
+            profiles: toml::from_str(profiles).unwrap(),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn test_resolve_profile_inheritance() {
+        let config = config_with_profiles(
+            r#"
+            [base]
+            cmake_args = ["-DGGML_NATIVE=ON", "-DLLAMA_CURL=OFF"]
+            branch = "master"
+            submodules = "skip"
+
+            [lto]
+            cmake_args = ["-DGGML_LTO=ON"]
+
+            [vulkan]
+            extends = ["base", "lto"]
+            cmake_args = ["-DGGML_VULKAN=ON"]
+            remove_cmake_args = ["-DLLAMA_CURL"]
+            branch = "vulkan-fixes"
+            "#,
+        );
+
+        let profile = config.resolve_profile("vulkan").unwrap();
+        assert_eq!(
+            profile.cmake_args,
+            vec!["-DGGML_NATIVE=ON", "-DGGML_LTO=ON", "-DGGML_VULKAN=ON"]
+        );
+        assert_eq!(profile.branch(), "vulkan-fixes");
+        assert_eq!(profile.submodules(), SubmodulePolicy::Skip);
+        assert!(profile.extends.is_empty());
+        assert!(profile.remove_cmake_args.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_profile_errors() {
+        let config = config_with_profiles(
+            r#"
+            [a]
+            extends = ["b"]
+            [b]
+            extends = ["a"]
+            [orphan]
+            extends = ["missing"]
+            "#,
+        );
+
+        let cycle = config.resolve_profile("a").unwrap_err();
+        assert!(cycle.message.contains("a -> b -> a"), "{}", cycle.message);
+
+        let missing = config.resolve_profile("orphan").unwrap_err();
+        assert!(missing.message.contains("unknown profile 'missing'"));
+
+        assert!(config.resolve_profile("nonexistent").is_err());
+    }
+
+    #[test]
+    fn test_removes_arg() {
+        assert!(removes_arg("-DGGML_LTO=ON", "-DGGML_LTO=ON"));
+        assert!(removes_arg("-DGGML_LTO", "-DGGML_LTO=ON"));
+        assert!(removes_arg("-DGGML_LTO", "-DGGML_LTO:BOOL=ON"));
+        assert!(!removes_arg("-DGGML_LTO", "-DGGML_LTO_EXTRA=ON"));
+        assert!(!removes_arg("-DGGML_LTO=OFF", "-DGGML_LTO=ON"));
+    }
+
+    #[test]
+    fn test_port_range() {
+        let range = PortRange::try_from("9000-9010".to_string()).unwrap();
+        assert_eq!(
+            range,
+            PortRange {
+                start: 9000,
+                end: 9010
+            }
+        );
+        assert_eq!(String::from(range), "9000-9010");
+
+        assert!(PortRange::try_from("9010-9000".to_string()).is_err());
+        assert!(PortRange::try_from("9000".to_string()).is_err());
+        assert!(PortRange::try_from("0-10".to_string()).is_err());
+    }
+}
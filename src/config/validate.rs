@@ -0,0 +1,568 @@
+use std::fmt::Display;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use toml::Spanned;
+use toml::de::{DeTable, DeValue};
+
+use crate::config::layers::{self, ConfigOrigin, ConfigSources, LoadedConfig};
+use crate::config::migrate;
+
+/// Expected type of the configuration value.
+#[derive(Debug)]
+pub enum Kind {
+    String,
+    Integer,
+    StringArray,
+    /// Table with arbitrary keys and string values
+    StringMap,
+    /// String with one of the listed values
+    Enum(&'static [&'static str]),
+    /// Table with a known set of keys
+    Table(&'static [Field]),
+    /// Table with arbitrary keys and values of the same kind
+    Map(&'static Kind),
+}
+
+/// Single key of the configuration schema.
+#[derive(Debug)]
+pub struct Field {
+    pub name: &'static str,
+    pub kind: Kind,
+    /// Required keys must be present in the merged configuration, not in every file.
+    pub required: bool,
+}
+
+const fn required(name: &'static str, kind: Kind) -> Field {
+    Field {
+        name,
+        kind,
+        required: true,
+    }
+}
+
+const fn optional(name: &'static str, kind: Kind) -> Field {
+    Field {
+        name,
+        kind,
+        required: false,
+    }
+}
+
+const CONFIG_FIELDS: &[Field] = &[
+    required("default_profile", Kind::String),
+    optional("install_retention", Kind::Integer),
+];
+
+const PATHS_FIELDS: &[Field] = &[
+    required("llama_dir", Kind::String),
+    required("models_dir", Kind::String),
+    optional("runtime_dir", Kind::String),
+];
+
+const MODELS_FIELDS: &[Field] = &[
+    optional("aliases", Kind::StringMap),
+    optional("quant_presets", Kind::Map(&Kind::StringArray)),
+];
+
+const SERVER_FIELDS: &[Field] = &[optional("port_range", Kind::String)];
+
+const PROFILE_FIELDS: &[Field] = &[
+    optional("extends", Kind::StringArray),
+    optional("cmake_args", Kind::StringArray),
+    optional("remove_cmake_args", Kind::StringArray),
+    optional("cmake_generator", Kind::String),
+    optional("repo_url", Kind::String),
+    optional("branch", Kind::String),
+    optional("ref", Kind::String),
+    optional("remotes", Kind::StringMap),
+    optional("submodules", Kind::Enum(&["pinned", "remote", "skip"])),
+];
+
+const PROFILE: Kind = Kind::Table(PROFILE_FIELDS);
+
+/// Schema of the whole configuration file.
+pub const SCHEMA: Kind = Kind::Table(&[
+    optional(migrate::SCHEMA_VERSION_KEY, Kind::Integer),
+    required("config", Kind::Table(CONFIG_FIELDS)),
+    required("paths", Kind::Table(PATHS_FIELDS)),
+    optional("models", Kind::Table(MODELS_FIELDS)),
+    optional("server", Kind::Table(SERVER_FIELDS)),
+    optional("profiles", Kind::Map(&PROFILE)),
+]);
+
+impl Kind {
+    /// Returns the kind of the value at given dotted key, or `None` if the key is unknown.
+    pub fn find(&'static self, key: &str) -> Option<&'static Kind> {
+        key.split('.').try_fold(self, Kind::child)
+    }
+
+    /// Returns the kind of the value nested directly in this one.
+    fn child(&'static self, name: &str) -> Option<&'static Kind> {
+        match self {
+            Kind::Table(fields) => fields
+                .iter()
+                .find(|field| field.name == name)
+                .map(|field| &field.kind),
+            Kind::Map(kind) => Some(*kind),
+            Kind::StringMap => Some(&Kind::String),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Kind::String => write!(f, "a string"),
+            Kind::Integer => write!(f, "an integer"),
+            Kind::StringArray => write!(f, "an array of strings"),
+            Kind::StringMap => write!(f, "a table of strings"),
+            Kind::Enum(values) => write!(f, "one of: {}", values.join(", ")),
+            Kind::Table(_) | Kind::Map(_) => write!(f, "a table"),
+        }
+    }
+}
+
+/// Single problem found in the configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// Path of the file, or description of other configuration source.
+    pub source: String,
+    /// Line and column (both starting from 1) of the problem, if known.
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.position {
+            Some((line, column)) => {
+                write!(f, "{}:{}:{}: {}", self.source, line, column, self.message)
+            }
+            None => write!(f, "{}: {}", self.source, self.message),
+        }
+    }
+}
+
+/// Validates all the configuration layers. Every file is checked for syntax errors, unknown keys
+/// and values of invalid types. If the layers are correct, merged configuration is checked for
+/// missing required keys and profiles that can't be resolved.
+pub fn validate(sources: &ConfigSources) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for origin in sources.files() {
+        let path = origin.file().expect("file origin always has a path");
+        diagnostics.extend(validate_file(path));
+    }
+
+    for (name, path, value) in sources.env_values() {
+        let source = ConfigOrigin::Environment(name.to_string()).to_string();
+        let content = toml::to_string(&layers::env_layer(&path, value)).unwrap_or_default();
+        diagnostics.extend(
+            check_document(&content)
+                .into_iter()
+                .map(|(_, message)| Diagnostic {
+                    source: source.clone(),
+                    position: None,
+                    message,
+                }),
+        );
+    }
+
+    if !diagnostics.is_empty() {
+        return diagnostics;
+    }
+
+    match layers::load(sources) {
+        Ok(loaded) => validate_merged(&loaded),
+        Err(e) => vec![Diagnostic {
+            source: "configuration".to_string(),
+            position: None,
+            message: e.message,
+        }],
+    }
+}
+
+/// Returns the configuration files using older schema versions, with their versions.
+pub fn outdated_files(sources: &ConfigSources) -> Vec<(PathBuf, u32)> {
+    sources
+        .files()
+        .iter()
+        .filter_map(|origin| {
+            let path = origin.file()?;
+            let table = std::fs::read_to_string(path).ok()?.parse().ok()?;
+            let version = migrate::schema_version(&table).ok()?;
+            is_outdated(version).then(|| (path.to_path_buf(), version))
+        })
+        .collect()
+}
+
+/// Checks single configuration file for syntax errors, unknown keys and invalid values.
+/// Files using older schema versions are migrated before checking, so the problems
+/// are reported without their positions.
+pub fn validate_file(path: &Path) -> Vec<Diagnostic> {
+    let source = path.display().to_string();
+    let diagnostic = |position, message| Diagnostic {
+        source: source.clone(),
+        position,
+        message,
+    };
+
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => return vec![diagnostic(None, format!("failed to read the file: {}", e))],
+    };
+
+    let outdated_table = content
+        .parse::<toml::Table>()
+        .ok()
+        .filter(|table| migrate::schema_version(table).is_ok_and(is_outdated));
+    let Some(mut table) = outdated_table else {
+        return check_document(&content)
+            .into_iter()
+            .map(|(span, message)| {
+                diagnostic(span.map(|span| position(&content, span.start)), message)
+            })
+            .collect();
+    };
+
+    if let Err(e) = migrate::migrate(&mut table) {
+        return vec![diagnostic(None, e.message)];
+    }
+    let migrated = toml::to_string(&table).unwrap_or_default();
+    check_document(&migrated)
+        .into_iter()
+        .map(|(_, message)| diagnostic(None, message))
+        .collect()
+}
+
+fn is_outdated(version: u32) -> bool {
+    version < migrate::CURRENT_SCHEMA_VERSION
+}
+
+/// Checks the TOML document against the schema, returning the problems with their byte spans.
+pub fn check_document(content: &str) -> Vec<(Option<Range<usize>>, String)> {
+    let (document, errors) = DeTable::parse_recoverable(content);
+    let mut problems: Vec<_> = errors
+        .into_iter()
+        .map(|e| (e.span(), e.message().trim().to_string()))
+        .collect();
+
+    // Recovered document is incomplete, checking it would only produce misleading errors
+    if problems.is_empty() {
+        check_table(document.get_ref(), &SCHEMA, "", &mut problems);
+    }
+
+    if let Ok(table) = content.parse::<toml::Table>()
+        && let Err(e) = migrate::schema_version(&table)
+    {
+        let span = find_key_span(content, migrate::SCHEMA_VERSION_KEY);
+        problems.push((span, e.message));
+    }
+    problems
+}
+
+fn check_table(
+    table: &DeTable,
+    kind: &'static Kind,
+    prefix: &str,
+    problems: &mut Vec<(Option<Range<usize>>, String)>,
+) {
+    for (key, value) in table {
+        let path = layers::join_key(prefix, key.get_ref());
+        match kind.child(key.get_ref()) {
+            Some(kind) => check_value(value, kind, &path, problems),
+            None => problems.push((Some(key.span()), format!("unknown key `{}`", path))),
+        }
+    }
+}
+
+fn check_value(
+    value: &Spanned<DeValue>,
+    kind: &'static Kind,
+    path: &str,
+    problems: &mut Vec<(Option<Range<usize>>, String)>,
+) {
+    let is_valid = match (kind, value.get_ref()) {
+        (Kind::String, DeValue::String(_)) | (Kind::Integer, DeValue::Integer(_)) => true,
+        (Kind::Enum(values), DeValue::String(value)) => values.contains(&value.as_ref()),
+        (Kind::StringArray, DeValue::Array(array)) => array
+            .into_iter()
+            .all(|item| matches!(item.get_ref(), DeValue::String(_))),
+        (Kind::StringMap | Kind::Table(_) | Kind::Map(_), DeValue::Table(table)) => {
+            check_table(table, kind, path, problems);
+            true
+        }
+        _ => false,
+    };
+
+    if !is_valid {
+        problems.push((
+            Some(value.span()),
+            format!("invalid value of `{}`, expected {}", path, kind),
+        ));
+    }
+}
+
+fn validate_merged(loaded: &LoadedConfig) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    let mut missing = Vec::new();
+    find_missing(&loaded.table, &SCHEMA, "", &mut missing);
+    diagnostics.extend(missing.into_iter().map(|key| Diagnostic {
+        source: "configuration".to_string(),
+        position: None,
+        message: format!("missing required key `{}`", key),
+    }));
+
+    let config = &loaded.config;
+    let default_profile = &config.config.default_profile;
+    if config.get_profile(Some(default_profile)).is_none() {
+        diagnostics.push(locate(
+            loaded,
+            "config.default_profile",
+            format!("default profile `{}` is not defined", default_profile),
+        ));
+    }
+
+    let mut profile_names: Vec<&String> = config.profiles.keys().collect();
+    profile_names.sort();
+    for name in profile_names {
+        if let Err(e) = config.resolve_profile(name) {
+            diagnostics.push(locate(
+                loaded,
+                &format!("profiles.{}.extends", name),
+                e.message,
+            ));
+        }
+    }
+
+    diagnostics
+}
+
+fn find_missing(table: &toml::Table, kind: &Kind, prefix: &str, missing: &mut Vec<String>) {
+    let Kind::Table(fields) = kind else {
+        return;
+    };
+
+    for field in fields.iter() {
+        let path = layers::join_key(prefix, field.name);
+        match table.get(field.name) {
+            Some(toml::Value::Table(nested)) => find_missing(nested, &field.kind, &path, missing),
+            Some(_) => {}
+            None if field.required => missing.push(path),
+            None => {}
+        }
+    }
+}
+
+/// Creates a diagnostic pointing at the key in the file it has been set in.
+fn locate(loaded: &LoadedConfig, key: &str, message: String) -> Diagnostic {
+    let origin = loaded.origins.get(key).unwrap_or(&ConfigOrigin::Default);
+    let Some(path) = origin.file() else {
+        return Diagnostic {
+            source: origin.to_string(),
+            position: None,
+            message,
+        };
+    };
+
+    let content = std::fs::read_to_string(path).unwrap_or_default();
+    Diagnostic {
+        source: path.display().to_string(),
+        position: find_key_span(&content, key).map(|span| position(&content, span.start)),
+        message,
+    }
+}
+
+/// Returns the span of the last segment of a dotted key in the TOML document.
+fn find_key_span(content: &str, key: &str) -> Option<Range<usize>> {
+    let (document, _) = DeTable::parse_recoverable(content);
+    let mut table = document.get_ref();
+    let segments: Vec<&str> = key.split('.').collect();
+
+    for (index, segment) in segments.iter().enumerate() {
+        let (key, value) = table.iter().find(|(key, _)| key.get_ref() == segment)?;
+        if index == segments.len() - 1 {
+            return Some(key.span());
+        }
+
+        match value.get_ref() {
+            DeValue::Table(nested) => table = nested,
+            _ => return None,
+        }
+    }
+
+    None
+}
+
+/// Converts byte offset to line and column, both starting from 1.
+fn position(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn sources(temp_dir: &TempDir) -> ConfigSources {
+        ConfigSources {
+            system: temp_dir.path().join("system.toml"),
+            user: temp_dir.path().join("user.toml"),
+            project_search_start: temp_dir.path().to_path_buf(),
+            env: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_find_kind() {
+        assert!(matches!(SCHEMA.find("paths.llama_dir"), Some(Kind::String)));
+        assert!(matches!(
+            SCHEMA.find("profiles.anything.cmake_args"),
+            Some(Kind::StringArray)
+        ));
+        assert!(matches!(
+            SCHEMA.find("profiles.cpu.remotes.fork"),
+            Some(Kind::String)
+        ));
+        assert!(SCHEMA.find("paths.unknown").is_none());
+        assert!(SCHEMA.find("config.default_profile.nested").is_none());
+    }
+
+    #[test]
+    fn test_valid_configuration() {
+        let temp_dir = TempDir::new().unwrap();
+        let sources = sources(&temp_dir);
+        std::fs::write(
+            &sources.user,
+            r#"
+[config]
+default_profile = "fast"
+
+[profiles.fast]
+extends = ["cpu"]
+submodules = "skip"
+remotes = { fork = "https://example.com/llama.cpp.git" }
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(validate(&sources), Vec::new());
+    }
+
+    #[test]
+    fn test_syntax_error_position() {
+        let temp_dir = TempDir::new().unwrap();
+        let sources = sources(&temp_dir);
+        std::fs::write(&sources.user, "[paths]\nmodels_dir = = \"./models\"\n").unwrap();
+
+        let diagnostics = validate(&sources);
+        assert!(!diagnostics.is_empty());
+        assert_eq!(diagnostics[0].position.map(|(line, _)| line), Some(2));
+        assert!(
+            diagnostics[0]
+                .to_string()
+                .starts_with(&format!("{}:2:", sources.user.display()))
+        );
+    }
+
+    #[test]
+    fn test_schema_errors() {
+        let temp_dir = TempDir::new().unwrap();
+        let sources = sources(&temp_dir);
+        std::fs::write(
+            &sources.user,
+            r#"schema_version = 2
+
+[config]
+install_retention = "many"
+
+[profiles.cpu]
+cmake_arg = ["-DGGML_CPU=ON"]
+submodules = "always"
+"#,
+        )
+        .unwrap();
+
+        let messages: Vec<String> = validate(&sources).iter().map(|d| d.to_string()).collect();
+        let file = sources.user.display();
+        assert_eq!(
+            messages,
+            vec![
+                format!(
+                    "{}:4:21: invalid value of `config.install_retention`, expected an integer",
+                    file
+                ),
+                format!("{}:7:1: unknown key `profiles.cpu.cmake_arg`", file),
+                format!(
+                    "{}:8:14: invalid value of `profiles.cpu.submodules`, expected one of: pinned, remote, skip",
+                    file
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unresolvable_profiles() {
+        let temp_dir = TempDir::new().unwrap();
+        let sources = ConfigSources {
+            env: vec![(
+                "LLAMA_MGR_PROFILES__CPU__CMAKE_ARGZ".to_string(),
+                "[]".to_string(),
+            )],
+            ..sources(&temp_dir)
+        };
+        std::fs::write(&sources.user, "[config]\ndefault_profile = \"missing\"\n").unwrap();
+
+        let diagnostics = validate(&sources);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "environment (LLAMA_MGR_PROFILES__CPU__CMAKE_ARGZ): unknown key `profiles.cpu.cmake_argz`"
+        );
+
+        let sources = ConfigSources {
+            env: Vec::new(),
+            ..sources
+        };
+        std::fs::write(
+            &sources.user,
+            "[config]\ndefault_profile = \"missing\"\n\n[profiles.loop]\nextends = [\"loop\"]\n",
+        )
+        .unwrap();
+        let diagnostics = validate(&sources);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].position, Some((2, 1)));
+        assert!(diagnostics[0].message.contains("`missing`"));
+        assert_eq!(diagnostics[1].position, Some((5, 1)));
+        assert!(
+            diagnostics[1].message.contains("cycle"),
+            "{}",
+            diagnostics[1]
+        );
+    }
+
+    #[test]
+    fn test_outdated_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let sources = sources(&temp_dir);
+        std::fs::write(&sources.user, "[profile.cpu]\nbranch = \"dev\"\n").unwrap();
+        assert_eq!(validate(&sources), Vec::new());
+        assert_eq!(outdated_files(&sources), vec![(sources.user.clone(), 1)]);
+
+        std::fs::write(&sources.user, "[profile.cpu]\nbranch = 1\n").unwrap();
+        let diagnostics = validate(&sources);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].position, None);
+        assert!(diagnostics[0].message.contains("profiles.cpu.branch"));
+
+        std::fs::write(&sources.user, "schema_version = 99\n").unwrap();
+        let diagnostics = validate(&sources);
+        assert_eq!(diagnostics[0].position, Some((1, 1)));
+    }
+}
@@ -1,6 +1,6 @@
 use std::fmt::Display;
-use std::process::ExitCode;
 use std::io;
+use std::process::ExitCode;
 
 use thiserror::Error;
 
@@ -31,4 +31,4 @@ impl From<io::Error> for RuntimeError {
     }
 }
 
-pub type Result<T> = std::result::Result<T, RuntimeError>;
\ No newline at end of file
+pub type Result<T> = std::result::Result<T, RuntimeError>;
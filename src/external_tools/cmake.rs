@@ -248,13 +248,7 @@ int main() {
         let (source_dir, build_dir) = create_test_project(&temp_dir);
 
         // Generate without additional args - should use default value
-        let result = cmake.generate(
-            &source_dir,
-            &build_dir,
-            &PathBuf::from("Ninja"),
-            None,
-            None,
-        );
+        let result = cmake.generate(&source_dir, &build_dir, &PathBuf::from("Ninja"), None, None);
 
         assert!(result.is_ok());
         let status = result.unwrap();
@@ -320,22 +314,6 @@ int main() {
         assert!(status.success());
     }
 
-    use std::io;
-    use std::path::Path;
-    fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<()> {
-        fs::create_dir_all(&dst)?;
-        for entry in fs::read_dir(src)? {
-            let entry = entry?;
-            let ty = entry.file_type()?;
-            if ty.is_dir() {
-                copy_dir_all(entry.path(), dst.as_ref().join(entry.file_name()))?;
-            } else {
-                fs::copy(entry.path(), dst.as_ref().join(entry.file_name()))?;
-            }
-        }
-        Ok(())
-    }
-
     #[test]
     fn test_install() {
         let temp_dir = TempDir::new().unwrap();
@@ -1,7 +1,9 @@
 use std::{
     ffi::OsStr,
+    fmt::Display,
     path::{Path, PathBuf},
     process::Command,
+    str::FromStr,
 };
 
 use crate::error::{Result, RuntimeError};
@@ -11,6 +13,77 @@ pub struct Git {
     path: PathBuf,
 }
 
+/// Exact revision of the repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitRef {
+    /// Commit SHA, either full or abbreviated, as given by the user.
+    Commit(String),
+    /// llama.cpp release build number, tagged as `bNNNN`.
+    Build(u32),
+    /// Any other tag.
+    Tag(String),
+}
+
+impl GitRef {
+    /// Returns the refspec that should be fetched from remote to make the revision available.
+    /// `None` means that all the branches have to be fetched.
+    fn refspec(&self) -> Option<String> {
+        match self {
+            GitRef::Commit(sha) if sha.len() == 40 => Some(sha.to_lowercase()),
+            GitRef::Commit(_) => None,
+            GitRef::Build(_) | GitRef::Tag(_) => Some(format!("refs/tags/{0}:refs/tags/{0}", self)),
+        }
+    }
+
+    /// Returns the name that can be passed to `git checkout`.
+    fn checkout_target(&self) -> String {
+        match self {
+            GitRef::Commit(sha) => sha.to_lowercase(),
+            GitRef::Build(_) | GitRef::Tag(_) => format!("refs/tags/{}", self),
+        }
+    }
+}
+
+impl FromStr for GitRef {
+    type Err = RuntimeError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+
+        if s.is_empty() {
+            return Err(RuntimeError::new(
+                "Git ref cannot be empty".to_string(),
+                exitcode::USAGE as u8,
+            ));
+        }
+
+        if let Some(build) = s.strip_prefix('b')
+            && !build.is_empty()
+            && build.chars().all(|c| c.is_ascii_digit())
+            && let Ok(build) = build.parse()
+        {
+            return Ok(GitRef::Build(build));
+        }
+
+        if (7..=40).contains(&s.len()) && s.chars().all(|c| c.is_ascii_hexdigit()) {
+            // Tags can look like commits too, see `Git::checkout_ref`
+            return Ok(GitRef::Commit(s.to_string()));
+        }
+
+        Ok(GitRef::Tag(s.to_string()))
+    }
+}
+
+impl Display for GitRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GitRef::Commit(sha) => write!(f, "{}", sha),
+            GitRef::Build(build) => write!(f, "b{}", build),
+            GitRef::Tag(tag) => write!(f, "{}", tag),
+        }
+    }
+}
+
 impl Git {
     fn configure_git_command(&self, cmd: &mut Command) {
         // Configure git to not prompt for credentials
@@ -30,8 +103,8 @@ impl Git {
         self.configure_git_command(&mut cmd);
 
         cmd.arg("clone");
-        if branch.is_some() {
-            cmd.arg("-b").arg(branch.unwrap());
+        if let Some(branch) = branch {
+            cmd.arg("-b").arg(branch);
         }
         cmd.arg(repo_url);
         cmd.arg(repo_path.as_ref().as_os_str());
@@ -69,7 +142,200 @@ impl Git {
         Ok(())
     }
 
-    pub fn update_submodules(&self, repo_path: impl AsRef<Path>) -> Result<()> {
+    /// Adds a remote, or changes its URL if it already exists.
+    pub fn set_remote(
+        &self,
+        repo_path: impl AsRef<Path>,
+        name: &str,
+        url: impl AsRef<OsStr>,
+    ) -> Result<()> {
+        let mut cmd = Command::new(&self.path);
+        cmd.current_dir(&repo_path);
+
+        self.configure_git_command(&mut cmd);
+
+        cmd.arg("remote").arg("get-url").arg(name);
+        let remote_exists = cmd.output()?.status.success();
+
+        let mut cmd = Command::new(&self.path);
+        cmd.current_dir(&repo_path);
+
+        self.configure_git_command(&mut cmd);
+
+        cmd.arg("remote")
+            .arg(if remote_exists { "set-url" } else { "add" })
+            .arg(name)
+            .arg(url);
+        let output = cmd.output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(RuntimeError::new(
+                format!("git remote configuration failed: {}", stderr),
+                exitcode::DATAERR as u8,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Fetches all the branches and tags from the remote.
+    pub fn fetch(&self, repo_path: impl AsRef<Path>, remote: &str) -> Result<()> {
+        let mut cmd = Command::new(&self.path);
+        cmd.current_dir(repo_path);
+
+        self.configure_git_command(&mut cmd);
+
+        cmd.arg("fetch").arg("--tags").arg(remote);
+        let output = cmd.output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(RuntimeError::new(
+                format!("git fetch of remote '{}' failed: {}", remote, stderr),
+                exitcode::DATAERR as u8,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Fetches the exact revision and checks it out as detached HEAD. Remotes are tried in order,
+    /// until one of them has the revision. Names looking like commits are looked up as tags too,
+    /// when there's no such commit, as tags like `deadbeef` look like abbreviated commits.
+    pub fn checkout_ref(
+        &self,
+        repo_path: impl AsRef<Path>,
+        git_ref: &GitRef,
+        remotes: &[&str],
+    ) -> Result<()> {
+        let mut candidates = vec![git_ref.clone()];
+        if let GitRef::Commit(name) = git_ref {
+            candidates.push(GitRef::Tag(name.clone()));
+        }
+
+        let mut first_error = None;
+        for candidate in &candidates {
+            for remote in remotes {
+                let result = self
+                    .fetch_ref(&repo_path, remote, candidate)
+                    .and_then(|_| self.checkout_detached(&repo_path, candidate));
+                match result {
+                    Ok(()) => return Ok(()),
+                    Err(e) => {
+                        first_error.get_or_insert(e);
+                    }
+                }
+            }
+        }
+
+        Err(first_error.unwrap_or_else(|| {
+            RuntimeError::new(
+                format!("No remote to fetch '{}' from", git_ref),
+                exitcode::USAGE as u8,
+            )
+        }))
+    }
+
+    fn fetch_ref(&self, repo_path: impl AsRef<Path>, remote: &str, git_ref: &GitRef) -> Result<()> {
+        let mut cmd = Command::new(&self.path);
+        cmd.current_dir(&repo_path);
+
+        self.configure_git_command(&mut cmd);
+
+        cmd.arg("fetch").arg("--tags").arg(remote);
+        if let Some(refspec) = git_ref.refspec() {
+            cmd.arg(refspec);
+        }
+
+        let output = cmd.output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(RuntimeError::new(
+                format!(
+                    "git fetch of '{}' from {} failed: {}",
+                    git_ref, remote, stderr
+                ),
+                exitcode::DATAERR as u8,
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn checkout_detached(&self, repo_path: impl AsRef<Path>, git_ref: &GitRef) -> Result<()> {
+        let mut cmd = Command::new(&self.path);
+        cmd.current_dir(&repo_path);
+
+        self.configure_git_command(&mut cmd);
+
+        cmd.arg("checkout")
+            .arg("--detach")
+            .arg(git_ref.checkout_target());
+        let output = cmd.output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(RuntimeError::new(
+                format!("git checkout of '{}' failed: {}", git_ref, stderr),
+                exitcode::DATAERR as u8,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Checks out an existing branch, for example to leave the detached HEAD state.
+    pub fn checkout_branch(
+        &self,
+        repo_path: impl AsRef<Path>,
+        branch: impl AsRef<OsStr>,
+    ) -> Result<()> {
+        let mut cmd = Command::new(&self.path);
+        cmd.current_dir(repo_path);
+
+        self.configure_git_command(&mut cmd);
+
+        cmd.arg("checkout").arg(branch);
+        let output = cmd.output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(RuntimeError::new(
+                format!("git checkout failed: {}", stderr),
+                exitcode::DATAERR as u8,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the hash of the currently checked out commit.
+    pub fn head_commit(&self, repo_path: impl AsRef<Path>) -> Result<String> {
+        let mut cmd = Command::new(&self.path);
+        cmd.current_dir(repo_path);
+
+        self.configure_git_command(&mut cmd);
+
+        cmd.arg("rev-parse").arg("HEAD");
+        let output = cmd.output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(RuntimeError::new(
+                format!("git rev-parse failed: {}", stderr),
+                exitcode::DATAERR as u8,
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Initializes and updates the submodules.
+    /// If `remote` is `true`, submodules are updated to the tip of their tracked branch
+    /// instead of the commit recorded in the repository.
+    pub fn update_submodules(&self, repo_path: impl AsRef<Path>, remote: bool) -> Result<()> {
         let mut cmd = Command::new(&self.path);
         cmd.current_dir(repo_path);
 
@@ -78,8 +344,10 @@ impl Git {
         cmd.arg("submodule")
             .arg("update")
             .arg("--init")
-            .arg("--recursive")
-            .arg("--remote");
+            .arg("--recursive");
+        if remote {
+            cmd.arg("--remote");
+        }
 
         let output = cmd.output()?;
 
@@ -104,7 +372,7 @@ impl ExternalTool for Git {
     where
         Self: Sized,
     {
-        which::which("git").map(|path| Self::new(path))
+        which::which("git").map(Self::new)
     }
 
     fn is_available(&self) -> bool {
@@ -228,7 +496,7 @@ mod tests {
         );
 
         // Test submodule update
-        let submodule_result = git.update_submodules(repo_path);
+        let submodule_result = git.update_submodules(repo_path, true);
         assert!(
             submodule_result.is_ok(),
             "Failed to update submodules: {:?}",
@@ -273,6 +541,207 @@ mod tests {
         }
     }
 
+    /// Creates a local repository with a single commit and returns its hash.
+    fn create_local_repository(repo_path: &Path) -> String {
+        run_git(repo_path, &["init", "-q", "-b", "master"]);
+        fs::write(repo_path.join("README.md"), "test repository").unwrap();
+        run_git(repo_path, &["add", "README.md"]);
+        run_git(repo_path, &["commit", "-q", "-m", "Initial commit"]);
+        run_git(repo_path, &["rev-parse", "HEAD"])
+    }
+
+    /// Runs a git command in the repository, returning its trimmed stdout.
+    fn run_git(repo_path: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .current_dir(repo_path)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?} failed", args);
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    #[test]
+    fn test_parse_git_ref() {
+        assert_eq!("b4567".parse::<GitRef>().unwrap(), GitRef::Build(4567));
+        assert_eq!(
+            "ABCDEF0".parse::<GitRef>().unwrap(),
+            GitRef::Commit("ABCDEF0".to_string())
+        );
+        assert_eq!(
+            GitRef::Commit("ABCDEF0".to_string()).checkout_target(),
+            "abcdef0"
+        );
+        assert_eq!(
+            "0123456789abcdef0123456789abcdef01234567"
+                .parse::<GitRef>()
+                .unwrap(),
+            GitRef::Commit("0123456789abcdef0123456789abcdef01234567".to_string())
+        );
+        assert_eq!(
+            "v1.0.0".parse::<GitRef>().unwrap(),
+            GitRef::Tag("v1.0.0".to_string())
+        );
+        assert_eq!("b".parse::<GitRef>().unwrap(), GitRef::Tag("b".to_string()));
+        assert!("".parse::<GitRef>().is_err());
+    }
+
+    #[test]
+    fn test_git_ref_to_string() {
+        assert_eq!(GitRef::Build(1234).to_string(), "b1234");
+        assert_eq!(GitRef::Tag("v1.0".to_string()).to_string(), "v1.0");
+    }
+
+    #[test]
+    fn test_checkout_ref() {
+        let git = Git::global().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let origin_path = temp_dir.path().join("origin");
+        let clone_path = temp_dir.path().join("clone");
+        fs::create_dir(&origin_path).unwrap();
+
+        let first_commit = create_local_repository(&origin_path);
+        run_git(&origin_path, &["tag", "b1"]);
+        fs::write(origin_path.join("second.txt"), "second").unwrap();
+        run_git(&origin_path, &["add", "second.txt"]);
+        run_git(&origin_path, &["commit", "-q", "-m", "Second commit"]);
+        run_git(&origin_path, &["tag", "v2"]);
+        let second_commit = run_git(&origin_path, &["rev-parse", "HEAD"]);
+        fs::write(origin_path.join("third.txt"), "third").unwrap();
+        run_git(&origin_path, &["add", "third.txt"]);
+        run_git(&origin_path, &["commit", "-q", "-m", "Third commit"]);
+        let third_commit = run_git(&origin_path, &["rev-parse", "HEAD"]);
+
+        git.clone(&clone_path, origin_path.as_os_str(), None::<&str>)
+            .unwrap();
+
+        git.checkout_ref(&clone_path, &GitRef::Build(1), &["origin"])
+            .unwrap();
+        assert_eq!(git.head_commit(&clone_path).unwrap(), first_commit);
+
+        git.checkout_ref(&clone_path, &GitRef::Tag("v2".to_string()), &["origin"])
+            .unwrap();
+        assert_eq!(git.head_commit(&clone_path).unwrap(), second_commit);
+
+        let short_sha = third_commit[..10].parse::<GitRef>().unwrap();
+        git.checkout_ref(&clone_path, &short_sha, &["origin"])
+            .unwrap();
+        assert_eq!(git.head_commit(&clone_path).unwrap(), third_commit);
+
+        let full_sha = first_commit.parse::<GitRef>().unwrap();
+        git.checkout_ref(&clone_path, &full_sha, &["origin"])
+            .unwrap();
+        assert_eq!(git.head_commit(&clone_path).unwrap(), first_commit);
+
+        // Going back to the branch allows pulling again
+        git.checkout_branch(&clone_path, "master").unwrap();
+        git.pull(&clone_path).unwrap();
+        assert_eq!(git.head_commit(&clone_path).unwrap(), third_commit);
+
+        let result = git.checkout_ref(&clone_path, &GitRef::Build(999), &["origin"]);
+        assert!(result.is_err(), "Should fail to checkout missing tag");
+    }
+
+    #[test]
+    fn test_checkout_ref_falls_back() {
+        let git = Git::global().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let origin_path = temp_dir.path().join("origin");
+        let fork_path = temp_dir.path().join("fork");
+        let clone_path = temp_dir.path().join("clone");
+        fs::create_dir(&origin_path).unwrap();
+        fs::create_dir(&fork_path).unwrap();
+
+        // Tag that looks like an abbreviated commit
+        let origin_commit = create_local_repository(&origin_path);
+        run_git(&origin_path, &["tag", "deadbeef"]);
+        let fork_commit = create_local_repository(&fork_path);
+        run_git(&fork_path, &["tag", "fork-release"]);
+
+        git.clone(&clone_path, origin_path.as_os_str(), None::<&str>)
+            .unwrap();
+        git.set_remote(&clone_path, "fork", fork_path.as_os_str())
+            .unwrap();
+
+        let hex_tag = "deadbeef".parse::<GitRef>().unwrap();
+        assert_eq!(hex_tag, GitRef::Commit("deadbeef".to_string()));
+        git.checkout_ref(&clone_path, &hex_tag, &["origin"])
+            .unwrap();
+        assert_eq!(git.head_commit(&clone_path).unwrap(), origin_commit);
+
+        // Tag only the other remote has
+        let fork_tag = GitRef::Tag("fork-release".to_string());
+        assert!(
+            git.checkout_ref(&clone_path, &fork_tag, &["origin"])
+                .is_err()
+        );
+        git.checkout_ref(&clone_path, &fork_tag, &["origin", "fork"])
+            .unwrap();
+        assert_eq!(git.head_commit(&clone_path).unwrap(), fork_commit);
+    }
+
+    #[test]
+    fn test_set_remote_and_fetch() {
+        let git = Git::global().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let origin_path = temp_dir.path().join("origin");
+        let fork_path = temp_dir.path().join("fork");
+        let clone_path = temp_dir.path().join("clone");
+        fs::create_dir(&origin_path).unwrap();
+        fs::create_dir(&fork_path).unwrap();
+
+        create_local_repository(&origin_path);
+        create_local_repository(&fork_path);
+        run_git(&fork_path, &["tag", "fork-release"]);
+
+        git.clone(&clone_path, origin_path.as_os_str(), None::<&str>)
+            .unwrap();
+
+        git.set_remote(&clone_path, "fork", fork_path.as_os_str())
+            .unwrap();
+        assert_eq!(
+            run_git(&clone_path, &["remote", "get-url", "fork"]),
+            fork_path.to_string_lossy()
+        );
+
+        // Setting an existing remote changes its URL
+        git.set_remote(&clone_path, "origin", fork_path.as_os_str())
+            .unwrap();
+        assert_eq!(
+            run_git(&clone_path, &["remote", "get-url", "origin"]),
+            fork_path.to_string_lossy()
+        );
+
+        git.fetch(&clone_path, "fork").unwrap();
+        assert_eq!(
+            run_git(&clone_path, &["tag", "--list", "fork-release"]),
+            "fork-release"
+        );
+
+        assert!(git.fetch(&clone_path, "missing").is_err());
+    }
+
+    #[test]
+    fn test_head_commit() {
+        let git = Git::global().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let expected_commit = create_local_repository(temp_dir.path());
+
+        let commit = git.head_commit(temp_dir.path());
+        assert!(commit.is_ok(), "Failed to get HEAD commit: {:?}", commit);
+        assert_eq!(commit.unwrap(), expected_commit);
+    }
+
+    #[test]
+    fn test_head_commit_non_git_directory() {
+        let git = Git::global().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+
+        let result = git.head_commit(temp_dir.path());
+        assert!(result.is_err(), "Should fail outside of git repository");
+    }
+
     #[test]
     #[serial]
     fn test_pull_non_git_directory() {
@@ -0,0 +1,53 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::external_tools::ExternalTool;
+
+/// `llama-imatrix` tool from llama.cpp, computing importance matrices for quantization.
+pub struct LlamaImatrix {
+    path: PathBuf,
+}
+
+impl LlamaImatrix {
+    /// Creates the command computing the importance matrix of the model from the calibration text.
+    pub fn imatrix_command<I, S>(
+        &self,
+        model: &Path,
+        calibration: &Path,
+        output: &Path,
+        extra_args: I,
+    ) -> Command
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut command = Command::new(&self.path);
+        command
+            .arg("--model")
+            .arg(model)
+            .arg("--file")
+            .arg(calibration)
+            .arg("--output")
+            .arg(output)
+            .args(extra_args);
+        command
+    }
+}
+
+impl ExternalTool for LlamaImatrix {
+    fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn global() -> Result<Self, which::Error>
+    where
+        Self: Sized,
+    {
+        which::which("llama-imatrix").map(Self::new)
+    }
+
+    fn is_available(&self) -> bool {
+        Command::new(&self.path).arg("--help").output().is_ok()
+    }
+}
@@ -0,0 +1,229 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use regex::Regex;
+
+use crate::external_tools::ExternalTool;
+
+/// `llama-quantize` tool from llama.cpp.
+pub struct LlamaQuantize {
+    path: PathBuf,
+}
+
+/// Quantization type supported by `llama-quantize`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuantType {
+    /// Numeric ID of the type (`llama_ftype`), `None` for types without one, like `COPY`.
+    pub id: Option<u32>,
+    /// Name of the type, like `Q4_K_M`.
+    pub name: String,
+    pub description: String,
+    /// Bits per weight, as reported by `llama-quantize`.
+    pub bits_per_weight: Option<f64>,
+}
+
+impl QuantType {
+    /// Returns the name of the type this one is an alias for, like `Q4_K_M` for `Q4_K`.
+    pub fn alias_for(&self) -> Option<&str> {
+        self.description.strip_prefix("alias for ").map(str::trim)
+    }
+
+    /// Returns `true` if the type needs an importance matrix to keep reasonable quality:
+    /// all the `IQ` types and K-quants with less than 4 bits per weight.
+    pub fn needs_imatrix(&self) -> bool {
+        let name = self.name.to_ascii_uppercase();
+        name.starts_with("IQ") || name.starts_with("Q2_K") || name.starts_with("Q3_K")
+    }
+
+    /// Returns nominal bits per weight of the block type, for types that use it for all the weights.
+    /// Mixes (`_S`, `_M`, `_L`) and aliases have none, as their real size depends on the model.
+    pub fn nominal_bits_per_weight(&self) -> Option<f64> {
+        if self.alias_for().is_some() {
+            return None;
+        }
+        NOMINAL_BITS_PER_WEIGHT
+            .iter()
+            .find(|(type_name, _)| type_name.eq_ignore_ascii_case(&self.name))
+            .map(|(_, bits)| *bits)
+    }
+
+    /// Returns `true` if the name matches this type, ignoring case.
+    pub fn matches(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name) || self.id.is_some_and(|id| name.parse() == Ok(id))
+    }
+}
+
+/// Nominal bits per weight of the base block types, used when `llama-quantize` does not report them.
+const NOMINAL_BITS_PER_WEIGHT: &[(&str, f64)] = &[
+    ("Q4_0", 4.5),
+    ("Q4_1", 5.0),
+    ("Q5_0", 5.5),
+    ("Q5_1", 6.0),
+    ("Q8_0", 8.5),
+    ("Q2_K", 2.625),
+    ("Q3_K", 3.4375),
+    ("Q4_K", 4.5),
+    ("Q5_K", 5.5),
+    ("Q6_K", 6.5625),
+    ("TQ1_0", 1.6875),
+    ("TQ2_0", 2.0625),
+    ("MXFP4_MOE", 4.25),
+    ("F16", 16.0),
+    ("BF16", 16.0),
+    ("F32", 32.0),
+];
+
+/// Parses the list of quantization types from `llama-quantize --help` output.
+/// Types are listed in lines like `  15  or  Q4_K_M  :  4.58G, +0.1754 ppl @ Llama-3-8B`.
+pub fn parse_quant_types(help: &str) -> Vec<QuantType> {
+    let line_regex =
+        Regex::new(r"^\s*(?:(\d+)\s+or\s+)?([A-Z][A-Z0-9_]*)\s*:\s*(.*?)\s*$").unwrap();
+    let bits_regex = Regex::new(r"(\d+(?:\.\d+)?)\s*bpw").unwrap();
+
+    let Some(types_start) = help.find("Allowed quantization types:") else {
+        return Vec::new();
+    };
+
+    help[types_start..]
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let captures = line_regex.captures(line)?;
+            let name = captures[2].to_string();
+            let description = captures[3].to_string();
+            let bits_per_weight = bits_regex
+                .captures(&description)
+                .and_then(|bits| bits[1].parse().ok());
+
+            Some(QuantType {
+                id: captures.get(1).and_then(|id| id.as_str().parse().ok()),
+                name,
+                description,
+                bits_per_weight,
+            })
+        })
+        .collect()
+}
+
+impl LlamaQuantize {
+    /// Returns the quantization types supported by this build of `llama-quantize`.
+    pub fn quant_types(&self) -> std::io::Result<Vec<QuantType>> {
+        // Help is printed to stdout, with non-zero exit code
+        let output = Command::new(&self.path).arg("--help").output()?;
+        let mut help = String::from_utf8_lossy(&output.stdout).to_string();
+        help.push_str(&String::from_utf8_lossy(&output.stderr));
+
+        let types = parse_quant_types(&help);
+        if types.is_empty() {
+            return Err(std::io::Error::other(format!(
+                "Failed to read quantization types from '{} --help'",
+                self.path.display()
+            )));
+        }
+        Ok(types)
+    }
+
+    /// Creates the command quantizing the model, optionally with the importance matrix.
+    /// Additional arguments are placed before the positional ones.
+    pub fn quantize_command<I, S>(
+        &self,
+        input: &Path,
+        output: &Path,
+        quant_type: &str,
+        imatrix: Option<&Path>,
+        extra_args: I,
+    ) -> Command
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut command = Command::new(&self.path);
+        if let Some(imatrix) = imatrix {
+            command.arg("--imatrix").arg(imatrix);
+        }
+        command
+            .args(extra_args)
+            .arg(input)
+            .arg(output)
+            .arg(quant_type);
+        command
+    }
+}
+
+impl ExternalTool for LlamaQuantize {
+    fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn global() -> Result<Self, which::Error>
+    where
+        Self: Sized,
+    {
+        which::which("llama-quantize").map(Self::new)
+    }
+
+    fn is_available(&self) -> bool {
+        Command::new(&self.path).arg("--help").output().is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELP: &str = "usage: llama-quantize [--help] [--allow-requantize] model-f32.gguf [model-quant.gguf] type [nthreads]
+
+  --allow-requantize: Allows requantizing tensors that have already been quantized.
+  --imatrix file_name: use data in file_name as importance matrix for quant optimizations
+
+Allowed quantization types:
+   2  or  Q4_0    :  4.34G, +0.4685 ppl @ Llama-3-8B
+  19  or  IQ2_XXS :  2.06 bpw quantization
+  15  or  Q4_K    : alias for Q4_K_M
+  15  or  Q4_K_M  :  4.58G, +0.1754 ppl @ Llama-3-8B
+  30  or  IQ4_XS  :  4.25 bpw non-linear quantization
+   0  or  F32     : 26.00G              @ 7B
+          COPY    : only copy tensors, no quantizing
+";
+
+    #[test]
+    fn test_parse_quant_types() {
+        let types = parse_quant_types(HELP);
+        let names: Vec<&str> = types.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["Q4_0", "IQ2_XXS", "Q4_K", "Q4_K_M", "IQ4_XS", "F32", "COPY"]
+        );
+
+        assert_eq!(
+            types[3],
+            QuantType {
+                id: Some(15),
+                name: "Q4_K_M".to_string(),
+                description: "4.58G, +0.1754 ppl @ Llama-3-8B".to_string(),
+                bits_per_weight: None,
+            }
+        );
+        assert_eq!(types[3].nominal_bits_per_weight(), None);
+        assert_eq!(types[2].nominal_bits_per_weight(), None);
+        assert_eq!(types[0].bits_per_weight, None);
+        assert_eq!(types[0].nominal_bits_per_weight(), Some(4.5));
+        assert_eq!(types[1].bits_per_weight, Some(2.06));
+        assert_eq!(types[2].alias_for(), Some("Q4_K_M"));
+        assert_eq!(types[6].id, None);
+        assert_eq!(types[6].bits_per_weight, None);
+
+        assert!(types[1].needs_imatrix());
+        assert!(!types[3].needs_imatrix());
+
+        assert!(types[3].matches("q4_k_m"));
+        assert!(types[3].matches("15"));
+        assert!(!types[3].matches("Q4_K"));
+    }
+
+    #[test]
+    fn test_parse_quant_types_without_list() {
+        assert!(parse_quant_types("usage: llama-quantize").is_empty());
+    }
+}
@@ -0,0 +1,110 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::external_tools::ExternalTool;
+
+/// `llama-server` tool from llama.cpp.
+pub struct LlamaServer {
+    path: PathBuf,
+}
+
+/// Options of `llama-server` managed by llama-mgr.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerOptions {
+    pub model: Option<PathBuf>,
+    pub address: String,
+    pub port: u16,
+    pub ctx_size: Option<u32>,
+    pub gpu_layers: Option<u32>,
+    /// Arguments passed to `llama-server` as they are, after the managed ones.
+    pub extra_args: Vec<String>,
+}
+
+impl ServerOptions {
+    /// Returns the `llama-server` arguments for these options.
+    pub fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(model) = &self.model {
+            args.push("--model".to_string());
+            args.push(model.to_string_lossy().to_string());
+        }
+        args.push("--host".to_string());
+        args.push(self.address.clone());
+        args.push("--port".to_string());
+        args.push(self.port.to_string());
+        if let Some(ctx_size) = self.ctx_size {
+            args.push("--ctx-size".to_string());
+            args.push(ctx_size.to_string());
+        }
+        if let Some(gpu_layers) = self.gpu_layers {
+            args.push("--n-gpu-layers".to_string());
+            args.push(gpu_layers.to_string());
+        }
+        args.extend(self.extra_args.iter().cloned());
+        args
+    }
+}
+
+impl LlamaServer {
+    /// Path to the `llama-server` executable.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Creates the command running the server with given options.
+    pub fn server_command(&self, options: &ServerOptions) -> Command {
+        let mut command = Command::new(&self.path);
+        command.args(options.args());
+        command
+    }
+}
+
+impl ExternalTool for LlamaServer {
+    fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn global() -> Result<Self, which::Error>
+    where
+        Self: Sized,
+    {
+        which::which("llama-server").map(Self::new)
+    }
+
+    fn is_available(&self) -> bool {
+        Command::new(&self.path).arg("--version").output().is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_args() {
+        let options = ServerOptions {
+            model: Some(PathBuf::from("/models/qwen3-8b-q4_k_m.gguf")),
+            address: "127.0.0.1".to_string(),
+            port: 8080,
+            ctx_size: Some(8192),
+            gpu_layers: None,
+            extra_args: vec!["--flash-attn".to_string(), "on".to_string()],
+        };
+
+        assert_eq!(
+            options.args(),
+            vec![
+                "--model",
+                "/models/qwen3-8b-q4_k_m.gguf",
+                "--host",
+                "127.0.0.1",
+                "--port",
+                "8080",
+                "--ctx-size",
+                "8192",
+                "--flash-attn",
+                "on"
+            ]
+        );
+    }
+}
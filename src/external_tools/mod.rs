@@ -2,6 +2,9 @@ use std::path::PathBuf;
 
 pub mod cmake;
 pub mod git;
+pub mod llama_imatrix;
+pub mod llama_quantize;
+pub mod llama_server;
 pub mod ninja;
 pub mod uv;
 pub mod version;
@@ -1,9 +1,9 @@
 use crate::external_tools::ExternalTool;
 use crate::external_tools::version::Version;
 
-use std::io::Write;
+use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
-use std::process::{Child, Command};
+use std::process::{Command, Stdio};
 use std::str::FromStr;
 
 use which;
@@ -25,10 +25,62 @@ pub struct VirtualEnvironment {
     pub python_instance: PythonInstance,
 }
 
-#[derive(Debug)]
-pub struct VirtualEnvironmentShell<'a> {
-    pub venv: &'a VirtualEnvironment,
-    pub shell_process: Child,
+/// How the output of a process is handled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputMode {
+    /// Output is collected and returned in `PythonOutput`.
+    Capture,
+    /// Output is passed directly to the terminal, as the process writes it.
+    Stream,
+}
+
+/// Exit status of a process.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunStatus {
+    Success,
+    /// Process exited with non-zero exit code.
+    Failed(i32),
+    /// Process has been terminated by a signal.
+    Terminated(Option<i32>),
+}
+
+impl RunStatus {
+    pub fn success(&self) -> bool {
+        *self == RunStatus::Success
+    }
+}
+
+impl From<std::process::ExitStatus> for RunStatus {
+    fn from(status: std::process::ExitStatus) -> Self {
+        match status.code() {
+            Some(0) => RunStatus::Success,
+            Some(code) => RunStatus::Failed(code),
+            #[cfg(unix)]
+            None => RunStatus::Terminated(std::os::unix::process::ExitStatusExt::signal(&status)),
+            #[cfg(not(unix))]
+            None => RunStatus::Terminated(None),
+        }
+    }
+}
+
+impl std::fmt::Display for RunStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunStatus::Success => write!(f, "success"),
+            RunStatus::Failed(code) => write!(f, "exit code {}", code),
+            RunStatus::Terminated(Some(signal)) => write!(f, "signal {}", signal),
+            RunStatus::Terminated(None) => write!(f, "termination"),
+        }
+    }
+}
+
+/// Result of running Python inside the virtual environment.
+/// With `OutputMode::Stream`, `stdout` and `stderr` are empty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PythonOutput {
+    pub status: RunStatus,
+    pub stdout: String,
+    pub stderr: String,
 }
 
 impl FromStr for PythonInstance {
@@ -90,6 +142,7 @@ impl Uv {
     }
 
     /// Installs (or updates) selected python instance.
+    #[allow(dead_code)]
     pub fn install_python_instance(&self, instance: PythonInstance) -> UvResult<()> {
         let mut command = Command::new(&self.path);
         command.arg("python").arg("install").arg(instance.id);
@@ -129,6 +182,7 @@ impl Uv {
     }
 
     /// Uninstalls selected python instance.
+    #[allow(dead_code)]
     pub fn uninstall_python_instance(&self, instance: PythonInstance) -> UvResult<()> {
         let mut command = Command::new(&self.path);
         command.arg("python").arg("uninstall").arg(instance.id);
@@ -146,6 +200,7 @@ impl Uv {
     }
 
     /// Uninstalls selected python instance by version.
+    #[allow(dead_code)]
     pub fn uninstall_python_version(&self, version: Version) -> UvResult<()> {
         let mut command = Command::new(&self.path);
         command
@@ -190,10 +245,10 @@ impl Uv {
         // Get the python instance that was used to create the venv
         let python_instances = self.get_python_instances()?;
 
-        // Find the instance that matches our version
+        // Find the installed instance that matches our version
         let python_instance = python_instances
             .into_iter()
-            .find(|instance| instance.version == version)
+            .find(|instance| version.matches(&instance.version) && instance.path.is_some())
             .ok_or_else(|| {
                 std::io::Error::other("Failed to find Python instance for virtual environment")
             })?;
@@ -223,29 +278,185 @@ impl ExternalTool for Uv {
 }
 
 impl VirtualEnvironment {
-    /// Creates a new `bash` shell, activates the virtual environment inside it, and then returns it.
-    pub fn create_shell(&self) -> UvResult<VirtualEnvironmentShell<'_>> {
-        let activate_script_path = self.path.join("bin").join("activate");
-        let command = format!("source {} && exec bash", activate_script_path.display());
+    /// Opens an existing virtual environment, reading the Python version from its `pyvenv.cfg`.
+    pub fn open<T: AsRef<Path>>(path: T) -> UvResult<Self> {
+        let path = &std::path::absolute(path)?;
+        let config = std::fs::read_to_string(path.join("pyvenv.cfg")).map_err(|e| {
+            std::io::Error::new(
+                e.kind(),
+                format!("{} is not a virtual environment: {}", path.display(), e),
+            )
+        })?;
+
+        let value = |key: &str| {
+            config.lines().find_map(|line| {
+                let (name, value) = line.split_once('=')?;
+                (name.trim() == key).then(|| value.trim().to_string())
+            })
+        };
 
-        let shell_process = Command::new("bash").arg("-c").arg(&command).spawn()?;
+        let version: Version = value("version_info")
+            .or_else(|| value("version"))
+            .and_then(|version| version.parse().ok())
+            .ok_or_else(|| {
+                std::io::Error::other(format!(
+                    "Failed to read Python version of virtual environment {}",
+                    path.display()
+                ))
+            })?;
 
-        Ok(VirtualEnvironmentShell {
-            venv: &self,
-            shell_process,
+        Ok(VirtualEnvironment {
+            path: path.to_path_buf(),
+            python_instance: PythonInstance {
+                id: format!("cpython-{}", version),
+                version,
+                path: value("home").map(PathBuf::from),
+            },
         })
     }
-}
 
-impl VirtualEnvironmentShell<'_> {
+    /// Directory with the executables of the virtual environment - `Scripts` on Windows, `bin` elsewhere.
+    fn bin_dir(&self) -> PathBuf {
+        if cfg!(target_os = "windows") {
+            self.path.join("Scripts")
+        } else {
+            self.path.join("bin")
+        }
+    }
+
+    /// Path to the Python interpreter of the virtual environment.
+    pub fn python_path(&self) -> PathBuf {
+        let python = if cfg!(target_os = "windows") {
+            "python.exe"
+        } else {
+            "python"
+        };
+        self.bin_dir().join(python)
+    }
+
+    /// Creates a command running the Python interpreter of the virtual environment,
+    /// with the same variables that the `activate` script sets.
+    fn python_command(&self) -> Command {
+        let mut paths = vec![self.bin_dir()];
+        if let Some(path) = std::env::var_os("PATH") {
+            paths.extend(std::env::split_paths(&path));
+        }
+
+        let mut command = Command::new(self.python_path());
+        command
+            .env("VIRTUAL_ENV", &self.path)
+            .env_remove("PYTHONHOME");
+        if let Ok(path) = std::env::join_paths(paths) {
+            command.env("PATH", path);
+        }
+        command
+    }
+
+    /// Runs the Python interpreter of the virtual environment with given arguments.
+    pub fn run_python<I, S>(&self, args: I, mode: OutputMode) -> UvResult<PythonOutput>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut command = self.python_command();
+        command.args(args).stdin(Stdio::null());
+
+        match mode {
+            OutputMode::Capture => {
+                let output = command.output()?;
+                Ok(PythonOutput {
+                    status: output.status.into(),
+                    stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+                    stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+                })
+            }
+            OutputMode::Stream => Ok(PythonOutput {
+                status: command.status()?.into(),
+                stdout: String::new(),
+                stderr: String::new(),
+            }),
+        }
+    }
+
     /// Runs a python script inside the virtual environment
-    pub fn run_python_script<T: AsRef<Path>>(&self, script_path: T) -> UvResult<()> {
-        todo!()
+    pub fn run_python_script<T, I, S>(
+        &self,
+        script_path: T,
+        args: I,
+        mode: OutputMode,
+    ) -> UvResult<PythonOutput>
+    where
+        T: AsRef<Path>,
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut script_args = vec![script_path.as_ref().as_os_str().to_os_string()];
+        script_args.extend(args.into_iter().map(|arg| arg.as_ref().to_os_string()));
+        self.run_python(script_args, mode)
     }
 
     /// Installs Python packages inside the virtual environment
-    pub fn install_packages<T: Into<String>>(&self, packages_list: &[T]) -> UvResult<()> {
-        todo!()
+    pub fn install_packages<T: AsRef<str>>(&self, uv: &Uv, packages_list: &[T]) -> UvResult<()> {
+        self.pip_install(uv, packages_list.iter().map(AsRef::as_ref))
+    }
+
+    /// Installs Python packages from requirements files inside the virtual environment
+    pub fn install_requirements<T: AsRef<Path>>(
+        &self,
+        uv: &Uv,
+        requirement_files: &[T],
+    ) -> UvResult<()> {
+        self.pip_install(
+            uv,
+            requirement_files
+                .iter()
+                .flat_map(|file| [OsStr::new("-r"), file.as_ref().as_os_str()]),
+        )
+    }
+
+    /// Returns the list of installed packages with their exact versions, in requirements format.
+    pub fn freeze(&self, uv: &Uv) -> UvResult<String> {
+        let output = Command::new(&uv.path)
+            .arg("pip")
+            .arg("freeze")
+            .arg("--python")
+            .arg(self.python_path())
+            .output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(std::io::Error::other(format!(
+                "'uv pip freeze' failed: {}",
+                stderr
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn pip_install<I, S>(&self, uv: &Uv, args: I) -> UvResult<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut command = Command::new(&uv.path);
+        command
+            .arg("pip")
+            .arg("install")
+            .arg("--python")
+            .arg(self.python_path())
+            .args(args);
+
+        let status = command.status()?;
+
+        if !status.success() {
+            return Err(std::io::Error::other(format!(
+                "'uv pip install' failed with status: {}",
+                status
+            )));
+        }
+
+        Ok(())
     }
 }
 
@@ -349,7 +560,7 @@ mod tests {
         }
 
         // Install
-        uv.install_python_version(version_to_test.clone()).unwrap();
+        uv.install_python_version(version_to_test).unwrap();
         let instances_after_install = uv.get_python_instances().unwrap();
         let installed_instance = instances_after_install
             .into_iter()
@@ -357,8 +568,7 @@ mod tests {
         assert!(installed_instance.is_some());
 
         // Uninstall
-        uv.uninstall_python_version(version_to_test.clone())
-            .unwrap();
+        uv.uninstall_python_version(version_to_test).unwrap();
         let instances_after_uninstall = uv.get_python_instances().unwrap();
         let uninstalled_instance = instances_after_uninstall
             .into_iter()
@@ -379,7 +589,7 @@ mod tests {
             .any(|i| i.version == version_to_test && i.path.is_some());
 
         if needs_install {
-            uv.install_python_version(version_to_test.clone()).unwrap();
+            uv.install_python_version(version_to_test).unwrap();
         }
 
         // Create a virtual environment
@@ -391,41 +601,47 @@ mod tests {
         assert_eq!(venv.python_instance.version, version_to_test);
 
         // Clean up
-        std::fs::remove_dir_all(&venv_path).unwrap_or_else(|_| ());
+        std::fs::remove_dir_all(&venv_path).unwrap_or(());
     }
 
     #[test]
-    #[serial]
-    fn test_create_shell() {
-        let uv = Uv::global().unwrap();
-
-        // Install Python version 3.8.20 if not already installed
-        let version_to_test = Version::from_str("3.8.20").unwrap();
-        let python_instances = uv.get_python_instances().unwrap();
-        let needs_install = !python_instances
-            .iter()
-            .any(|i| i.version == version_to_test && i.path.is_some());
-
-        if needs_install {
-            uv.install_python_version(version_to_test.clone()).unwrap();
-        }
-
-        // Create a virtual environment
-        let venv_path = std::env::temp_dir().join("test_venv_shell");
-        let venv = uv.create_venv(&venv_path, version_to_test).unwrap();
-
-        // Test creating shell - this should succeed without errors
-        let shell = venv.create_shell();
-        assert!(shell.is_ok());
-
-        // Verify the shell has access to Python binaries in the virtual environment
-        let shell = shell.unwrap();
+    #[cfg(unix)]
+    fn test_run_python_script() {
+        // Virtual environment using system Python, so the test does not need uv
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let venv_path = temp_dir.path().join("venv");
+        std::fs::create_dir_all(venv_path.join("bin")).unwrap();
+        std::fs::write(
+            venv_path.join("pyvenv.cfg"),
+            "home = /usr/bin\nversion_info = 3.12.3\n",
+        )
+        .unwrap();
+        std::os::unix::fs::symlink(
+            which::which("python3").unwrap(),
+            venv_path.join("bin").join("python"),
+        )
+        .unwrap();
+
+        let venv = VirtualEnvironment::open(&venv_path).unwrap();
+        assert_eq!(
+            venv.python_instance.version,
+            Version::from_str("3.12.3").unwrap()
+        );
 
-        // Test that we can execute a command in the shell context
-        // Note: This is a basic test, in a real scenario we'd want more comprehensive testing
-        // TODO: after implementing run_python_script, create an example python script in temp dir and try running it with venv.
+        let script_path = temp_dir.path().join("script.py");
+        std::fs::write(
+            &script_path,
+            "import os, sys\nprint(os.environ['VIRTUAL_ENV'], sys.argv[1])\nsys.exit(3)\n",
+        )
+        .unwrap();
 
-        // Clean up
-        std::fs::remove_dir_all(&venv_path).unwrap_or_else(|_| ());
+        let output = venv
+            .run_python_script(&script_path, ["argument"], OutputMode::Capture)
+            .unwrap();
+        assert_eq!(output.status, RunStatus::Failed(3));
+        assert_eq!(
+            output.stdout.trim(),
+            format!("{} argument", venv_path.display())
+        );
     }
 }
@@ -10,7 +10,8 @@ pub struct Version {
 }
 
 impl Version {
-    fn matches(&self, other: &Version) -> bool {
+    /// Returns `true` if versions are equal, treating missing components as wildcards.
+    pub fn matches(&self, other: &Version) -> bool {
         if self.major != other.major {
             return false;
         }
@@ -52,6 +53,17 @@ pub enum VersionParsingError {
     VersionNotFound,
 }
 
+impl Display for VersionParsingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VersionParsingError::ParseIntError(e) => write!(f, "invalid version number: {}", e),
+            VersionParsingError::VersionNotFound => write!(f, "version not found"),
+        }
+    }
+}
+
+impl std::error::Error for VersionParsingError {}
+
 impl FromStr for Version {
     type Err = VersionParsingError;
 
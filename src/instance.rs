@@ -0,0 +1,411 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Result, RuntimeError},
+    external_tools::ExternalTool,
+};
+
+const REPO_DIR_NAME: &str = "repo";
+const BUILD_DIR_NAME: &str = "build";
+const INSTALLS_DIR_NAME: &str = "installs";
+const CURRENT_INSTALL_LINK_NAME: &str = "current";
+const VENV_DIR_NAME: &str = "venv";
+const PYTHON_LOCK_FILE_NAME: &str = "requirements.lock";
+const INSTALL_STATE_FILE_NAME: &str = "install_state.toml";
+const METADATA_FILE_NAME: &str = "instance.toml";
+const DEFAULT_INSTANCE_FILE_NAME: &str = "default_instance";
+
+/// Single llama.cpp instance, stored in its own subdirectory of `paths.llama_dir`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instance {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Information about the instance, stored in `$LLAMA_INSTANCE_DIR/instance.toml`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstanceMetadata {
+    /// Name of the profile the instance has been built with.
+    pub profile: String,
+    /// Git commit of the currently installed build.
+    pub commit: Option<String>,
+    /// Date of the currently installed build.
+    pub built_at: Option<DateTime<Utc>>,
+    /// All the builds kept in `$LLAMA_INSTANCE_DIR/installs`.
+    #[serde(default)]
+    pub installs: Vec<InstallRecord>,
+    /// Digest of the Python requirements installed in the virtual environment.
+    #[serde(default)]
+    pub python_requirements: Option<String>,
+}
+
+/// Single versioned build of llama.cpp.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstallRecord {
+    /// Name of the build directory in `$LLAMA_INSTANCE_DIR/installs`.
+    pub id: String,
+    pub commit: String,
+    pub built_at: DateTime<Utc>,
+}
+
+impl Instance {
+    /// Creates a handle for the instance with given name.
+    /// Does not create anything on the disk.
+    pub fn new(llama_dir: impl AsRef<Path>, name: &str) -> Result<Self> {
+        validate_name(name)?;
+
+        Ok(Self {
+            name: name.to_string(),
+            path: llama_dir.as_ref().join(name),
+        })
+    }
+
+    /// Returns the list of all instances in `llama_dir`, sorted by name.
+    pub fn list(llama_dir: impl AsRef<Path>) -> Result<Vec<Self>> {
+        if !llama_dir.as_ref().exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut instances = Vec::new();
+        for entry in std::fs::read_dir(&llama_dir)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+
+            let name = entry.file_name().to_string_lossy().to_string();
+            if let Ok(instance) = Self::new(&llama_dir, &name) {
+                instances.push(instance);
+            }
+        }
+
+        instances.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(instances)
+    }
+
+    /// Returns the name of the default instance, if it has been set.
+    pub fn get_default(llama_dir: impl AsRef<Path>) -> Result<Option<String>> {
+        let path = llama_dir.as_ref().join(DEFAULT_INSTANCE_FILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let name = std::fs::read_to_string(path)?.trim().to_string();
+        Ok((!name.is_empty()).then_some(name))
+    }
+
+    /// Sets (or clears, if `None` is provided) the default instance.
+    pub fn set_default(llama_dir: impl AsRef<Path>, name: Option<&str>) -> Result<()> {
+        let path = llama_dir.as_ref().join(DEFAULT_INSTANCE_FILE_NAME);
+
+        match name {
+            Some(name) => {
+                std::fs::create_dir_all(&llama_dir)?;
+                std::fs::write(path, name)?;
+            }
+            None => {
+                if path.exists() {
+                    std::fs::remove_file(path)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.is_dir()
+    }
+
+    /// Directory with llama.cpp source code.
+    pub fn repo_dir(&self) -> PathBuf {
+        self.path.join(REPO_DIR_NAME)
+    }
+
+    /// Directory with CMake build files.
+    pub fn build_dir(&self) -> PathBuf {
+        self.path.join(BUILD_DIR_NAME)
+    }
+
+    /// Currently active installation of llama.cpp.
+    /// This is a link pointing to one of the versioned installs.
+    pub fn install_dir(&self) -> PathBuf {
+        self.path.join(CURRENT_INSTALL_LINK_NAME)
+    }
+
+    /// Directory with the binaries of currently active installation.
+    pub fn bin_dir(&self) -> PathBuf {
+        self.install_dir().join("bin")
+    }
+
+    /// Returns the path to the llama.cpp binary with given name from the active installation.
+    /// The name is given without the platform's executable suffix.
+    pub fn binary_path(&self, name: &str) -> Result<PathBuf> {
+        let path = self
+            .bin_dir()
+            .join(format!("{}{}", name, std::env::consts::EXE_SUFFIX));
+        if !path.exists() {
+            return Err(RuntimeError::new(
+                format!(
+                    "{} not found in instance '{}', run `llama-mgr install` first",
+                    name, self.name
+                ),
+                exitcode::UNAVAILABLE as u8,
+            ));
+        }
+        Ok(path)
+    }
+
+    /// Creates an external tool for the binary with given name, ensuring it can be run.
+    pub fn tool<T: ExternalTool>(&self, name: &str) -> Result<T> {
+        let tool = T::new(self.binary_path(name)?);
+        if !tool.is_available() {
+            return Err(RuntimeError::new(
+                format!(
+                    "{} of instance '{}' cannot be run, reinstall it with `llama-mgr install`",
+                    name, self.name
+                ),
+                exitcode::UNAVAILABLE as u8,
+            ));
+        }
+        Ok(tool)
+    }
+
+    /// Directory with all the versioned installs.
+    pub fn installs_dir(&self) -> PathBuf {
+        self.path.join(INSTALLS_DIR_NAME)
+    }
+
+    /// Installation prefix of the build with given ID.
+    pub fn versioned_install_dir(&self, id: &str) -> PathBuf {
+        self.installs_dir().join(id)
+    }
+
+    /// Returns the ID of the build that `install_dir` points to, if any.
+    pub fn current_install_id(&self) -> Result<Option<String>> {
+        let link = self.install_dir();
+        if link.symlink_metadata().is_err() {
+            return Ok(None);
+        }
+
+        let target = std::fs::read_link(&link)?;
+        Ok(target
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string()))
+    }
+
+    /// Atomically switches `install_dir` to the build with given ID.
+    /// The link is created next to the current one and renamed over it,
+    /// so there's no moment when `install_dir` does not exist.
+    pub fn set_current_install(&self, id: &str) -> Result<()> {
+        if !self.versioned_install_dir(id).is_dir() {
+            return Err(RuntimeError::new(
+                format!("Build '{}' does not exist in instance '{}'", id, self.name),
+                exitcode::NOINPUT as u8,
+            ));
+        }
+
+        let temporary_link = self.path.join(format!("{}.tmp", CURRENT_INSTALL_LINK_NAME));
+        if temporary_link.symlink_metadata().is_ok() {
+            std::fs::remove_file(&temporary_link)?;
+        }
+
+        // Relative target keeps the instance directory relocatable
+        let target = Path::new(INSTALLS_DIR_NAME).join(id);
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&target, &temporary_link)?;
+        #[cfg(windows)]
+        std::os::windows::fs::symlink_dir(&target, &temporary_link)?;
+
+        // Windows can't rename over an existing link, so it's briefly missing there
+        #[cfg(windows)]
+        if self.install_dir().symlink_metadata().is_ok() {
+            std::fs::remove_dir(self.install_dir())?;
+        }
+
+        std::fs::rename(&temporary_link, self.install_dir())?;
+        Ok(())
+    }
+
+    /// Python virtual environment directory.
+    pub fn venv_dir(&self) -> PathBuf {
+        self.path.join(VENV_DIR_NAME)
+    }
+
+    /// Exact versions of the Python packages installed in the virtual environment.
+    pub fn python_lock_path(&self) -> PathBuf {
+        self.path.join(PYTHON_LOCK_FILE_NAME)
+    }
+
+    /// Path to the file with installation pipeline state.
+    pub fn install_state_path(&self) -> PathBuf {
+        self.path.join(INSTALL_STATE_FILE_NAME)
+    }
+
+    /// Loads instance metadata, or returns `None` if it has not been created yet.
+    pub fn load_metadata(&self) -> Result<Option<InstanceMetadata>> {
+        let path = self.path.join(METADATA_FILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+        toml::from_str(&content).map(Some).map_err(|e| {
+            RuntimeError::new(
+                format!(
+                    "Instance metadata file {} is corrupted: {}",
+                    path.display(),
+                    e
+                ),
+                exitcode::DATAERR as u8,
+            )
+        })
+    }
+
+    pub fn save_metadata(&self, metadata: &InstanceMetadata) -> Result<()> {
+        let content = toml::to_string(metadata).map_err(|e| {
+            RuntimeError::new(
+                format!("Failed to serialize instance metadata: {}", e),
+                exitcode::SOFTWARE as u8,
+            )
+        })?;
+
+        std::fs::create_dir_all(&self.path)?;
+        std::fs::write(self.path.join(METADATA_FILE_NAME), content)?;
+        Ok(())
+    }
+
+    /// Removes the instance directory with all of its contents.
+    pub fn remove(&self) -> Result<()> {
+        std::fs::remove_dir_all(&self.path)?;
+        Ok(())
+    }
+}
+
+/// Instance names are used as directory names, so they cannot contain path separators.
+fn validate_name(name: &str) -> Result<()> {
+    let is_valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+
+    if !is_valid {
+        return Err(RuntimeError::new(
+            format!(
+                "Invalid instance name '{}'. Only letters, digits, '-', '_' and '.' are allowed.",
+                name
+            ),
+            exitcode::USAGE as u8,
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_invalid_instance_names() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(Instance::new(temp_dir.path(), "").is_err());
+        assert!(Instance::new(temp_dir.path(), "../escape").is_err());
+        assert!(Instance::new(temp_dir.path(), ".hidden").is_err());
+        assert!(Instance::new(temp_dir.path(), "with space").is_err());
+        assert!(Instance::new(temp_dir.path(), "stable-b1234_v1.0").is_ok());
+    }
+
+    #[test]
+    fn test_list_instances() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(
+            Instance::list(temp_dir.path().join("missing"))
+                .unwrap()
+                .is_empty()
+        );
+
+        std::fs::create_dir(temp_dir.path().join("vulkan")).unwrap();
+        std::fs::create_dir(temp_dir.path().join("cpu")).unwrap();
+        std::fs::write(temp_dir.path().join(DEFAULT_INSTANCE_FILE_NAME), "cpu").unwrap();
+
+        let names: Vec<String> = Instance::list(temp_dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|i| i.name)
+            .collect();
+        assert_eq!(names, vec!["cpu", "vulkan"]);
+    }
+
+    #[test]
+    fn test_default_instance() {
+        let temp_dir = TempDir::new().unwrap();
+        assert_eq!(Instance::get_default(temp_dir.path()).unwrap(), None);
+
+        Instance::set_default(temp_dir.path(), Some("stable")).unwrap();
+        assert_eq!(
+            Instance::get_default(temp_dir.path()).unwrap(),
+            Some("stable".to_string())
+        );
+
+        Instance::set_default(temp_dir.path(), None).unwrap();
+        assert_eq!(Instance::get_default(temp_dir.path()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_metadata_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let instance = Instance::new(temp_dir.path(), "stable").unwrap();
+        assert_eq!(instance.load_metadata().unwrap(), None);
+
+        let metadata = InstanceMetadata {
+            profile: "vulkan".to_string(),
+            commit: Some("0123456789abcdef".to_string()),
+            built_at: Some(Utc::now()),
+            installs: vec![InstallRecord {
+                id: "20250101-000000-0123456789".to_string(),
+                commit: "0123456789abcdef".to_string(),
+                built_at: Utc::now(),
+            }],
+            python_requirements: Some("fedcba9876543210".to_string()),
+        };
+        instance.save_metadata(&metadata).unwrap();
+
+        assert!(instance.exists());
+        assert_eq!(instance.load_metadata().unwrap(), Some(metadata));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_switch_current_install() {
+        let temp_dir = TempDir::new().unwrap();
+        let instance = Instance::new(temp_dir.path(), "stable").unwrap();
+        assert_eq!(instance.current_install_id().unwrap(), None);
+        assert!(instance.set_current_install("missing").is_err());
+
+        for id in ["first", "second"] {
+            let bin_dir = instance.versioned_install_dir(id).join("bin");
+            std::fs::create_dir_all(&bin_dir).unwrap();
+            std::fs::write(bin_dir.join("llama-server"), id).unwrap();
+        }
+
+        instance.set_current_install("first").unwrap();
+        assert_eq!(
+            instance.current_install_id().unwrap(),
+            Some("first".to_string())
+        );
+
+        instance.set_current_install("second").unwrap();
+        assert_eq!(
+            instance.current_install_id().unwrap(),
+            Some("second".to_string())
+        );
+        let server = instance.install_dir().join("bin").join("llama-server");
+        assert_eq!(std::fs::read_to_string(server).unwrap(), "second");
+    }
+}
@@ -7,9 +7,14 @@ mod commands;
 mod config;
 mod error;
 mod external_tools;
+mod instance;
+mod models;
+mod server;
 
 use crate::error::RuntimeError;
 use config::Config;
+use config::layers::ConfigSources;
+use instance::Instance;
 
 #[derive(Parser)]
 #[command(name = "llama-mgr", version, author, about)]
@@ -25,6 +30,10 @@ struct Cli {
     /// Profile to use
     #[arg(short, long, global = true)]
     profile: Option<String>,
+
+    /// llama.cpp instance to use
+    #[arg(long, global = true)]
+    instance: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -35,12 +44,20 @@ enum Commands {
     Uninstall(commands::uninstall::UninstallCommand),
     /// Run llama-quantize
     Quantize(commands::quantize::QuantizeCommand),
+    /// Compute the importance matrix of a model with llama-imatrix
+    Imatrix(commands::imatrix::ImatrixCommand),
     /// Convert a raw huggingface model to GGUF
     Convert(commands::convert::ConvertCommand),
     /// Run llama-server instances
     Server(commands::server::ServerCommand),
     /// Start the llama-mgr in daemon mode
     Daemon(commands::daemon::DaemonCommand),
+    /// Manage llama.cpp instances
+    Instance(commands::instance::InstanceCommand),
+    /// Inspect, modify and validate the configuration
+    Config(commands::config::ConfigCommand),
+    /// Manage the models in the models directory
+    Models(commands::models::ModelsCommand),
 }
 
 impl From<&Commands> for &str {
@@ -49,9 +66,13 @@ impl From<&Commands> for &str {
             Commands::Install(_) => "install",
             Commands::Uninstall(_) => "uninstall",
             Commands::Quantize(_) => "quantize",
+            Commands::Imatrix(_) => "imatrix",
             Commands::Convert(_) => "convert",
             Commands::Server(_) => "server",
             Commands::Daemon(_) => "daemon",
+            Commands::Instance(_) => "instance",
+            Commands::Config(_) => "config",
+            Commands::Models(_) => "models",
         }
     }
 }
@@ -63,15 +84,15 @@ fn create_default_config_file(config_path: &Path) -> std::result::Result<(), Run
         ));
     }
 
-    if let Some(parent) = config_path.parent() {
-        if !parent.exists() {
-            std::fs::create_dir_all(parent).map_err(|e| {
-                RuntimeError::new(
-                    format!("Failed to create config directory: {}", e),
-                    exitcode::CANTCREAT as u8,
-                )
-            })?;
-        }
+    if let Some(parent) = config_path.parent()
+        && !parent.exists()
+    {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            RuntimeError::new(
+                format!("Failed to create config directory: {}", e),
+                exitcode::CANTCREAT as u8,
+            )
+        })?;
     }
 
     let default_config = Config::default();
@@ -92,19 +113,51 @@ fn create_default_config_file(config_path: &Path) -> std::result::Result<(), Run
     Ok(())
 }
 
-/// Load configuration from file
-fn load_config(config_path: &str) -> Result<Config, Box<dyn std::error::Error>> {
+/// Find all the configuration layers, with user configuration file at `config_path`.
+/// If there are no configuration files at all, default user configuration file is created.
+fn config_sources(config_path: &str) -> error::Result<ConfigSources> {
     let expanded_path = shellexpand::tilde(config_path);
-    let config_path = PathBuf::from(expanded_path.as_ref());
+    let sources = ConfigSources::new(PathBuf::from(expanded_path.as_ref()));
 
-    if !config_path.exists() {
-        create_default_config_file(&config_path)?;
+    if !sources.any_file_exists() {
+        create_default_config_file(&sources.user)?;
     }
 
-    let config_str = std::fs::read_to_string(&config_path)?;
-    let config: Config = toml::from_str(&config_str)?;
+    Ok(sources)
+}
+
+/// Select the instance - explicitly requested one, the default one,
+/// or the one named after the selected profile.
+fn select_instance(cli: &Cli, config: &Config) -> error::Result<Instance> {
+    let llama_dir = &config.paths.llama_dir;
+
+    let name = match &cli.instance {
+        Some(name) => name.clone(),
+        None => match Instance::get_default(llama_dir)? {
+            Some(name) => name,
+            None => cli
+                .profile
+                .clone()
+                .unwrap_or_else(|| config.config.default_profile.clone()),
+        },
+    };
 
-    Ok(config)
+    Instance::new(llama_dir, &name)
+}
+
+fn handle_result(command_name: &str, result: error::Result<()>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            log::error!(
+                "Error occurred while executing command '{}' - {}",
+                command_name,
+                error.message,
+            );
+
+            error.exit_code
+        }
+    }
 }
 
 fn main() -> ExitCode {
@@ -115,55 +168,103 @@ fn main() -> ExitCode {
 
     let cli = Cli::parse();
 
+    // Supervisor of a detached server is started with everything it needs in the state file
+    if let Commands::Server(args) = &cli.command
+        && let Some((runtime_dir, id)) = args.supervised_server()
+    {
+        return handle_result("server", commands::server::supervise(runtime_dir, id));
+    }
+
+    let sources = match config_sources(&cli.config) {
+        Ok(sources) => sources,
+        Err(e) => {
+            log::error!("Failed to prepare configuration: {}", e);
+            return e.exit_code;
+        }
+    };
+
+    let command_name: &str = (&cli.command).into();
+
+    // Configuration command loads the configuration by itself,
+    // so broken configuration files can still be validated and fixed
+    if let Commands::Config(args) = cli.command {
+        let result = commands::config::run(args, &sources, cli.profile.as_deref());
+        return handle_result(command_name, result);
+    }
+
     // Load configuration
-    let config = match load_config(&cli.config) {
-        Ok(config) => config,
+    let loaded_config = match config::layers::load(&sources) {
+        Ok(loaded_config) => loaded_config,
         Err(e) => {
             log::error!("Failed to load configuration: {}", e);
-            return ExitCode::from(exitcode::NOINPUT as u8);
+            log::error!("Run `llama-mgr config validate` to find all the problems");
+            return e.exit_code;
+        }
+    };
+    let config = &loaded_config.config;
+
+    if let Commands::Models(args) = cli.command {
+        return handle_result(command_name, commands::models::run(args, config));
+    }
+
+    let instance = match select_instance(&cli, config) {
+        Ok(instance) => instance,
+        Err(e) => {
+            log::error!("Failed to select instance: {}", e);
+            return e.exit_code;
+        }
+    };
+
+    if let Commands::Instance(args) = cli.command {
+        return handle_result(command_name, commands::instance::run(args, config));
+    }
+
+    // Apply profile override from command line if provided,
+    // otherwise use the profile the instance has been built with
+    let instance_profile = match instance.load_metadata() {
+        Ok(metadata) => metadata
+            .map(|metadata| metadata.profile)
+            .filter(|profile| !profile.is_empty()),
+        Err(e) => {
+            log::error!("Failed to load instance metadata: {}", e);
+            return e.exit_code;
         }
     };
 
-    // Apply profile override from command line if provided
     let profile_name: &str = if let Some(profile) = &cli.profile {
         profile
+    } else if let Some(profile) = &instance_profile {
+        profile
     } else {
         &config.config.default_profile
     };
 
-    // Get the selected profile
-    let profile = match config.get_profile(Some(profile_name)) {
-        Some(profile) => profile,
-        None => {
-            log::error!("Profile '{}' not found in configuration", profile_name);
-            return ExitCode::from(exitcode::UNAVAILABLE as u8);
+    // Get the selected profile, merged with the profiles it extends
+    let profile = match config.resolve_profile(profile_name) {
+        Ok(profile) => profile,
+        Err(e) => {
+            log::error!("Failed to resolve profile: {}", e);
+            return e.exit_code;
         }
     };
 
     log::info!("Using profile: {}", profile_name);
-
-    let command_name: &str = (&cli.command).into();
+    log::info!("Using instance: {}", instance.name);
 
     let result = match cli.command {
-        Commands::Install(args) => commands::install::run(args, &config, profile),
-        Commands::Uninstall(args) => commands::uninstall::run(args, &config, profile),
-        Commands::Quantize(args) => commands::quantize::run(args, &config, profile),
-        Commands::Convert(args) => commands::convert::run(args, &config, profile),
-        Commands::Server(args) => commands::server::run(args, &config, profile),
-        Commands::Daemon(args) => commands::daemon::run(args, &config, profile),
+        Commands::Install(args) => {
+            commands::install::run(args, config, profile_name, &profile, &instance)
+        }
+        Commands::Uninstall(args) => commands::uninstall::run(args, config, &instance),
+        Commands::Quantize(args) => commands::quantize::run(args, config, &profile, &instance),
+        Commands::Imatrix(args) => commands::imatrix::run(args, config, &profile, &instance),
+        Commands::Convert(args) => commands::convert::run(args, config, &profile, &instance),
+        Commands::Server(args) => commands::server::run(args, config, &profile, &instance),
+        Commands::Daemon(args) => commands::daemon::run(args, config, &profile),
+        Commands::Instance(_) | Commands::Config(_) | Commands::Models(_) => {
+            unreachable!("command has already been handled")
+        }
     };
 
-    if result.is_err() {
-        let error = result.expect_err("Couldn't unwrap application's error code!");
-
-        log::error!(
-            "Error occurred while executing command '{}' - {}",
-            String::from(command_name),
-            error.message,
-        );
-
-        error.exit_code
-    } else {
-        ExitCode::SUCCESS
-    }
+    handle_result(command_name, result)
 }
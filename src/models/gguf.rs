@@ -0,0 +1,463 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use crate::error::{Result, RuntimeError};
+
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+/// Version 1 used 32-bit lengths and counts, and is not produced by llama.cpp for a long time.
+const MIN_SUPPORTED_VERSION: u32 = 2;
+const MAX_SUPPORTED_VERSION: u32 = 3;
+/// Longer arrays (like tokenizer vocabularies) are skipped, and only their length is kept.
+const MAX_ARRAY_VALUES: u64 = 16;
+/// Sanity limit for strings, protecting from allocating huge buffers for corrupted files.
+const MAX_STRING_LENGTH: u64 = 16 * 1024 * 1024;
+const MAX_TENSOR_DIMENSIONS: u32 = 8;
+
+/// Metadata value stored in GGUF file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GgufValue {
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Array(Vec<GgufValue>),
+    /// Array that has been too long to be kept in memory.
+    SkippedArray {
+        length: u64,
+    },
+}
+
+impl GgufValue {
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            GgufValue::Unsigned(value) => Some(*value),
+            GgufValue::Signed(value) => u64::try_from(*value).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            GgufValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl Display for GgufValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GgufValue::Unsigned(value) => write!(f, "{}", value),
+            GgufValue::Signed(value) => write!(f, "{}", value),
+            GgufValue::Float(value) => write!(f, "{}", value),
+            GgufValue::Bool(value) => write!(f, "{}", value),
+            GgufValue::String(value) => write!(f, "{:?}", value),
+            GgufValue::Array(values) => {
+                write!(f, "[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            GgufValue::SkippedArray { length } => write!(f, "[... {} values]", length),
+        }
+    }
+}
+
+/// Description of a single tensor, without its data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TensorInfo {
+    pub name: String,
+    pub dimensions: Vec<u64>,
+    /// `ggml_type` of the tensor data.
+    pub data_type: u32,
+}
+
+impl TensorInfo {
+    pub fn element_count(&self) -> u64 {
+        self.dimensions.iter().product()
+    }
+}
+
+/// Header and metadata of GGUF file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GgufFile {
+    pub version: u32,
+    pub metadata: BTreeMap<String, GgufValue>,
+    pub tensors: Vec<TensorInfo>,
+}
+
+impl GgufFile {
+    /// Reads the header, metadata and tensor descriptions of GGUF file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)?;
+        Self::read(&mut BufReader::new(file)).map_err(|e| {
+            RuntimeError::new(
+                format!("Failed to read GGUF file {}: {}", path.display(), e.message),
+                exitcode::DATAERR as u8,
+            )
+        })
+    }
+
+    pub fn read(reader: &mut impl Read) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != GGUF_MAGIC {
+            return Err(invalid_data("not a GGUF file"));
+        }
+
+        let version = read_u32(reader)?;
+        if !(MIN_SUPPORTED_VERSION..=MAX_SUPPORTED_VERSION).contains(&version) {
+            return Err(invalid_data(&format!(
+                "unsupported GGUF version {}",
+                version
+            )));
+        }
+
+        let tensor_count = read_u64(reader)?;
+        let metadata_count = read_u64(reader)?;
+
+        let mut metadata = BTreeMap::new();
+        for _ in 0..metadata_count {
+            let key = read_string(reader)?;
+            let value_type = read_u32(reader)?;
+            let value = read_value(reader, value_type)?;
+            metadata.insert(key, value);
+        }
+
+        let mut tensors = Vec::new();
+        for _ in 0..tensor_count {
+            let name = read_string(reader)?;
+            let dimension_count = read_u32(reader)?;
+            if dimension_count > MAX_TENSOR_DIMENSIONS {
+                return Err(invalid_data(&format!(
+                    "tensor {} has {} dimensions",
+                    name, dimension_count
+                )));
+            }
+
+            let dimensions = (0..dimension_count)
+                .map(|_| read_u64(reader))
+                .collect::<Result<Vec<_>>>()?;
+            let data_type = read_u32(reader)?;
+            // Offset of the tensor data is not needed
+            read_u64(reader)?;
+
+            tensors.push(TensorInfo {
+                name,
+                dimensions,
+                data_type,
+            });
+        }
+
+        Ok(Self {
+            version,
+            metadata,
+            tensors,
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&GgufValue> {
+        self.metadata.get(key)
+    }
+
+    pub fn architecture(&self) -> Option<&str> {
+        self.get("general.architecture").and_then(GgufValue::as_str)
+    }
+
+    /// Returns architecture-specific value, like `llama.context_length`.
+    pub fn architecture_value(&self, key: &str) -> Option<&GgufValue> {
+        self.get(&format!("{}.{}", self.architecture()?, key))
+    }
+
+    pub fn context_length(&self) -> Option<u64> {
+        self.architecture_value("context_length")
+            .and_then(GgufValue::as_u64)
+    }
+
+    /// Amount of parameters, calculated from the tensor dimensions.
+    pub fn parameter_count(&self) -> u64 {
+        self.tensors.iter().map(TensorInfo::element_count).sum()
+    }
+
+    /// Name of the quantization type declared in `general.file_type`.
+    pub fn file_type(&self) -> Option<String> {
+        let file_type = self.get("general.file_type")?.as_u64()?;
+        Some(
+            file_type_name(file_type)
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("unknown ({})", file_type)),
+        )
+    }
+}
+
+/// Names of `llama_ftype` values.
+fn file_type_name(file_type: u64) -> Option<&'static str> {
+    let name = match file_type {
+        0 => "F32",
+        1 => "F16",
+        2 => "Q4_0",
+        3 => "Q4_1",
+        7 => "Q8_0",
+        8 => "Q5_0",
+        9 => "Q5_1",
+        10 => "Q2_K",
+        11 => "Q3_K_S",
+        12 => "Q3_K_M",
+        13 => "Q3_K_L",
+        14 => "Q4_K_S",
+        15 => "Q4_K_M",
+        16 => "Q5_K_S",
+        17 => "Q5_K_M",
+        18 => "Q6_K",
+        19 => "IQ2_XXS",
+        20 => "IQ2_XS",
+        21 => "Q2_K_S",
+        22 => "IQ3_XS",
+        23 => "IQ3_XXS",
+        24 => "IQ1_S",
+        25 => "IQ4_NL",
+        26 => "IQ3_S",
+        27 => "IQ3_M",
+        28 => "IQ2_S",
+        29 => "IQ2_M",
+        30 => "IQ4_XS",
+        31 => "IQ1_M",
+        32 => "BF16",
+        36 => "TQ1_0",
+        37 => "TQ2_0",
+        38 => "MXFP4_MOE",
+        _ => return None,
+    };
+    Some(name)
+}
+
+fn invalid_data(message: &str) -> RuntimeError {
+    RuntimeError::new(message.to_string(), exitcode::DATAERR as u8)
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> Result<[u8; N]> {
+    let mut buffer = [0u8; N];
+    reader.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32> {
+    Ok(u32::from_le_bytes(read_bytes(reader)?))
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64> {
+    Ok(u64::from_le_bytes(read_bytes(reader)?))
+}
+
+fn read_string(reader: &mut impl Read) -> Result<String> {
+    let length = read_u64(reader)?;
+    if length > MAX_STRING_LENGTH {
+        return Err(invalid_data(&format!(
+            "string of {} bytes is too long",
+            length
+        )));
+    }
+
+    let mut buffer = Vec::new();
+    reader.take(length).read_to_end(&mut buffer)?;
+    if buffer.len() as u64 != length {
+        return Err(invalid_data("unexpected end of file"));
+    }
+
+    Ok(String::from_utf8_lossy(&buffer).into_owned())
+}
+
+fn read_value(reader: &mut impl Read, value_type: u32) -> Result<GgufValue> {
+    let value = match value_type {
+        0 => GgufValue::Unsigned(u8::from_le_bytes(read_bytes(reader)?).into()),
+        1 => GgufValue::Signed(i8::from_le_bytes(read_bytes(reader)?).into()),
+        2 => GgufValue::Unsigned(u16::from_le_bytes(read_bytes(reader)?).into()),
+        3 => GgufValue::Signed(i16::from_le_bytes(read_bytes(reader)?).into()),
+        4 => GgufValue::Unsigned(read_u32(reader)?.into()),
+        5 => GgufValue::Signed(i32::from_le_bytes(read_bytes(reader)?).into()),
+        6 => GgufValue::Float(f32::from_le_bytes(read_bytes(reader)?).into()),
+        7 => GgufValue::Bool(read_bytes::<1>(reader)?[0] != 0),
+        8 => GgufValue::String(read_string(reader)?),
+        9 => {
+            let item_type = read_u32(reader)?;
+            let length = read_u64(reader)?;
+            if length > MAX_ARRAY_VALUES {
+                skip_array(reader, item_type, length)?;
+                return Ok(GgufValue::SkippedArray { length });
+            }
+
+            GgufValue::Array(
+                (0..length)
+                    .map(|_| read_value(reader, item_type))
+                    .collect::<Result<_>>()?,
+            )
+        }
+        10 => GgufValue::Unsigned(read_u64(reader)?),
+        11 => GgufValue::Signed(i64::from_le_bytes(read_bytes(reader)?)),
+        12 => GgufValue::Float(f64::from_le_bytes(read_bytes(reader)?)),
+        _ => {
+            return Err(invalid_data(&format!(
+                "unknown metadata value type {}",
+                value_type
+            )));
+        }
+    };
+
+    Ok(value)
+}
+
+/// Skips array values without storing them.
+fn skip_array(reader: &mut impl Read, item_type: u32, length: u64) -> Result<()> {
+    let item_size: u64 = match item_type {
+        0 | 1 | 7 => 1,
+        2 | 3 => 2,
+        4..=6 => 4,
+        10..=12 => 8,
+        _ => {
+            for _ in 0..length {
+                read_value(reader, item_type)?;
+            }
+            return Ok(());
+        }
+    };
+
+    let size = item_size
+        .checked_mul(length)
+        .ok_or_else(|| invalid_data("array is too long"))?;
+    let skipped = std::io::copy(&mut reader.take(size), &mut std::io::sink())?;
+    if skipped != size {
+        return Err(invalid_data("unexpected end of file"));
+    }
+
+    Ok(())
+}
+
+/// Helpers for writing GGUF files in tests.
+#[cfg(test)]
+pub mod test_utils {
+    use std::path::Path;
+
+    pub enum Value<'a> {
+        U32(u32),
+        String(&'a str),
+        Strings(Vec<&'a str>),
+    }
+
+    fn write_string(buffer: &mut Vec<u8>, value: &str) {
+        buffer.extend((value.len() as u64).to_le_bytes());
+        buffer.extend(value.as_bytes());
+    }
+
+    /// Creates GGUF file with given metadata and tensors (name and dimensions).
+    pub fn write_gguf(path: &Path, metadata: &[(&str, Value)], tensors: &[(&str, &[u64])]) {
+        let mut buffer = Vec::new();
+        buffer.extend(b"GGUF");
+        buffer.extend(3u32.to_le_bytes());
+        buffer.extend((tensors.len() as u64).to_le_bytes());
+        buffer.extend((metadata.len() as u64).to_le_bytes());
+
+        for (key, value) in metadata {
+            write_string(&mut buffer, key);
+            match value {
+                Value::U32(value) => {
+                    buffer.extend(4u32.to_le_bytes());
+                    buffer.extend(value.to_le_bytes());
+                }
+                Value::String(value) => {
+                    buffer.extend(8u32.to_le_bytes());
+                    write_string(&mut buffer, value);
+                }
+                Value::Strings(values) => {
+                    buffer.extend(9u32.to_le_bytes());
+                    buffer.extend(8u32.to_le_bytes());
+                    buffer.extend((values.len() as u64).to_le_bytes());
+                    for value in values {
+                        write_string(&mut buffer, value);
+                    }
+                }
+            }
+        }
+
+        for (name, dimensions) in tensors {
+            write_string(&mut buffer, name);
+            buffer.extend((dimensions.len() as u32).to_le_bytes());
+            for dimension in dimensions.iter() {
+                buffer.extend(dimension.to_le_bytes());
+            }
+            // F32 data type, offset
+            buffer.extend(0u32.to_le_bytes());
+            buffer.extend(0u64.to_le_bytes());
+        }
+
+        std::fs::write(path, buffer).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_utils::{Value, write_gguf};
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_read_gguf() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("model.gguf");
+        let vocabulary: Vec<&str> = (0..100).map(|_| "token").collect();
+        write_gguf(
+            &path,
+            &[
+                ("general.architecture", Value::String("qwen3")),
+                ("general.file_type", Value::U32(15)),
+                ("qwen3.context_length", Value::U32(40960)),
+                ("general.tags", Value::Strings(vec!["text", "chat"])),
+                ("tokenizer.ggml.tokens", Value::Strings(vocabulary)),
+            ],
+            &[("token_embd.weight", &[4, 8]), ("output.weight", &[8, 2])],
+        );
+
+        let gguf = GgufFile::open(&path).unwrap();
+        assert_eq!(gguf.version, 3);
+        assert_eq!(gguf.architecture(), Some("qwen3"));
+        assert_eq!(gguf.context_length(), Some(40960));
+        assert_eq!(gguf.file_type().as_deref(), Some("Q4_K_M"));
+        assert_eq!(gguf.parameter_count(), 48);
+        assert_eq!(gguf.tensors.len(), 2);
+        assert_eq!(gguf.tensors[1].name, "output.weight");
+        assert_eq!(
+            gguf.get("general.tags").unwrap().to_string(),
+            r#"["text", "chat"]"#
+        );
+        assert_eq!(
+            gguf.get("tokenizer.ggml.tokens"),
+            Some(&GgufValue::SkippedArray { length: 100 })
+        );
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("model.gguf");
+
+        std::fs::write(&path, b"not a model").unwrap();
+        let error = GgufFile::open(&path).unwrap_err();
+        assert!(error.message.contains("not a GGUF file"), "{}", error);
+
+        write_gguf(
+            &path,
+            &[("general.architecture", Value::String("llama"))],
+            &[],
+        );
+        let content = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &content[..content.len() - 2]).unwrap();
+        assert!(GgufFile::open(&path).is_err());
+    }
+}
@@ -0,0 +1,372 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, RuntimeError};
+use crate::models::find_files;
+
+const CONFIG_FILE_NAME: &str = "config.json";
+const SNAPSHOTS_DIR_NAME: &str = "snapshots";
+const REFS_DIR_NAME: &str = "refs";
+const REPOSITORY_DIR_PREFIX: &str = "models--";
+const REPOSITORY_SEPARATOR: &str = "--";
+const DEFAULT_REVISION: &str = "main";
+const WEIGHT_EXTENSIONS: [&str; 3] = ["safetensors", "bin", "pt"];
+
+/// Hugging Face checkpoint registered in the model index with `models import`, ready to be converted to GGUF.
+/// Checkpoint files are not copied, the entry points to the snapshot directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointEntry {
+    pub name: String,
+    /// Absolute path of the snapshot directory.
+    pub path: PathBuf,
+    /// Repository ID, like `Qwen/Qwen3-8B`, if the checkpoint comes from the Hugging Face cache.
+    pub repository: Option<String>,
+    /// Commit hash of the snapshot, if the checkpoint comes from the Hugging Face cache.
+    pub revision: Option<String>,
+    /// First entry of `architectures` from `config.json`, like `Qwen3ForCausalLM`.
+    pub architecture: Option<String>,
+    pub model_type: Option<String>,
+    /// Data type of the weights, from `torch_dtype` (or `dtype`) in `config.json`.
+    pub dtype: Option<String>,
+    /// Total size of the snapshot files.
+    pub size: u64,
+    pub imported: DateTime<Utc>,
+}
+
+/// Subset of `config.json` used by the index.
+#[derive(Debug, Default, Deserialize)]
+struct CheckpointConfig {
+    #[serde(default)]
+    architectures: Vec<String>,
+    model_type: Option<String>,
+    torch_dtype: Option<String>,
+    dtype: Option<String>,
+    /// Multimodal models keep the language model parameters in a nested config.
+    text_config: Option<Box<CheckpointConfig>>,
+}
+
+impl CheckpointConfig {
+    fn dtype(&self) -> Option<String> {
+        self.torch_dtype
+            .clone()
+            .or_else(|| self.dtype.clone())
+            .or_else(|| self.text_config.as_ref().and_then(|config| config.dtype()))
+    }
+}
+
+/// Returns the Hugging Face hub cache directory, respecting `HF_HUB_CACHE` and `HF_HOME` variables.
+pub fn hub_cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("HF_HUB_CACHE") {
+        return Some(PathBuf::from(dir));
+    }
+    if let Some(dir) = std::env::var_os("HF_HOME") {
+        return Some(PathBuf::from(dir).join("hub"));
+    }
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache/huggingface/hub"))
+}
+
+/// Finds the checkpoint directory. Source can be a repository ID (`Qwen/Qwen3-8B`) looked up in
+/// the hub cache, a cached repository directory (`models--Qwen--Qwen3-8B`), a snapshot directory,
+/// or any directory with `config.json`. Revision selects the snapshot of a cached repository,
+/// and can be either a ref name (like `main`) or a commit hash prefix.
+pub fn locate_checkpoint(
+    source: &str,
+    cache_dir: Option<&Path>,
+    revision: Option<&str>,
+) -> Result<PathBuf> {
+    let path = Path::new(source);
+    let repository_dir = if path.is_dir() {
+        if !path.join(SNAPSHOTS_DIR_NAME).is_dir() {
+            if revision.is_some() {
+                return Err(RuntimeError::new(
+                    format!(
+                        "{} is not a cached Hugging Face repository, revision cannot be selected",
+                        path.display()
+                    ),
+                    exitcode::USAGE as u8,
+                ));
+            }
+            return Ok(std::path::absolute(path)?);
+        }
+        path.to_path_buf()
+    } else {
+        let Some(cache_dir) = cache_dir else {
+            return Err(RuntimeError::new(
+                format!(
+                    "{} does not exist, and Hugging Face cache directory is unknown",
+                    source
+                ),
+                exitcode::NOINPUT as u8,
+            ));
+        };
+        let repository_dir = cache_dir.join(repository_dir_name(source));
+        if !repository_dir.is_dir() {
+            return Err(RuntimeError::new(
+                format!(
+                    "{} is neither a directory nor a repository in Hugging Face cache ({})",
+                    source,
+                    cache_dir.display()
+                ),
+                exitcode::NOINPUT as u8,
+            ));
+        }
+        repository_dir
+    };
+
+    let snapshot = select_snapshot(&repository_dir, revision)?;
+    Ok(std::path::absolute(
+        repository_dir.join(SNAPSHOTS_DIR_NAME).join(snapshot),
+    )?)
+}
+
+/// Returns the commit hash of the snapshot matching the revision.
+fn select_snapshot(repository_dir: &Path, revision: Option<&str>) -> Result<String> {
+    let mut snapshots = Vec::new();
+    for entry in std::fs::read_dir(repository_dir.join(SNAPSHOTS_DIR_NAME))? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            snapshots.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+    snapshots.sort();
+
+    let reference = revision.unwrap_or(DEFAULT_REVISION);
+    let ref_path = repository_dir.join(REFS_DIR_NAME).join(reference);
+    if let Ok(commit) = std::fs::read_to_string(&ref_path) {
+        let commit = commit.trim().to_string();
+        if snapshots.contains(&commit) {
+            return Ok(commit);
+        }
+        return Err(RuntimeError::new(
+            format!(
+                "Ref '{}' of {} points to commit {}, which is not downloaded",
+                reference,
+                repository_dir.display(),
+                commit
+            ),
+            exitcode::NOINPUT as u8,
+        ));
+    }
+
+    let matching: Vec<&String> = match revision {
+        Some(revision) => snapshots
+            .iter()
+            .filter(|snapshot| snapshot.starts_with(revision))
+            .collect(),
+        None => snapshots.iter().collect(),
+    };
+
+    match matching.as_slice() {
+        [snapshot] => Ok(snapshot.to_string()),
+        [] => Err(RuntimeError::new(
+            format!(
+                "No snapshot of {} matches revision '{}'",
+                repository_dir.display(),
+                reference
+            ),
+            exitcode::NOINPUT as u8,
+        )),
+        _ => Err(RuntimeError::new(
+            format!(
+                "{} has multiple snapshots, select one with --revision: {}",
+                repository_dir.display(),
+                matching
+                    .iter()
+                    .map(|snapshot| snapshot.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            exitcode::USAGE as u8,
+        )),
+    }
+}
+
+/// Reads the checkpoint's `config.json` and files. Files in the hub cache are symlinks to blobs,
+/// which are resolved to find missing blobs of incomplete downloads and calculate the real size.
+pub fn inspect_checkpoint(path: &Path, name: Option<&str>) -> Result<CheckpointEntry> {
+    let config_path = path.join(CONFIG_FILE_NAME);
+    let config_content = std::fs::read_to_string(&config_path).map_err(|e| {
+        RuntimeError::new(
+            format!("Failed to read {}: {}", config_path.display(), e),
+            exitcode::NOINPUT as u8,
+        )
+    })?;
+    let config: CheckpointConfig = serde_json::from_str(&config_content).map_err(|e| {
+        RuntimeError::new(
+            format!("Invalid {}: {}", config_path.display(), e),
+            exitcode::DATAERR as u8,
+        )
+    })?;
+
+    let mut size = 0;
+    let mut has_weights = false;
+    for file in find_files(path)? {
+        let metadata = std::fs::metadata(&file).map_err(|e| {
+            RuntimeError::new(
+                format!(
+                    "Failed to resolve {}, the download may be incomplete: {}",
+                    file.display(),
+                    e
+                ),
+                exitcode::NOINPUT as u8,
+            )
+        })?;
+        size += metadata.len();
+        has_weights |= file
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| WEIGHT_EXTENSIONS.contains(&extension));
+    }
+    if !has_weights {
+        log::warn!(
+            "No model weights found in {}, only vocabulary can be converted",
+            path.display()
+        );
+    }
+
+    let (repository, revision) = cache_location(path).unzip();
+    let name = name
+        .map(str::to_string)
+        .or_else(|| repository.clone())
+        .or_else(|| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        })
+        .ok_or_else(|| {
+            RuntimeError::new(
+                format!("Cannot derive the name of {}", path.display()),
+                exitcode::USAGE as u8,
+            )
+        })?;
+
+    Ok(CheckpointEntry {
+        name,
+        path: path.to_path_buf(),
+        repository,
+        revision,
+        architecture: config.architectures.first().cloned(),
+        model_type: config.model_type.clone(),
+        dtype: config.dtype(),
+        size,
+        imported: Utc::now(),
+    })
+}
+
+/// Returns the repository ID and commit hash of the snapshot directory in the hub cache.
+fn cache_location(snapshot: &Path) -> Option<(String, String)> {
+    let revision = snapshot.file_name()?.to_str()?;
+    let snapshots_dir = snapshot.parent()?;
+    if snapshots_dir.file_name()? != SNAPSHOTS_DIR_NAME {
+        return None;
+    }
+
+    let repository = snapshots_dir
+        .parent()?
+        .file_name()?
+        .to_str()?
+        .strip_prefix(REPOSITORY_DIR_PREFIX)?
+        .replace(REPOSITORY_SEPARATOR, "/");
+    Some((repository, revision.to_string()))
+}
+
+fn repository_dir_name(repository: &str) -> String {
+    format!(
+        "{}{}",
+        REPOSITORY_DIR_PREFIX,
+        repository.replace('/', REPOSITORY_SEPARATOR)
+    )
+}
+
+// Hugging Face cache is recreated with symlinks, the way `huggingface_hub` lays it out on unix
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const COMMIT: &str = "0123456789abcdef";
+
+    /// Creates the repository in the same layout as `huggingface_hub` does.
+    fn create_cached_repository(cache_dir: &Path, repository: &str, weights: bool) -> PathBuf {
+        let repository_dir = cache_dir.join(repository_dir_name(repository));
+        let blobs_dir = repository_dir.join("blobs");
+        let snapshot_dir = repository_dir.join(SNAPSHOTS_DIR_NAME).join(COMMIT);
+        std::fs::create_dir_all(&blobs_dir).unwrap();
+        std::fs::create_dir_all(&snapshot_dir).unwrap();
+        std::fs::create_dir_all(repository_dir.join(REFS_DIR_NAME)).unwrap();
+        std::fs::write(repository_dir.join(REFS_DIR_NAME).join("main"), COMMIT).unwrap();
+
+        std::fs::write(
+            blobs_dir.join("config"),
+            r#"{"architectures": ["Qwen3ForCausalLM"], "model_type": "qwen3", "torch_dtype": "bfloat16"}"#,
+        )
+        .unwrap();
+        std::os::unix::fs::symlink("../../blobs/config", snapshot_dir.join(CONFIG_FILE_NAME))
+            .unwrap();
+
+        if weights {
+            std::fs::write(blobs_dir.join("weights"), "0123456789").unwrap();
+        }
+        std::os::unix::fs::symlink(
+            "../../blobs/weights",
+            snapshot_dir.join("model.safetensors"),
+        )
+        .unwrap();
+
+        snapshot_dir
+    }
+
+    #[test]
+    fn test_locate_checkpoint() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_dir = temp_dir.path();
+        let snapshot_dir = create_cached_repository(cache_dir, "Qwen/Qwen3-8B", true);
+        let repository_dir = cache_dir.join("models--Qwen--Qwen3-8B");
+
+        let located =
+            |source: &str, revision| locate_checkpoint(source, Some(cache_dir), revision).unwrap();
+        assert_eq!(located("Qwen/Qwen3-8B", None), snapshot_dir);
+        assert_eq!(
+            located(repository_dir.to_str().unwrap(), None),
+            snapshot_dir
+        );
+        assert_eq!(located("Qwen/Qwen3-8B", Some("0123")), snapshot_dir);
+        assert_eq!(located(snapshot_dir.to_str().unwrap(), None), snapshot_dir);
+
+        assert!(locate_checkpoint("Qwen/Qwen3-8B", Some(cache_dir), Some("fff")).is_err());
+        assert!(locate_checkpoint("Qwen/Missing", Some(cache_dir), None).is_err());
+
+        // Without refs/main, the only snapshot is used
+        std::fs::remove_file(repository_dir.join(REFS_DIR_NAME).join("main")).unwrap();
+        assert_eq!(located("Qwen/Qwen3-8B", None), snapshot_dir);
+        std::fs::create_dir(repository_dir.join(SNAPSHOTS_DIR_NAME).join("fedcba")).unwrap();
+        assert!(locate_checkpoint("Qwen/Qwen3-8B", Some(cache_dir), None).is_err());
+    }
+
+    #[test]
+    fn test_inspect_checkpoint() {
+        let temp_dir = TempDir::new().unwrap();
+        let snapshot_dir = create_cached_repository(temp_dir.path(), "Qwen/Qwen3-8B", true);
+
+        let checkpoint = inspect_checkpoint(&snapshot_dir, None).unwrap();
+        assert_eq!(checkpoint.name, "Qwen/Qwen3-8B");
+        assert_eq!(checkpoint.repository.as_deref(), Some("Qwen/Qwen3-8B"));
+        assert_eq!(checkpoint.revision.as_deref(), Some(COMMIT));
+        assert_eq!(checkpoint.architecture.as_deref(), Some("Qwen3ForCausalLM"));
+        assert_eq!(checkpoint.model_type.as_deref(), Some("qwen3"));
+        assert_eq!(checkpoint.dtype.as_deref(), Some("bfloat16"));
+        // Size of the blobs, not the symlinks
+        assert!(checkpoint.size > 10);
+
+        let checkpoint = inspect_checkpoint(&snapshot_dir, Some("qwen3-base")).unwrap();
+        assert_eq!(checkpoint.name, "qwen3-base");
+    }
+
+    #[test]
+    fn test_inspect_incomplete_checkpoint() {
+        let temp_dir = TempDir::new().unwrap();
+        let snapshot_dir = create_cached_repository(temp_dir.path(), "Qwen/Qwen3-8B", false);
+        assert!(inspect_checkpoint(&snapshot_dir, None).is_err());
+    }
+}
@@ -0,0 +1,691 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::error::{Result, RuntimeError};
+
+pub mod gguf;
+pub mod huggingface;
+pub mod store;
+
+use gguf::{GgufFile, GgufValue};
+use huggingface::CheckpointEntry;
+
+const INDEX_FILE_NAME: &str = "index.toml";
+const MODEL_EXTENSION: &str = "gguf";
+const IMATRIX_EXTENSION: &str = "imatrix";
+/// Version of the index format, indexes with other versions are rebuilt.
+const INDEX_VERSION: u32 = 1;
+/// Tag used when the reference does not specify one, like in ollama.
+const DEFAULT_TAG: &str = "latest";
+
+/// Information about a single model file, stored in the index.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelEntry {
+    /// Path of the file, relative to `paths.models_dir`.
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: DateTime<Utc>,
+    /// Base name of the model from `general.basename`, like `Qwen3`.
+    pub basename: Option<String>,
+    /// Size of the model from `general.size_label`, like `8B`.
+    pub size_label: Option<String>,
+    pub architecture: Option<String>,
+    pub parameter_count: u64,
+    pub context_length: Option<u64>,
+    pub quant_type: Option<String>,
+    pub tensor_count: u64,
+}
+
+impl ModelEntry {
+    fn from_gguf(path: PathBuf, size: u64, modified: DateTime<Utc>, gguf: &GgufFile) -> Self {
+        Self {
+            path,
+            size,
+            modified,
+            basename: gguf
+                .get("general.basename")
+                .and_then(GgufValue::as_str)
+                .map(str::to_string),
+            size_label: gguf
+                .get("general.size_label")
+                .and_then(GgufValue::as_str)
+                .map(str::to_string),
+            architecture: gguf.architecture().map(str::to_string),
+            parameter_count: gguf.parameter_count(),
+            context_length: gguf.context_length(),
+            quant_type: gguf.file_type(),
+            tensor_count: gguf.tensors.len() as u64,
+        }
+    }
+
+    /// Name of the model - its path without the extension, with `/` as separator.
+    pub fn name(&self) -> String {
+        self.path
+            .with_extension("")
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Family of the model, the part of the reference before `:`. It's taken from
+    /// model's base name, or architecture if the base name is not set.
+    pub fn family(&self) -> Option<String> {
+        let family = self.basename.as_ref().or(self.architecture.as_ref())?;
+        Some(
+            family
+                .trim()
+                .to_lowercase()
+                .replace(char::is_whitespace, "-"),
+        )
+    }
+
+    /// Tag of the model, the part of the reference after `:`, made of size and quantization type.
+    pub fn tag(&self) -> String {
+        let size = self
+            .size_label
+            .clone()
+            .unwrap_or_else(|| format_parameter_count(self.parameter_count));
+
+        match &self.quant_type {
+            Some(quant_type) => format!("{}-{}", size, quant_type).to_lowercase(),
+            None => size.to_lowercase(),
+        }
+    }
+
+    /// Ollama-style reference to the model, like `qwen3:8b-q4_k_m`.
+    pub fn reference(&self) -> Option<String> {
+        Some(format!("{}:{}", self.family()?, self.tag()))
+    }
+}
+
+/// Model file that could not be parsed. It's recorded in the index, so it's not parsed
+/// (and warned about) again until it changes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InvalidModelFile {
+    /// Path of the file, relative to `paths.models_dir`.
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: DateTime<Utc>,
+    pub error: String,
+}
+
+/// Result of the index update.
+#[derive(Debug, Default, PartialEq)]
+pub struct IndexUpdate {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    /// Amount of the files that failed to parse for the first time since they changed.
+    pub invalid: usize,
+}
+
+impl IndexUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.added == 0 && self.updated == 0 && self.removed == 0 && self.invalid == 0
+    }
+}
+
+/// Persistent index of the models in `paths.models_dir`, stored in `index.toml` inside it.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelIndex {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub models: Vec<ModelEntry>,
+    /// Imported Hugging Face checkpoints, which can be converted to GGUF.
+    #[serde(default)]
+    pub checkpoints: Vec<CheckpointEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub invalid: Vec<InvalidModelFile>,
+}
+
+impl ModelIndex {
+    fn path(models_dir: &Path) -> PathBuf {
+        models_dir.join(INDEX_FILE_NAME)
+    }
+
+    /// Loads the index, or returns an empty one if it has not been created yet.
+    pub fn load(models_dir: impl AsRef<Path>) -> Result<Self> {
+        let path = Self::path(models_dir.as_ref());
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+        toml::from_str(&content).map_err(|e| {
+            RuntimeError::new(
+                format!("Model index {} is corrupted: {}", path.display(), e),
+                exitcode::DATAERR as u8,
+            )
+        })
+    }
+
+    pub fn save(&self, models_dir: impl AsRef<Path>) -> Result<()> {
+        let content = toml::to_string(self).map_err(|e| {
+            RuntimeError::new(
+                format!("Failed to serialize model index: {}", e),
+                exitcode::SOFTWARE as u8,
+            )
+        })?;
+
+        std::fs::create_dir_all(&models_dir)?;
+        std::fs::write(Self::path(models_dir.as_ref()), content)?;
+        Ok(())
+    }
+
+    /// Loads the index, brings it up to date with the models directory and saves it if anything changed.
+    pub fn refresh(models_dir: impl AsRef<Path>) -> Result<Self> {
+        let models_dir = models_dir.as_ref();
+        let mut index = Self::load(models_dir)?;
+        let update = index.update(models_dir)?;
+
+        if !update.is_empty() {
+            log::info!(
+                "Model index updated: {} added, {} updated, {} removed, {} invalid",
+                update.added,
+                update.updated,
+                update.removed,
+                update.invalid
+            );
+            index.save(models_dir)?;
+        }
+
+        Ok(index)
+    }
+
+    /// Scans the models directory. Only the files that are new, or have different size or
+    /// modification time than the indexed ones are parsed. Files that failed to parse are
+    /// skipped until they change. Files and checkpoints that no longer exist are removed.
+    pub fn update(&mut self, models_dir: impl AsRef<Path>) -> Result<IndexUpdate> {
+        let models_dir = models_dir.as_ref();
+        let mut update = IndexUpdate::default();
+        let mut models = Vec::new();
+        let mut invalid = Vec::new();
+
+        if self.version != INDEX_VERSION {
+            log::debug!("Model index has version {}, rebuilding it", self.version);
+            update.removed = self.models.len();
+            self.models.clear();
+            self.invalid.clear();
+            self.version = INDEX_VERSION;
+        }
+
+        for path in find_model_files(models_dir)? {
+            let metadata = std::fs::metadata(&path)?;
+            let size = metadata.len();
+            let modified =
+                DateTime::<Utc>::from(metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH));
+            let relative_path = path.strip_prefix(models_dir).unwrap_or(&path).to_path_buf();
+
+            let existing = self.models.iter().find(|model| model.path == relative_path);
+            if let Some(existing) = existing
+                && existing.size == size
+                && existing.modified == modified
+            {
+                models.push(existing.clone());
+                continue;
+            }
+
+            let known_invalid = self.invalid.iter().find(|file| {
+                file.path == relative_path && file.size == size && file.modified == modified
+            });
+            if let Some(known_invalid) = known_invalid {
+                log::debug!("Skipping invalid model {}", path.display());
+                invalid.push(known_invalid.clone());
+                continue;
+            }
+
+            log::debug!("Indexing model {}", path.display());
+            let gguf = match GgufFile::open(&path) {
+                Ok(gguf) => gguf,
+                Err(e) => {
+                    log::warn!("Skipping model: {}", e);
+                    update.invalid += 1;
+                    invalid.push(InvalidModelFile {
+                        path: relative_path,
+                        size,
+                        modified,
+                        error: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            match existing {
+                Some(_) => update.updated += 1,
+                None => update.added += 1,
+            }
+            models.push(ModelEntry::from_gguf(relative_path, size, modified, &gguf));
+        }
+
+        update.removed += self
+            .models
+            .iter()
+            .filter(|model| !models.iter().any(|new| new.path == model.path))
+            .count();
+
+        models.sort_by(|a, b| a.path.cmp(&b.path));
+        self.models = models;
+        invalid.sort_by(|a, b| a.path.cmp(&b.path));
+        self.invalid = invalid;
+
+        let checkpoint_count = self.checkpoints.len();
+        self.checkpoints.retain(|checkpoint| {
+            let exists = checkpoint.path.is_dir();
+            if !exists {
+                log::debug!(
+                    "Removing checkpoint {}, {} no longer exists",
+                    checkpoint.name,
+                    checkpoint.path.display()
+                );
+            }
+            exists
+        });
+        update.removed += checkpoint_count - self.checkpoints.len();
+
+        Ok(update)
+    }
+
+    /// Refreshes the index after a model has been written to `path`, and returns its entry.
+    /// Returns `None` if the path is outside the models directory, or the model could not be indexed.
+    pub fn register(models_dir: impl AsRef<Path>, path: &Path) -> Result<Option<ModelEntry>> {
+        let models_dir = std::path::absolute(models_dir)?;
+        let Ok(relative_path) = std::path::absolute(path)?
+            .strip_prefix(&models_dir)
+            .map(Path::to_path_buf)
+        else {
+            log::warn!(
+                "{} is outside of the models directory, so it will not be indexed",
+                path.display()
+            );
+            return Ok(None);
+        };
+
+        let index = Self::refresh(&models_dir)?;
+        let model = index.find(&relative_path.to_string_lossy()).cloned();
+        if model.is_none() {
+            log::warn!(
+                "{} could not be indexed, it may not be a valid GGUF file",
+                path.display()
+            );
+        }
+        Ok(model)
+    }
+
+    /// Adds the checkpoint to the index, replacing the one with the same name.
+    /// Returns `true` if the checkpoint has been replaced.
+    pub fn register_checkpoint(&mut self, checkpoint: CheckpointEntry) -> bool {
+        let replaced = self
+            .checkpoints
+            .iter()
+            .position(|existing| existing.name == checkpoint.name)
+            .map(|position| self.checkpoints.remove(position))
+            .is_some();
+
+        self.checkpoints.push(checkpoint);
+        self.checkpoints.sort_by(|a, b| a.name.cmp(&b.name));
+        replaced
+    }
+
+    /// Finds the imported checkpoint by its name.
+    pub fn find_checkpoint(&self, name: &str) -> Option<&CheckpointEntry> {
+        self.checkpoints
+            .iter()
+            .find(|checkpoint| checkpoint.name == name)
+    }
+
+    /// Finds the model by its name or path relative to the models directory.
+    pub fn find(&self, name: &str) -> Option<&ModelEntry> {
+        self.models
+            .iter()
+            .find(|model| model.name() == name || model.path == Path::new(name))
+    }
+
+    /// Resolves the model reference. Reference can be an alias, name of the model,
+    /// or ollama-style `family:tag` reference. Tag can be shortened to the size (`qwen3:8b`),
+    /// or omitted if there's only one model in the family.
+    pub fn resolve(
+        &self,
+        reference: &str,
+        aliases: &BTreeMap<String, String>,
+    ) -> Result<&ModelEntry> {
+        let reference = aliases
+            .get(reference)
+            .map(String::as_str)
+            .unwrap_or(reference);
+
+        if let Some(model) = self.find(reference) {
+            return Ok(model);
+        }
+
+        let (family, tag) = match reference.split_once(':') {
+            Some((family, tag)) => (family, Some(tag.to_lowercase())),
+            None => (reference, None),
+        };
+        let tag = tag.filter(|tag| tag != DEFAULT_TAG);
+        let family = family.to_lowercase();
+
+        let family_models: Vec<&ModelEntry> = self
+            .models
+            .iter()
+            .filter(|model| model.family().as_deref() == Some(family.as_str()))
+            .collect();
+        if family_models.is_empty() {
+            return Err(RuntimeError::new(
+                format!("Model '{}' not found", reference),
+                exitcode::NOINPUT as u8,
+            ));
+        }
+
+        let candidates: Vec<&ModelEntry> = match &tag {
+            None => family_models.clone(),
+            Some(tag) => {
+                let exact: Vec<&ModelEntry> = family_models
+                    .iter()
+                    .copied()
+                    .filter(|model| &model.tag() == tag)
+                    .collect();
+                if exact.is_empty() {
+                    let prefix = format!("{}-", tag);
+                    family_models
+                        .iter()
+                        .copied()
+                        .filter(|model| model.tag().starts_with(&prefix))
+                        .collect()
+                } else {
+                    exact
+                }
+            }
+        };
+
+        match candidates.as_slice() {
+            [model] => Ok(model),
+            [] => {
+                let mut tags: Vec<String> = family_models.iter().map(|model| model.tag()).collect();
+                tags.sort();
+                tags.dedup();
+                Err(RuntimeError::new(
+                    format!(
+                        "Model '{}' not found, available tags of '{}': {}",
+                        reference,
+                        family,
+                        tags.join(", ")
+                    ),
+                    exitcode::NOINPUT as u8,
+                ))
+            }
+            candidates => Err(RuntimeError::new(
+                format!(
+                    "Model reference '{}' is ambiguous, it matches: {}",
+                    reference,
+                    candidates
+                        .iter()
+                        .map(|model| format!(
+                            "{} ({})",
+                            model.reference().unwrap_or_default(),
+                            model.path.display()
+                        ))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                exitcode::USAGE as u8,
+            )),
+        }
+    }
+}
+
+/// Resolves the model reference to the path of GGUF file. Paths of existing files are returned
+/// as they are, other references are resolved with the model index and aliases from configuration.
+pub fn resolve_model_path(config: &Config, reference: &str) -> Result<PathBuf> {
+    let path = Path::new(reference);
+    if path.is_file() {
+        return Ok(path.to_path_buf());
+    }
+
+    let models_dir = &config.paths.models_dir;
+    let index = ModelIndex::refresh(models_dir)?;
+    let model = index.resolve(reference, &config.models.aliases)?;
+    Ok(models_dir.join(&model.path))
+}
+
+/// Path of the cached importance matrix of the model, kept next to it (`qwen3-8b-bf16.imatrix`).
+pub fn imatrix_path(model_path: &Path) -> PathBuf {
+    model_path.with_extension(IMATRIX_EXTENSION)
+}
+
+/// Returns `true` if the output exists and is newer than all the inputs it has been made from.
+pub fn is_up_to_date(output: &Path, inputs: &[&Path]) -> bool {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|metadata| metadata.modified());
+    let Ok(output_modified) = modified(output) else {
+        return false;
+    };
+
+    inputs
+        .iter()
+        .all(|input| modified(input).is_ok_and(|input_modified| output_modified >= input_modified))
+}
+
+/// Formats parameter count the way model sizes are usually named, like `7.6B`.
+pub fn format_parameter_count(count: u64) -> String {
+    const UNITS: [(f64, &str); 3] = [(1e12, "T"), (1e9, "B"), (1e6, "M")];
+
+    let count = count as f64;
+    for (scale, unit) in UNITS {
+        if count >= scale {
+            return format!("{:.1}{}", count / scale, unit);
+        }
+    }
+
+    format!("{}", count)
+}
+
+/// Returns all the model files in the directory and its subdirectories, skipping hidden ones.
+fn find_model_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let is_hidden = |path: &Path| {
+        path.strip_prefix(dir)
+            .unwrap_or(path)
+            .components()
+            .any(|component| component.as_os_str().to_string_lossy().starts_with('.'))
+    };
+
+    Ok(find_files(dir)?
+        .into_iter()
+        .filter(|path| {
+            !is_hidden(path)
+                && path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|extension| extension.eq_ignore_ascii_case(MODEL_EXTENSION))
+        })
+        .collect())
+}
+
+/// Returns the paths of all the files in the directory and its subdirectories,
+/// without following symlinks to directories. Directory that does not exist has no files.
+pub fn find_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if !dir.is_dir() {
+        return Ok(files);
+    }
+
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            files.extend(find_files(&entry.path())?);
+        } else {
+            files.push(entry.path());
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::gguf::test_utils::{Value, write_gguf};
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_model(path: &Path, architecture: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        write_gguf(
+            path,
+            &[
+                ("general.architecture", Value::String(architecture)),
+                ("general.file_type", Value::U32(7)),
+            ],
+            &[("token_embd.weight", &[16, 16])],
+        );
+    }
+
+    #[test]
+    fn test_incremental_update() {
+        let temp_dir = TempDir::new().unwrap();
+        let models_dir = temp_dir.path();
+        write_model(&models_dir.join("llama-q8_0.gguf"), "llama");
+        write_model(&models_dir.join("qwen").join("qwen3-q8_0.gguf"), "qwen3");
+        write_model(&models_dir.join(".hidden").join("skipped.gguf"), "qwen3");
+        std::fs::write(models_dir.join("broken.gguf"), "not a model").unwrap();
+        std::fs::write(models_dir.join("notes.txt"), "not a model").unwrap();
+
+        let index = ModelIndex::refresh(models_dir).unwrap();
+        let names: Vec<String> = index.models.iter().map(ModelEntry::name).collect();
+        assert_eq!(names, vec!["llama-q8_0", "qwen/qwen3-q8_0"]);
+        assert_eq!(index.invalid.len(), 1);
+        assert_eq!(index.invalid[0].path, Path::new("broken.gguf"));
+
+        let model = index.find("qwen/qwen3-q8_0").unwrap();
+        assert_eq!(model.architecture.as_deref(), Some("qwen3"));
+        assert_eq!(model.quant_type.as_deref(), Some("Q8_0"));
+        assert_eq!(model.parameter_count, 256);
+        assert_eq!(model.tensor_count, 1);
+        assert_eq!(index.find("llama-q8_0.gguf"), index.models.first());
+
+        // Index is persistent
+        assert_eq!(ModelIndex::load(models_dir).unwrap(), index);
+
+        let mut index = ModelIndex::load(models_dir).unwrap();
+        assert!(index.update(models_dir).unwrap().is_empty());
+
+        std::fs::remove_file(models_dir.join("llama-q8_0.gguf")).unwrap();
+        write_model(&models_dir.join("mistral-q8_0.gguf"), "mistral");
+        write_gguf(
+            &models_dir.join("qwen").join("qwen3-q8_0.gguf"),
+            &[("general.architecture", Value::String("qwen3moe"))],
+            &[],
+        );
+
+        assert_eq!(
+            index.update(models_dir).unwrap(),
+            IndexUpdate {
+                added: 1,
+                updated: 1,
+                removed: 1,
+                invalid: 0
+            }
+        );
+
+        // Invalid file is parsed again once it changes
+        std::fs::write(models_dir.join("broken.gguf"), "still not a model").unwrap();
+        assert_eq!(index.update(models_dir).unwrap().invalid, 1);
+        write_model(&models_dir.join("broken.gguf"), "llama");
+        assert_eq!(index.update(models_dir).unwrap().added, 1);
+        assert!(index.invalid.is_empty());
+        let model = index.find("qwen/qwen3-q8_0").unwrap();
+        assert_eq!(model.architecture.as_deref(), Some("qwen3moe"));
+        assert_eq!(model.quant_type, None);
+    }
+
+    fn write_named_model(models_dir: &Path, file_name: &str, size: &str, file_type: u32) {
+        write_gguf(
+            &models_dir.join(file_name),
+            &[
+                ("general.architecture", Value::String("qwen3")),
+                ("general.basename", Value::String("Qwen3")),
+                ("general.size_label", Value::String(size)),
+                ("general.file_type", Value::U32(file_type)),
+            ],
+            &[],
+        );
+    }
+
+    #[test]
+    fn test_resolve_references() {
+        let temp_dir = TempDir::new().unwrap();
+        let models_dir = temp_dir.path();
+        write_named_model(models_dir, "qwen3-8b-q4.gguf", "8B", 15);
+        write_named_model(models_dir, "qwen3-8b-q8.gguf", "8B", 7);
+        write_named_model(models_dir, "qwen3-4b.gguf", "4B", 15);
+        write_gguf(
+            &models_dir.join("custom.gguf"),
+            &[("general.architecture", Value::String("llama"))],
+            &[("token_embd.weight", &[1000, 1000])],
+        );
+
+        let index = ModelIndex::refresh(models_dir).unwrap();
+        let aliases = BTreeMap::from([("coder".to_string(), "qwen3:4b".to_string())]);
+        let resolve = |reference: &str| {
+            index
+                .resolve(reference, &aliases)
+                .map(|model| model.name())
+                .map_err(|e| e.message)
+        };
+
+        assert_eq!(resolve("qwen3:8b-q4_k_m"), Ok("qwen3-8b-q4".to_string()));
+        assert_eq!(resolve("Qwen3:8B-Q8_0"), Ok("qwen3-8b-q8".to_string()));
+        assert_eq!(resolve("qwen3:4b"), Ok("qwen3-4b".to_string()));
+        assert_eq!(resolve("coder"), Ok("qwen3-4b".to_string()));
+        assert_eq!(resolve("qwen3-8b-q4"), Ok("qwen3-8b-q4".to_string()));
+        assert_eq!(resolve("llama:latest"), Ok("custom".to_string()));
+        assert_eq!(
+            index.find("custom").unwrap().reference().as_deref(),
+            Some("llama:1.0m")
+        );
+
+        let error = resolve("qwen3:8b").unwrap_err();
+        assert!(error.contains("ambiguous"), "{}", error);
+        assert!(
+            error.contains("qwen3:8b-q4_k_m (qwen3-8b-q4.gguf)"),
+            "{}",
+            error
+        );
+        assert!(resolve("qwen3").unwrap_err().contains("ambiguous"));
+
+        let error = resolve("qwen3:14b").unwrap_err();
+        assert!(error.contains("4b-q4_k_m, 8b-q4_k_m, 8b-q8_0"), "{}", error);
+        assert!(resolve("mistral").is_err());
+    }
+
+    #[test]
+    fn test_is_up_to_date() {
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("input.gguf");
+        let imatrix = temp_dir.path().join("input.imatrix");
+        let output = temp_dir.path().join("output.gguf");
+        std::fs::write(&input, "input").unwrap();
+        std::fs::write(&imatrix, "imatrix").unwrap();
+        assert!(!is_up_to_date(&output, &[&input]));
+
+        std::fs::write(&output, "output").unwrap();
+        assert!(is_up_to_date(&output, &[&input, &imatrix]));
+
+        let file = std::fs::File::options().write(true).open(&imatrix).unwrap();
+        file.set_modified(SystemTime::now() + std::time::Duration::from_secs(60))
+            .unwrap();
+        assert!(is_up_to_date(&output, &[&input]));
+        assert!(!is_up_to_date(&output, &[&input, &imatrix]));
+    }
+
+    #[test]
+    fn test_format_parameter_count() {
+        assert_eq!(format_parameter_count(7_615_616_512), "7.6B");
+        assert_eq!(format_parameter_count(135_000_000), "135.0M");
+        assert_eq!(format_parameter_count(1_000), "1000");
+    }
+}
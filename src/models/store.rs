@@ -0,0 +1,467 @@
+use std::collections::BTreeSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::{Result, RuntimeError};
+use crate::models::find_files;
+
+const BLOBS_DIR_NAME: &str = "blobs";
+const MANIFESTS_DIR_NAME: &str = "manifests";
+const MANIFEST_EXTENSION: &str = "toml";
+const BLOB_PREFIX: &str = "sha256-";
+const HASH_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Manifest of a model stored in the blob store, kept in `manifests/<name>.toml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// SHA-256 of the model file, as lowercase hex string.
+    pub digest: String,
+    pub size: u64,
+    pub created: DateTime<Utc>,
+}
+
+/// Result of adding a model to the store.
+#[derive(Debug, PartialEq)]
+pub struct StoredModel {
+    pub digest: String,
+    /// `true` if the same model has already been stored under a different name.
+    pub deduplicated: bool,
+}
+
+/// Result of garbage collection.
+#[derive(Debug, Default, PartialEq)]
+pub struct CollectedGarbage {
+    /// Names of the models whose files no longer exist.
+    pub manifests: Vec<String>,
+    /// Digests and sizes of the blobs not referenced by any manifest.
+    pub blobs: Vec<(String, u64)>,
+}
+
+/// Problem found while verifying the store.
+#[derive(Debug, PartialEq)]
+pub enum StoreProblem {
+    /// Content of the blob does not match its digest.
+    CorruptedBlob { digest: String, actual: String },
+    /// Manifest references a blob that does not exist.
+    MissingBlob { name: String, digest: String },
+}
+
+impl std::fmt::Display for StoreProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StoreProblem::CorruptedBlob { digest, actual } => write!(
+                f,
+                "blob {} is corrupted, its content has digest {}",
+                digest, actual
+            ),
+            StoreProblem::MissingBlob { name, digest } => {
+                write!(f, "model {} references missing blob {}", name, digest)
+            }
+        }
+    }
+}
+
+/// Content-addressed store of the model files, kept in `blobs` and `manifests` directories
+/// inside `paths.models_dir`. Every model is stored once as `blobs/sha256-<digest>`, and linked
+/// to its human-readable path (`<name>.gguf`) with a reflink, or a hardlink if the file system
+/// does not support reflinks.
+pub struct BlobStore {
+    models_dir: PathBuf,
+}
+
+impl BlobStore {
+    pub fn new(models_dir: impl AsRef<Path>) -> Self {
+        Self {
+            models_dir: models_dir.as_ref().to_path_buf(),
+        }
+    }
+
+    fn blobs_dir(&self) -> PathBuf {
+        self.models_dir.join(BLOBS_DIR_NAME)
+    }
+
+    fn manifests_dir(&self) -> PathBuf {
+        self.models_dir.join(MANIFESTS_DIR_NAME)
+    }
+
+    pub fn blob_path(&self, digest: &str) -> PathBuf {
+        self.blobs_dir().join(format!("{}{}", BLOB_PREFIX, digest))
+    }
+
+    fn manifest_path(&self, name: &str) -> PathBuf {
+        self.manifests_dir()
+            .join(format!("{}.{}", name, MANIFEST_EXTENSION))
+    }
+
+    /// Human-readable path of the model with given name.
+    fn model_path(&self, name: &str) -> PathBuf {
+        self.models_dir.join(format!("{}.gguf", name))
+    }
+
+    pub fn load_manifest(&self, name: &str) -> Result<Option<Manifest>> {
+        let path = self.manifest_path(name);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+        toml::from_str(&content).map(Some).map_err(|e| {
+            RuntimeError::new(
+                format!("Model manifest {} is corrupted: {}", path.display(), e),
+                exitcode::DATAERR as u8,
+            )
+        })
+    }
+
+    fn save_manifest(&self, name: &str, manifest: &Manifest) -> Result<()> {
+        let content = toml::to_string(manifest).map_err(|e| {
+            RuntimeError::new(
+                format!("Failed to serialize model manifest: {}", e),
+                exitcode::SOFTWARE as u8,
+            )
+        })?;
+
+        let path = self.manifest_path(name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Returns the names and manifests of all the stored models, sorted by name.
+    pub fn manifests(&self) -> Result<Vec<(String, Manifest)>> {
+        let manifests_dir = self.manifests_dir();
+        let mut manifests = Vec::new();
+
+        for path in find_files(&manifests_dir)? {
+            let Some(name) = path
+                .strip_prefix(&manifests_dir)
+                .ok()
+                .filter(|relative| {
+                    relative
+                        .extension()
+                        .is_some_and(|extension| extension == MANIFEST_EXTENSION)
+                })
+                .map(|relative| relative.with_extension("").to_string_lossy().to_string())
+            else {
+                continue;
+            };
+
+            if let Some(manifest) = self.load_manifest(&name)? {
+                manifests.push((name, manifest));
+            }
+        }
+
+        manifests.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(manifests)
+    }
+
+    /// Moves the model file with given name into the store, and replaces it with a link to the blob.
+    /// If the blob with the same content already exists, the file is removed and linked to that blob.
+    pub fn add(&self, name: &str) -> Result<StoredModel> {
+        let path = self.model_path(name);
+        let digest = sha256_file(&path)?;
+        let size = std::fs::metadata(&path)?.len();
+        let blob_path = self.blob_path(&digest);
+
+        if let Some(manifest) = self.load_manifest(name)?
+            && manifest.digest == digest
+            && blob_path.exists()
+        {
+            log::debug!("Model {} is already stored", name);
+            return Ok(StoredModel {
+                digest,
+                deduplicated: false,
+            });
+        }
+
+        let deduplicated = blob_path.exists();
+        if !deduplicated {
+            std::fs::create_dir_all(self.blobs_dir())?;
+            std::fs::rename(&path, &blob_path)?;
+        }
+
+        // Link is created next to the model and renamed over it, so the model never disappears
+        let temporary_path = path.with_extension("gguf.tmp");
+        if let Err(e) = link_file(&blob_path, &temporary_path) {
+            if !deduplicated {
+                std::fs::rename(&blob_path, &path)?;
+            }
+            return Err(RuntimeError::new(
+                format!("Failed to link {}: {}", path.display(), e),
+                exitcode::IOERR as u8,
+            ));
+        }
+        std::fs::rename(&temporary_path, &path)?;
+
+        self.save_manifest(
+            name,
+            &Manifest {
+                digest: digest.clone(),
+                size,
+                created: Utc::now(),
+            },
+        )?;
+
+        Ok(StoredModel {
+            digest,
+            deduplicated,
+        })
+    }
+
+    /// Removes the manifests of models that no longer exist, and the blobs not referenced
+    /// by any manifest. With `dry_run`, nothing is removed.
+    pub fn collect_garbage(&self, dry_run: bool) -> Result<CollectedGarbage> {
+        let mut garbage = CollectedGarbage::default();
+        let mut referenced = BTreeSet::new();
+
+        for (name, manifest) in self.manifests()? {
+            if self.model_path(&name).exists() {
+                referenced.insert(manifest.digest);
+                continue;
+            }
+
+            if !dry_run {
+                std::fs::remove_file(self.manifest_path(&name))?;
+            }
+            garbage.manifests.push(name);
+        }
+
+        for path in find_files(&self.blobs_dir())? {
+            let digest = blob_digest(&path);
+            if digest
+                .as_ref()
+                .is_some_and(|digest| referenced.contains(digest))
+            {
+                continue;
+            }
+
+            let size = std::fs::metadata(&path)?.len();
+            if !dry_run {
+                std::fs::remove_file(&path)?;
+            }
+            garbage
+                .blobs
+                .push((digest.unwrap_or_else(|| path.display().to_string()), size));
+        }
+
+        Ok(garbage)
+    }
+
+    /// Hashes all the blobs again, and checks whether all the manifests reference existing blobs.
+    pub fn verify(&self) -> Result<Vec<StoreProblem>> {
+        let mut problems = Vec::new();
+
+        for path in find_files(&self.blobs_dir())? {
+            let Some(digest) = blob_digest(&path) else {
+                continue;
+            };
+
+            log::info!("Verifying blob {}", digest);
+            let actual = sha256_file(&path)?;
+            if actual != digest {
+                problems.push(StoreProblem::CorruptedBlob { digest, actual });
+            }
+        }
+
+        for (name, manifest) in self.manifests()? {
+            if !self.blob_path(&manifest.digest).exists() {
+                problems.push(StoreProblem::MissingBlob {
+                    name,
+                    digest: manifest.digest,
+                });
+            }
+        }
+
+        Ok(problems)
+    }
+}
+
+/// Calculates SHA-256 of the file, returning it as lowercase hex string.
+pub fn sha256_file(path: impl AsRef<Path>) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; HASH_BUFFER_SIZE];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hex_digest(hasher))
+}
+
+/// Finishes the hash, returning it as lowercase hex string.
+pub fn hex_digest(hasher: Sha256) -> String {
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Returns the digest of the blob, based on its file name.
+fn blob_digest(path: &Path) -> Option<String> {
+    path.file_name()?
+        .to_str()?
+        .strip_prefix(BLOB_PREFIX)
+        .map(str::to_string)
+}
+
+/// Creates a reflink of the file if the file system supports it, or a hardlink otherwise.
+fn link_file(source: &Path, destination: &Path) -> std::io::Result<()> {
+    if destination.symlink_metadata().is_ok() {
+        std::fs::remove_file(destination)?;
+    }
+
+    match reflink(source, destination) {
+        Ok(()) => return Ok(()),
+        Err(e) => {
+            log::debug!("Reflink is not available ({}), using hardlink", e);
+            if destination.exists() {
+                std::fs::remove_file(destination)?;
+            }
+        }
+    }
+
+    std::fs::hard_link(source, destination)
+}
+
+#[cfg(target_os = "linux")]
+fn reflink(source: &Path, destination: &Path) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let source = std::fs::File::open(source)?;
+    let destination = std::fs::File::create_new(destination)?;
+
+    // SAFETY: both descriptors are valid for the duration of the call
+    let result = unsafe {
+        libc::ioctl(
+            destination.as_raw_fd(),
+            libc::FICLONE as _,
+            source.as_raw_fd(),
+        )
+    };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink(_source: &Path, _destination: &Path) -> std::io::Result<()> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sha256() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("file");
+        std::fs::write(&path, "abc").unwrap();
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_store_deduplication_and_gc() {
+        let temp_dir = TempDir::new().unwrap();
+        let models_dir = temp_dir.path();
+        std::fs::create_dir(models_dir.join("copies")).unwrap();
+        std::fs::write(models_dir.join("model.gguf"), "model").unwrap();
+        std::fs::write(models_dir.join("copies").join("model.gguf"), "model").unwrap();
+        std::fs::write(models_dir.join("other.gguf"), "other").unwrap();
+
+        let store = BlobStore::new(models_dir);
+        let first = store.add("model").unwrap();
+        assert!(!first.deduplicated);
+        let second = store.add("copies/model").unwrap();
+        assert!(second.deduplicated);
+        assert_eq!(first.digest, second.digest);
+        store.add("other").unwrap();
+        // Adding the model again does nothing
+        assert!(!store.add("model").unwrap().deduplicated);
+
+        assert_eq!(
+            std::fs::read_to_string(models_dir.join("copies").join("model.gguf")).unwrap(),
+            "model"
+        );
+        assert_eq!(
+            std::fs::read_dir(models_dir.join(BLOBS_DIR_NAME))
+                .unwrap()
+                .count(),
+            2
+        );
+        let names: Vec<String> = store
+            .manifests()
+            .unwrap()
+            .into_iter()
+            .map(|m| m.0)
+            .collect();
+        assert_eq!(names, vec!["copies/model", "model", "other"]);
+
+        // Blob is still referenced by the copy
+        std::fs::remove_file(models_dir.join("model.gguf")).unwrap();
+        std::fs::remove_file(models_dir.join("other.gguf")).unwrap();
+        let garbage = store.collect_garbage(true).unwrap();
+        assert_eq!(garbage.manifests, vec!["model", "other"]);
+        assert_eq!(garbage.blobs.len(), 1);
+        assert!(store.manifest_path("other").exists());
+
+        let garbage = store.collect_garbage(false).unwrap();
+        assert_eq!(garbage.blobs, vec![(sha256_of("other"), 5)]);
+        assert!(!store.manifest_path("other").exists());
+        assert!(store.blob_path(&first.digest).exists());
+        assert!(!store.blob_path(&sha256_of("other")).exists());
+    }
+
+    #[test]
+    fn test_verify() {
+        let temp_dir = TempDir::new().unwrap();
+        let models_dir = temp_dir.path();
+        std::fs::write(models_dir.join("model.gguf"), "model").unwrap();
+        std::fs::write(models_dir.join("other.gguf"), "other").unwrap();
+
+        let store = BlobStore::new(models_dir);
+        let model = store.add("model").unwrap();
+        let other = store.add("other").unwrap();
+        assert!(store.verify().unwrap().is_empty());
+
+        std::fs::remove_file(models_dir.join("model.gguf")).unwrap();
+        std::fs::write(store.blob_path(&model.digest), "corrupted").unwrap();
+        std::fs::remove_file(store.blob_path(&other.digest)).unwrap();
+
+        assert_eq!(
+            store.verify().unwrap(),
+            vec![
+                StoreProblem::CorruptedBlob {
+                    digest: model.digest,
+                    actual: sha256_of("corrupted"),
+                },
+                StoreProblem::MissingBlob {
+                    name: "other".to_string(),
+                    digest: other.digest,
+                },
+            ]
+        );
+    }
+
+    fn sha256_of(content: &str) -> String {
+        hex_digest(Sha256::new_with_prefix(content.as_bytes()))
+    }
+}
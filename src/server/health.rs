@@ -0,0 +1,271 @@
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::server::process;
+
+/// Endpoint of `llama-server` reporting whether the model is loaded.
+const HEALTH_ENDPOINT: &str = "/health";
+/// Timeout of connecting to the server and of reading its response.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Responses are tiny, anything longer is cut.
+const MAX_RESPONSE_SIZE: u64 = 64 * 1024;
+/// Interval of health checks while the model is loading.
+pub const LOADING_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+/// Interval of health checks of a ready server.
+pub const READY_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Health of a running server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthState {
+    /// Server is not listening yet, or is still loading the model
+    Loading,
+    /// Model is loaded and the server accepts requests
+    Ready,
+    /// Server responds with an error
+    Error,
+    /// Server process is gone
+    Exited,
+}
+
+impl std::fmt::Display for HealthState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            HealthState::Loading => "loading",
+            HealthState::Ready => "ready",
+            HealthState::Error => "error",
+            HealthState::Exited => "exited",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Result of a single health check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthReport {
+    pub state: HealthState,
+    /// Error reported by the server, or the reason the check failed
+    pub message: Option<String>,
+}
+
+impl HealthReport {
+    fn new(state: HealthState, message: Option<String>) -> Self {
+        Self { state, message }
+    }
+}
+
+/// Checks the health of `llama-server` process with its `/health` endpoint.
+pub struct HealthMonitor {
+    address: String,
+    port: u16,
+    pid: Option<u32>,
+}
+
+impl HealthMonitor {
+    /// Creates the monitor of the server listening on given address and port.
+    /// With PID, the server is reported as exited once the process is gone.
+    pub fn new(address: &str, port: u16, pid: Option<u32>) -> Self {
+        Self {
+            address: address.to_string(),
+            port,
+            pid,
+        }
+    }
+
+    pub fn check(&self) -> HealthReport {
+        if let Some(pid) = self.pid
+            && !process::is_alive(pid)
+        {
+            return HealthReport::new(HealthState::Exited, None);
+        }
+
+        let Some(address) = self.socket_address() else {
+            return HealthReport::new(
+                HealthState::Error,
+                Some(format!("Cannot resolve address {}", self.address)),
+            );
+        };
+
+        match request_health(address) {
+            Ok((status, body)) => match status {
+                200 => HealthReport::new(HealthState::Ready, None),
+                // Returned while the model is loading
+                503 => HealthReport::new(HealthState::Loading, error_message(&body)),
+                status => HealthReport::new(
+                    HealthState::Error,
+                    Some(format!(
+                        "HTTP {}: {}",
+                        status,
+                        error_message(&body).unwrap_or_default()
+                    )),
+                ),
+            },
+            // Server does not listen until it's initialized
+            Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+                HealthReport::new(HealthState::Loading, None)
+            }
+            Err(e) => HealthReport::new(HealthState::Error, Some(e.to_string())),
+        }
+    }
+
+    /// Checks the health until the server is ready, exits, or the timeout passes.
+    /// Returns the last report.
+    pub fn wait_until_ready(&self, timeout: Duration) -> HealthReport {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let report = self.check();
+            if matches!(report.state, HealthState::Ready | HealthState::Exited)
+                || Instant::now() >= deadline
+            {
+                return report;
+            }
+            std::thread::sleep(
+                LOADING_CHECK_INTERVAL.min(deadline.saturating_duration_since(Instant::now())),
+            );
+        }
+    }
+
+    /// Address to connect to. Server listening on all interfaces is reached through loopback.
+    fn socket_address(&self) -> Option<SocketAddr> {
+        let mut address = (self.address.as_str(), self.port)
+            .to_socket_addrs()
+            .ok()?
+            .next()?;
+        if address.ip().is_unspecified() {
+            address.set_ip(match address.ip() {
+                IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            });
+        }
+        Some(address)
+    }
+}
+
+/// Sends `GET /health` request, returns the status code and body of the response.
+fn request_health(address: SocketAddr) -> std::io::Result<(u16, String)> {
+    let mut stream = TcpStream::connect_timeout(&address, REQUEST_TIMEOUT)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        HEALTH_ENDPOINT, address
+    );
+    stream.write_all(request.as_bytes())?;
+
+    let mut response = Vec::new();
+    stream.take(MAX_RESPONSE_SIZE).read_to_end(&mut response)?;
+    parse_response(&String::from_utf8_lossy(&response)).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Invalid HTTP response to health check",
+        )
+    })
+}
+
+/// Parses the status code and body of HTTP response, like `HTTP/1.1 200 OK\r\n...\r\n\r\n{"status":"ok"}`.
+fn parse_response(response: &str) -> Option<(u16, String)> {
+    let status_line = response.lines().next()?;
+    let mut parts = status_line.split_whitespace();
+    if !parts.next()?.starts_with("HTTP/") {
+        return None;
+    }
+    let status = parts.next()?.parse().ok()?;
+    let body = response
+        .split_once("\r\n\r\n")
+        .map(|(_, body)| body.trim().to_string())
+        .unwrap_or_default();
+    Some((status, body))
+}
+
+/// Extracts the message from the error response, like `{"error":{"code":503,"message":"Loading model"}}`.
+fn error_message(body: &str) -> Option<String> {
+    if body.is_empty() {
+        return None;
+    }
+
+    let message = serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|json| {
+            json.pointer("/error/message")
+                .and_then(|message| message.as_str())
+                .map(str::to_string)
+        });
+    Some(message.unwrap_or_else(|| body.chars().take(200).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::test_support::{READY_RESPONSE, fake_server};
+    use std::net::TcpListener;
+
+    const LOADING_RESPONSE: &str = "HTTP/1.1 503 Service Unavailable\r\nContent-Type: application/json\r\n\r\n{\"error\":{\"code\":503,\"message\":\"Loading model\",\"type\":\"unavailable_error\"}}";
+    const ERROR_RESPONSE: &str = "HTTP/1.1 500 Internal Server Error\r\n\r\nout of memory";
+
+    #[test]
+    fn test_check() {
+        let (port, server) = fake_server(&[LOADING_RESPONSE, READY_RESPONSE, ERROR_RESPONSE]);
+        let monitor = HealthMonitor::new("127.0.0.1", port, None);
+
+        assert_eq!(
+            monitor.check(),
+            HealthReport::new(HealthState::Loading, Some("Loading model".to_string()))
+        );
+        assert_eq!(monitor.check(), HealthReport::new(HealthState::Ready, None));
+        assert_eq!(
+            monitor.check(),
+            HealthReport::new(
+                HealthState::Error,
+                Some("HTTP 500: out of memory".to_string())
+            )
+        );
+        server.join().unwrap();
+
+        // Nothing listens on the port anymore
+        assert_eq!(monitor.check().state, HealthState::Loading);
+    }
+
+    #[test]
+    fn test_check_exited() {
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+
+        let monitor = HealthMonitor::new("127.0.0.1", 1, Some(pid));
+        assert_eq!(monitor.check().state, HealthState::Exited);
+    }
+
+    #[test]
+    fn test_wait_until_ready() {
+        let (port, server) = fake_server(&[LOADING_RESPONSE, LOADING_RESPONSE, READY_RESPONSE]);
+        let monitor = HealthMonitor::new("0.0.0.0", port, None);
+        assert_eq!(
+            monitor.wait_until_ready(Duration::from_secs(10)).state,
+            HealthState::Ready
+        );
+        server.join().unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+        let monitor = HealthMonitor::new("127.0.0.1", port, None);
+        let started = Instant::now();
+        assert_eq!(
+            monitor.wait_until_ready(Duration::from_millis(300)).state,
+            HealthState::Loading
+        );
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_parse_response() {
+        assert_eq!(
+            parse_response(READY_RESPONSE),
+            Some((200, "{\"status\":\"ok\"}".to_string()))
+        );
+        assert_eq!(parse_response("garbage"), None);
+    }
+}
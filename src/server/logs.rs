@@ -0,0 +1,179 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Size of the log file after which it is rotated.
+pub const DEFAULT_MAX_LOG_SIZE: u64 = 10 * 1024 * 1024;
+/// Amount of rotated log files kept next to the current one (`server.log.1`, `server.log.2`, ...).
+pub const DEFAULT_KEPT_LOGS: usize = 3;
+
+/// Interval of polling the log file for new content when following it.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Log file rotated when it grows over the size limit.
+/// Rotation happens only between writes, so a single write is never split between files.
+pub struct RotatingLog {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    kept: usize,
+}
+
+impl RotatingLog {
+    /// Opens the log for appending, creating it if needed.
+    pub fn open(path: impl Into<PathBuf>, max_size: u64, kept: usize) -> std::io::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            size,
+            max_size,
+            kept,
+        })
+    }
+
+    /// Path of the rotated log with given number, like `server.log.1`.
+    pub fn rotated_path(path: &Path, number: usize) -> PathBuf {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(format!(".{}", number));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        if self.kept == 0 {
+            self.file.set_len(0)?;
+        } else {
+            let _ = std::fs::remove_file(Self::rotated_path(&self.path, self.kept));
+            for number in (1..self.kept).rev() {
+                let from = Self::rotated_path(&self.path, number);
+                if from.exists() {
+                    std::fs::rename(&from, Self::rotated_path(&self.path, number + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, Self::rotated_path(&self.path, 1))?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingLog {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(buf)?;
+        self.size += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Returns last `count` lines of the log, reaching for the rotated files if the current one is too short.
+pub fn tail(path: &Path, count: usize) -> std::io::Result<Vec<String>> {
+    let mut lines = Vec::new();
+    let mut number = 0;
+    loop {
+        let file_path = match number {
+            0 => path.to_path_buf(),
+            number => RotatingLog::rotated_path(path, number),
+        };
+        if !file_path.exists() {
+            break;
+        }
+
+        let content = std::fs::read(&file_path)?;
+        let mut file_lines: Vec<String> = String::from_utf8_lossy(&content)
+            .lines()
+            .map(str::to_string)
+            .collect();
+        file_lines.append(&mut lines);
+        lines = file_lines;
+        if lines.len() >= count {
+            break;
+        }
+        number += 1;
+    }
+
+    let start = lines.len().saturating_sub(count);
+    Ok(lines.split_off(start))
+}
+
+/// Prints the new content of the log as it's written, until `keep_following` returns `false`.
+/// Rotation of the log is detected by the file getting shorter, then it's read from the beginning.
+pub fn follow(
+    path: &Path,
+    output: &mut impl Write,
+    keep_following: impl Fn() -> bool,
+) -> std::io::Result<()> {
+    let mut position = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    loop {
+        // Condition is checked before reading, so the output written right before the end is not lost
+        let following = keep_following();
+
+        let length = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        if length < position {
+            position = 0;
+        }
+        if length > position {
+            let mut file = File::open(path)?;
+            file.seek(SeekFrom::Start(position))?;
+            let mut buffer = Vec::new();
+            file.take(length - position).read_to_end(&mut buffer)?;
+            output.write_all(&buffer)?;
+            output.flush()?;
+            position = length;
+        }
+
+        if !following {
+            return Ok(());
+        }
+        std::thread::sleep(FOLLOW_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rotation() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("server.log");
+
+        let mut log = RotatingLog::open(&path, 12, 2).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            log.write_all(line.as_bytes()).unwrap();
+        }
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fourth\n");
+        assert_eq!(
+            std::fs::read_to_string(RotatingLog::rotated_path(&path, 1)).unwrap(),
+            "third\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(RotatingLog::rotated_path(&path, 2)).unwrap(),
+            "second\n"
+        );
+        assert!(!RotatingLog::rotated_path(&path, 3).exists());
+
+        assert_eq!(tail(&path, 2).unwrap(), vec!["third", "fourth"]);
+        assert_eq!(tail(&path, 10).unwrap(), vec!["second", "third", "fourth"]);
+    }
+}
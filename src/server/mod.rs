@@ -0,0 +1,15 @@
+//! Running and supervising `llama-server` processes.
+//!
+//! Detached servers are run by a supervisor - a background `llama-mgr` process that owns
+//! the server process, writes its output to a rotated log and keeps its state up to date.
+//! State and logs live in `paths.runtime_dir`.
+
+pub mod health;
+pub mod logs;
+pub mod ports;
+pub mod process;
+pub mod restart;
+pub mod state;
+pub mod supervisor;
+#[cfg(test)]
+mod test_support;
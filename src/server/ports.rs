@@ -0,0 +1,262 @@
+use std::net::TcpListener;
+use std::str::FromStr;
+
+use crate::config::PortRange;
+use crate::error::{Result, RuntimeError};
+use crate::server::state::ServerRegistry;
+
+/// Port requested for the server - a fixed one, or any free one from the configured range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortSelection {
+    Auto,
+    Fixed(u16),
+}
+
+impl FromStr for PortSelection {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        if value.eq_ignore_ascii_case("auto") {
+            return Ok(PortSelection::Auto);
+        }
+        value
+            .parse()
+            .map(PortSelection::Fixed)
+            .map_err(|_| format!("expected port number or `auto`, got `{}`", value))
+    }
+}
+
+impl std::fmt::Display for PortSelection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PortSelection::Auto => f.write_str("auto"),
+            PortSelection::Fixed(port) => write!(f, "{}", port),
+        }
+    }
+}
+
+/// Process the port is taken by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortOwner {
+    /// Server managed by llama-mgr, with given ID
+    Server(String),
+    /// Other process, with its PID and name if they could be found
+    Process(Option<(u32, String)>),
+}
+
+impl std::fmt::Display for PortOwner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PortOwner::Server(id) => write!(f, "server '{}'", id),
+            PortOwner::Process(Some((pid, name))) => write!(f, "process {} (PID {})", name, pid),
+            PortOwner::Process(None) => f.write_str("another process"),
+        }
+    }
+}
+
+/// Returns the owner of the port on given address, or `None` if the port is free.
+/// Servers are looked up by their state, so the ports of servers that are still loading are taken too.
+pub fn port_owner(
+    registry: &ServerRegistry,
+    address: &str,
+    port: u16,
+    ignored_id: Option<&str>,
+) -> Result<Option<PortOwner>> {
+    let server = registry.list()?.into_iter().find(|state| {
+        state.port == port
+            && state.status.is_active()
+            && !state.is_stale()
+            && Some(state.id.as_str()) != ignored_id
+    });
+    if let Some(server) = server {
+        return Ok(Some(PortOwner::Server(server.id)));
+    }
+
+    if is_free(address, port) {
+        return Ok(None);
+    }
+    Ok(Some(PortOwner::Process(listening_process(port))))
+}
+
+/// Returns the port for the server: checks that the fixed one is free,
+/// or picks the first free one from the range.
+pub fn allocate(
+    registry: &ServerRegistry,
+    address: &str,
+    selection: PortSelection,
+    range: PortRange,
+) -> Result<u16> {
+    match selection {
+        PortSelection::Fixed(port) => match port_owner(registry, address, port, None)? {
+            Some(owner) => Err(RuntimeError::new(
+                format!("Port {} is already used by {}", port, owner),
+                exitcode::UNAVAILABLE as u8,
+            )),
+            None => Ok(port),
+        },
+        PortSelection::Auto => {
+            for port in range.ports() {
+                if port_owner(registry, address, port, None)?.is_none() {
+                    return Ok(port);
+                }
+            }
+            Err(RuntimeError::new(
+                format!(
+                    "No free port in range {}, change `server.port_range` in the configuration",
+                    String::from(range)
+                ),
+                exitcode::UNAVAILABLE as u8,
+            ))
+        }
+    }
+}
+
+/// Returns `true` if the port can be bound on given address.
+fn is_free(address: &str, port: u16) -> bool {
+    TcpListener::bind((address, port)).is_ok()
+}
+
+/// Finds the process listening on given TCP port, by matching the socket
+/// from `/proc/net/tcp` with the file descriptors of the processes.
+/// Only the processes of the current user can be inspected.
+#[cfg(target_os = "linux")]
+fn listening_process(port: u16) -> Option<(u32, String)> {
+    let inodes: Vec<String> = ["/proc/net/tcp", "/proc/net/tcp6"]
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .flat_map(|table| listening_inodes(&table, port))
+        .collect();
+    if inodes.is_empty() {
+        return None;
+    }
+
+    let sockets: Vec<String> = inodes
+        .iter()
+        .map(|inode| format!("socket:[{}]", inode))
+        .collect();
+    for entry in std::fs::read_dir("/proc").ok()?.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|pid| pid.parse().ok()) else {
+            continue;
+        };
+        let Ok(descriptors) = std::fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+
+        let owns_socket = descriptors.flatten().any(|descriptor| {
+            std::fs::read_link(descriptor.path()).is_ok_and(|target| {
+                sockets
+                    .iter()
+                    .any(|socket| target.as_os_str() == socket.as_str())
+            })
+        });
+        if owns_socket {
+            let name = std::fs::read_to_string(entry.path().join("comm"))
+                .map(|name| name.trim().to_string())
+                .unwrap_or_else(|_| "unknown".to_string());
+            return Some((pid, name));
+        }
+    }
+    None
+}
+
+#[cfg(not(target_os = "linux"))]
+fn listening_process(_port: u16) -> Option<(u32, String)> {
+    None
+}
+
+/// Returns the inodes of the sockets listening on given port, from `/proc/net/tcp` table.
+/// Lines look like `0: 0100007F:1F90 00000000:0000 0A ... 0 12345 ...`,
+/// where `0A` is the listening state and the inode is the 10th column.
+#[cfg(any(target_os = "linux", test))]
+fn listening_inodes(table: &str, port: u16) -> Vec<String> {
+    const LISTEN_STATE: &str = "0A";
+
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            let (_, local_port) = columns.get(1)?.rsplit_once(':')?;
+            let local_port = u16::from_str_radix(local_port, 16).ok()?;
+            (local_port == port && *columns.get(3)? == LISTEN_STATE)
+                .then(|| columns.get(9).map(|inode| inode.to_string()))
+                .flatten()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::state::ServerStatus;
+    use crate::server::test_support::state;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_port_selection() {
+        assert_eq!("auto".parse(), Ok(PortSelection::Auto));
+        assert_eq!("8081".parse(), Ok(PortSelection::Fixed(8081)));
+        assert!("80a".parse::<PortSelection>().is_err());
+    }
+
+    #[test]
+    fn test_listening_inodes() {
+        let table = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 41234 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:1F90 0100007F:C350 01 00000000:00000000 00:00000000 00000000  1000        0 41250 1 0000000000000000 20 4 30 10 -1
+   2: 00000000:0016 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1811 1 0000000000000000 100 0 0 10 0
+";
+        assert_eq!(listening_inodes(table, 8080), vec!["41234"]);
+        assert_eq!(listening_inodes(table, 22), vec!["1811"]);
+        assert!(listening_inodes(table, 8081).is_empty());
+    }
+
+    #[test]
+    fn test_allocate() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ServerRegistry::new(temp_dir.path());
+
+        // Listener on a port picked by the system stands in for a foreign process
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let taken = listener.local_addr().unwrap().port();
+        let range = PortRange {
+            start: taken,
+            end: taken.saturating_add(20),
+        };
+
+        let owner = port_owner(&registry, "127.0.0.1", taken, None).unwrap();
+        assert!(matches!(owner, Some(PortOwner::Process(_))));
+        #[cfg(target_os = "linux")]
+        assert_eq!(
+            owner,
+            Some(PortOwner::Process(Some((
+                std::process::id(),
+                std::fs::read_to_string("/proc/self/comm")
+                    .unwrap()
+                    .trim()
+                    .to_string()
+            ))))
+        );
+        assert!(allocate(&registry, "127.0.0.1", PortSelection::Fixed(taken), range).is_err());
+
+        let allocated = allocate(&registry, "127.0.0.1", PortSelection::Auto, range).unwrap();
+        assert_ne!(allocated, taken);
+
+        // Port recorded by an active server is taken, even if nothing listens on it yet
+        let mut server = state("qwen3", ServerStatus::Starting, std::process::id());
+        server.port = allocated;
+        registry.save(&server).unwrap();
+        assert_eq!(
+            port_owner(&registry, "127.0.0.1", allocated, None).unwrap(),
+            Some(PortOwner::Server("qwen3".to_string()))
+        );
+        assert_eq!(
+            port_owner(&registry, "127.0.0.1", allocated, Some("qwen3")).unwrap(),
+            None
+        );
+        assert_ne!(
+            allocate(&registry, "127.0.0.1", PortSelection::Auto, range).unwrap(),
+            allocated
+        );
+    }
+}
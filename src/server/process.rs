@@ -0,0 +1,293 @@
+use std::process::{Child, Command, ExitStatus};
+use std::time::{Duration, Instant};
+
+/// Interval of polling for process exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Moves the process started by the command to its own session, so it is not bound
+/// to the terminal and survives closing it. Signals from the terminal (like Ctrl-C) never reach it.
+#[cfg(unix)]
+pub fn detach(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+
+    // SAFETY: `setsid` is async-signal-safe
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setsid() == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(unix))]
+pub fn detach(_command: &mut Command) {}
+
+/// Moves the process started by the command to its own process group, so Ctrl-C reaches it
+/// only once - forwarded with [`forward_signals`]. Otherwise llama-server would get the signal
+/// twice and terminate immediately, skipping the cleanup.
+#[cfg(unix)]
+pub fn isolate_signals(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    command.process_group(0);
+}
+
+#[cfg(not(unix))]
+pub fn isolate_signals(_command: &mut Command) {}
+
+/// Returns `true` if the process with given PID exists.
+#[cfg(unix)]
+pub fn is_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+
+    // SAFETY: signal 0 only checks whether the process exists
+    let result = unsafe { libc::kill(pid, 0) };
+    if result != 0 {
+        // Process owned by other user still exists
+        return std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM);
+    }
+    !is_zombie(pid)
+}
+
+/// Returns `true` if the process has exited, but has not been reaped by its parent yet.
+#[cfg(target_os = "linux")]
+fn is_zombie(pid: libc::pid_t) -> bool {
+    stat_fields(pid as u32)
+        .and_then(|fields| fields.first().cloned())
+        .is_some_and(|state| state == "Z")
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn is_zombie(_pid: libc::pid_t) -> bool {
+    false
+}
+
+/// Returns the fields of `/proc/<pid>/stat` following the command name, starting with the state (field 3).
+#[cfg(target_os = "linux")]
+fn stat_fields(pid: u32) -> Option<Vec<String>> {
+    // Command name is in parentheses and may contain spaces itself
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let (_, rest) = stat.rsplit_once(')')?;
+    Some(rest.split_whitespace().map(str::to_string).collect())
+}
+
+/// Returns the start time of the process, in clock ticks since boot.
+/// Together with PID it identifies the process, as PIDs are reused.
+#[cfg(target_os = "linux")]
+pub fn start_time(pid: u32) -> Option<u64> {
+    // Start time is field 22
+    stat_fields(pid)?.get(22 - 3)?.parse().ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn start_time(_pid: u32) -> Option<u64> {
+    None
+}
+
+/// Returns `true` if the process with given PID is alive and is the one that had given start time,
+/// and not other process the PID has been reused for. Without start time, the PID is trusted only
+/// on platforms where it cannot be checked.
+pub fn is_same_process(pid: u32, recorded_start_time: Option<u64>) -> bool {
+    if !is_alive(pid) {
+        return false;
+    }
+    match (recorded_start_time, start_time(pid)) {
+        (Some(recorded), Some(current)) => recorded == current,
+        (None, Some(_)) => false,
+        (_, None) => true,
+    }
+}
+
+#[cfg(not(unix))]
+pub fn is_alive(_pid: u32) -> bool {
+    false
+}
+
+/// Asks the process to terminate with `SIGTERM`, and kills it if it's still alive after the timeout.
+/// Returns `true` if the process exited by itself.
+#[cfg(unix)]
+pub fn terminate(pid: u32, timeout: Duration) -> bool {
+    request_termination(pid);
+    if wait_for_exit(pid, timeout) {
+        return true;
+    }
+
+    send_signal(pid, libc::SIGKILL);
+    wait_for_exit(pid, timeout);
+    false
+}
+
+#[cfg(not(unix))]
+pub fn terminate(_pid: u32, _timeout: Duration) -> bool {
+    true
+}
+
+/// Asks the process to terminate with `SIGTERM`, without waiting for it.
+#[cfg(unix)]
+pub fn request_termination(pid: u32) {
+    send_signal(pid, libc::SIGTERM);
+}
+
+#[cfg(not(unix))]
+pub fn request_termination(_pid: u32) {}
+
+#[cfg(unix)]
+fn send_signal(pid: u32, signal: libc::c_int) {
+    if let Ok(pid) = libc::pid_t::try_from(pid) {
+        // SAFETY: sending a signal has no memory safety requirements
+        unsafe {
+            libc::kill(pid, signal);
+        }
+    }
+}
+
+/// Waits until the process with given PID exits. Returns `false` on timeout.
+pub fn wait_for_exit(pid: u32, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while is_alive(pid) {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+    true
+}
+
+/// Waits for the child, forwarding termination signals to it in the meantime.
+pub fn wait_forwarding_signals(child: &mut Child) -> std::io::Result<ExitStatus> {
+    forward_signals(child.id());
+    let status = child.wait();
+    restore_signals();
+    status
+}
+
+/// Exit code reported for given process status.
+/// Termination by signal is reported like shells do, as `128 + signal`.
+pub fn exit_code(status: ExitStatus) -> u8 {
+    if let Some(code) = status.code() {
+        return code as u8;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128u8.wrapping_add(signal as u8);
+        }
+    }
+
+    exitcode::SOFTWARE as u8
+}
+
+#[cfg(unix)]
+mod signals {
+    use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+    /// Signals forwarded to the supervised process.
+    const FORWARDED_SIGNALS: [libc::c_int; 3] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
+
+    /// PID of the supervised process, 0 if there's none.
+    static CHILD_PID: AtomicI32 = AtomicI32::new(0);
+    /// Set when any of the forwarded signals has been received.
+    static TERMINATION_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+    extern "C" fn forward_signal(signal: libc::c_int) {
+        TERMINATION_REQUESTED.store(true, Ordering::SeqCst);
+        let pid = CHILD_PID.load(Ordering::SeqCst);
+        if pid > 0 {
+            // SAFETY: `kill` is async-signal-safe
+            unsafe {
+                libc::kill(pid, signal);
+            }
+        }
+    }
+
+    pub fn forward_signals(pid: u32) {
+        CHILD_PID.store(pid as i32, Ordering::SeqCst);
+        for signal in FORWARDED_SIGNALS {
+            // SAFETY: handler only touches atomics and calls `kill`
+            unsafe {
+                libc::signal(signal, forward_signal as *const () as libc::sighandler_t);
+            }
+        }
+    }
+
+    pub fn restore_signals() {
+        for signal in FORWARDED_SIGNALS {
+            // SAFETY: restoring the default disposition is always sound
+            unsafe {
+                libc::signal(signal, libc::SIG_DFL);
+            }
+        }
+        CHILD_PID.store(0, Ordering::SeqCst);
+    }
+
+    pub fn termination_requested() -> bool {
+        TERMINATION_REQUESTED.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(not(unix))]
+mod signals {
+    pub fn forward_signals(_pid: u32) {}
+
+    pub fn restore_signals() {}
+
+    pub fn termination_requested() -> bool {
+        false
+    }
+}
+
+/// Forwards `SIGINT`, `SIGTERM` and `SIGHUP` received by this process to the process with given PID.
+/// With PID 0 the signals are only recorded, see [`termination_requested`].
+pub use signals::forward_signals;
+/// Restores default handling of the forwarded signals.
+pub use signals::restore_signals;
+/// Returns `true` if this process has been asked to terminate while forwarding the signals.
+pub use signals::termination_requested;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_exit_code() {
+        let status = |script: &str| Command::new("sh").args(["-c", script]).status().unwrap();
+
+        assert_eq!(exit_code(status("exit 0")), 0);
+        assert_eq!(exit_code(status("exit 3")), 3);
+        assert_eq!(exit_code(status("kill -TERM $$")), 128 + 15);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_terminate() {
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = child.id();
+        assert!(is_alive(pid));
+
+        // Reap the child in the background, so it doesn't linger as a zombie
+        let waiter = std::thread::spawn(move || child.wait().unwrap());
+        assert!(terminate(pid, Duration::from_secs(5)));
+        assert!(!waiter.join().unwrap().success());
+        assert!(!is_alive(pid));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_is_same_process() {
+        let pid = std::process::id();
+        let started = start_time(pid);
+        assert!(started.is_some());
+
+        assert!(is_same_process(pid, started));
+        // PID reused by other process
+        assert!(!is_same_process(pid, started.map(|time| time + 1)));
+        assert!(!is_same_process(pid, None));
+        assert!(!is_same_process(u32::MAX, started));
+    }
+}
@@ -0,0 +1,176 @@
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// Delay before the first restart, doubled with every consecutive one.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// Longest delay between restarts.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// Server running at least this long is considered healthy, and its next restart is not delayed.
+pub const STABLE_RUN: Duration = Duration::from_secs(60);
+/// Amount of restarts within the window after which the server is considered crash looping.
+const CRASH_LOOP_RESTARTS: usize = 5;
+/// Window of the crash loop detection.
+const CRASH_LOOP_WINDOW: TimeDelta = TimeDelta::minutes(5);
+/// Amount of restarts kept in the history of the server.
+pub const HISTORY_SIZE: usize = 10;
+/// Default amount of consecutive restarts of `on-failure` policy.
+pub const DEFAULT_MAX_RESTARTS: u32 = 5;
+
+/// What the supervisor does when the server exits by itself.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    /// Never restart the server
+    #[default]
+    Never,
+    /// Restart the server when it exits with non-zero code, up to `--max-restarts` times in a row
+    OnFailure,
+    /// Restart the server whenever it exits
+    Always,
+}
+
+impl RestartPolicy {
+    /// Returns `true` if the server that exited with given code should be restarted.
+    pub fn should_restart(&self, exit_code: i32) -> bool {
+        match self {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure => exit_code != 0,
+            RestartPolicy::Always => true,
+        }
+    }
+
+    /// Returns `true` if the policy limits the amount of consecutive restarts.
+    pub fn limits_restarts(&self) -> bool {
+        matches!(self, RestartPolicy::OnFailure)
+    }
+}
+
+impl std::fmt::Display for RestartPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            RestartPolicy::Never => "never",
+            RestartPolicy::OnFailure => "on-failure",
+            RestartPolicy::Always => "always",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Single restart of the server, after it exited.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RestartRecord {
+    pub at: DateTime<Utc>,
+    /// Exit code the server was restarted after
+    pub exit_code: i32,
+}
+
+/// Delay before the restart with given number of consecutive restarts, starting from 1.
+pub fn backoff_delay(attempt: u32) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(16);
+    INITIAL_BACKOFF
+        .saturating_mul(1 << exponent)
+        .min(MAX_BACKOFF)
+}
+
+fn default_max_restarts() -> u32 {
+    DEFAULT_MAX_RESTARTS
+}
+
+/// Restart settings and history of the server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RestartState {
+    #[serde(default)]
+    pub policy: RestartPolicy,
+    /// Maximum amount of consecutive restarts of `on-failure` policy
+    #[serde(default = "default_max_restarts")]
+    pub max_restarts: u32,
+    /// Total amount of restarts done by the supervisor
+    #[serde(default)]
+    pub count: u32,
+    /// Last restarts, the latest one last
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<RestartRecord>,
+}
+
+impl Default for RestartState {
+    fn default() -> Self {
+        Self::new(RestartPolicy::Never, DEFAULT_MAX_RESTARTS)
+    }
+}
+
+impl RestartState {
+    pub fn new(policy: RestartPolicy, max_restarts: u32) -> Self {
+        Self {
+            policy,
+            max_restarts,
+            count: 0,
+            history: Vec::new(),
+        }
+    }
+
+    /// Records the restart after the server exited with given code.
+    pub fn record(&mut self, exit_code: i32) {
+        self.count += 1;
+        self.history.push(RestartRecord {
+            at: Utc::now(),
+            exit_code,
+        });
+        if self.history.len() > HISTORY_SIZE {
+            self.history.remove(0);
+        }
+    }
+}
+
+/// Returns `true` if the server restarted so many times recently, that restarting it again won't help.
+pub fn is_crash_loop(history: &[RestartRecord], now: DateTime<Utc>) -> bool {
+    history
+        .iter()
+        .filter(|record| now - record.at < CRASH_LOOP_WINDOW)
+        .count()
+        >= CRASH_LOOP_RESTARTS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_restart() {
+        assert!(!RestartPolicy::Never.should_restart(1));
+        assert!(RestartPolicy::OnFailure.should_restart(137));
+        assert!(!RestartPolicy::OnFailure.should_restart(0));
+        assert!(RestartPolicy::Always.should_restart(0));
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        let delays: Vec<u64> = (1..=8)
+            .map(|attempt| backoff_delay(attempt).as_secs())
+            .collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 32, 60, 60]);
+        assert_eq!(backoff_delay(u32::MAX), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_is_crash_loop() {
+        let now = Utc::now();
+        let history = |minutes_ago: &[i64]| -> Vec<RestartRecord> {
+            minutes_ago
+                .iter()
+                .map(|minutes| RestartRecord {
+                    at: now - TimeDelta::minutes(*minutes),
+                    exit_code: 1,
+                })
+                .collect()
+        };
+
+        assert!(!is_crash_loop(&history(&[]), now));
+        assert!(!is_crash_loop(&history(&[4, 3, 2, 1]), now));
+        assert!(is_crash_loop(&history(&[4, 3, 2, 1, 0]), now));
+        // Old restarts don't count
+        assert!(!is_crash_loop(&history(&[50, 40, 3, 2, 1, 0]), now));
+    }
+}
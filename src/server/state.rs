@@ -0,0 +1,403 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, RuntimeError};
+use crate::server::health::HealthState;
+use crate::server::process;
+use crate::server::restart::RestartState;
+
+const SERVERS_DIR_NAME: &str = "servers";
+const LOGS_DIR_NAME: &str = "logs";
+const STATE_EXTENSION: &str = "toml";
+const LOCK_FILE_NAME: &str = "registry.lock";
+const LOG_EXTENSION: &str = "log";
+
+/// Entries without supervisor PID older than this are left over by interrupted `server start`.
+const STARTUP_GRACE_PERIOD_SECONDS: i64 = 60;
+
+/// Status of a detached server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerStatus {
+    /// Supervisor is starting the server
+    Starting,
+    /// Server process is running
+    Running,
+    /// Server exited and will be restarted after a delay
+    Restarting,
+    /// Server has been stopped on request
+    Stopped,
+    /// Server process exited by itself
+    Exited,
+    /// Server kept crashing and the supervisor gave up restarting it
+    Failed,
+}
+
+impl ServerStatus {
+    /// Returns `true` if the supervisor of the server should be alive.
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self,
+            ServerStatus::Starting | ServerStatus::Running | ServerStatus::Restarting
+        )
+    }
+}
+
+impl std::fmt::Display for ServerStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ServerStatus::Starting => "starting",
+            ServerStatus::Running => "running",
+            ServerStatus::Restarting => "restarting",
+            ServerStatus::Stopped => "stopped",
+            ServerStatus::Exited => "exited",
+            ServerStatus::Failed => "failed",
+        };
+        f.write_str(name)
+    }
+}
+
+/// State of a detached server, stored in `<runtime_dir>/servers/<id>.toml`.
+/// Written by `server start` and then kept up to date by the supervisor of the server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerState {
+    pub id: String,
+    pub status: ServerStatus,
+    /// Name of the instance the server binary belongs to
+    pub instance: String,
+    /// Model as requested by the user, like `qwen3:8b-q4_k_m`
+    pub model: Option<String>,
+    pub address: String,
+    pub port: u16,
+    /// Path of `llama-server` binary
+    pub binary: PathBuf,
+    /// Arguments `llama-server` is started with
+    pub args: Vec<String>,
+    /// Directory `llama-server` is started in, relative paths in the arguments are resolved against it
+    pub working_dir: PathBuf,
+    /// PID of the supervisor process, 0 until it starts
+    #[serde(default)]
+    pub supervisor_pid: u32,
+    /// Start time of the supervisor process, telling it apart from other process reusing its PID
+    #[serde(default)]
+    pub supervisor_start_time: Option<u64>,
+    /// PID of `llama-server` process
+    pub server_pid: Option<u32>,
+    /// Start time of `llama-server` process
+    #[serde(default)]
+    pub server_start_time: Option<u64>,
+    pub started_at: DateTime<Utc>,
+    /// Exit code of the last `llama-server` process
+    pub exit_code: Option<i32>,
+    /// Health of the running server, as reported by the last health check
+    #[serde(default)]
+    pub health: Option<HealthState>,
+    #[serde(default)]
+    pub restart: RestartState,
+    /// Server is run in the foreground by `llama-mgr server`, which acts as its supervisor.
+    /// Its output is not logged and it cannot be restarted.
+    #[serde(default)]
+    pub foreground: bool,
+}
+
+impl ServerState {
+    /// Returns `true` if the entry claims the server is active, but its supervisor is gone.
+    pub fn is_stale(&self) -> bool {
+        if !self.status.is_active() {
+            return false;
+        }
+        if self.supervisor_pid == 0 {
+            return (Utc::now() - self.started_at).num_seconds() > STARTUP_GRACE_PERIOD_SECONDS;
+        }
+        !self.is_supervisor_alive()
+    }
+
+    /// Returns `true` if the supervisor process is alive. PID reused by other process does not count.
+    pub fn is_supervisor_alive(&self) -> bool {
+        self.supervisor_pid != 0
+            && process::is_same_process(self.supervisor_pid, self.supervisor_start_time)
+    }
+
+    /// Returns the PID of `llama-server`, if the process is alive. PID reused by other process does not count.
+    pub fn live_server_pid(&self) -> Option<u32> {
+        self.server_pid
+            .filter(|pid| process::is_same_process(*pid, self.server_start_time))
+    }
+}
+
+/// Exclusive lock of the registry, released when dropped.
+pub struct RegistryLock {
+    _file: std::fs::File,
+}
+
+/// Directory with the state and logs of detached servers.
+pub struct ServerRegistry {
+    runtime_dir: PathBuf,
+}
+
+impl ServerRegistry {
+    pub fn new(runtime_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            runtime_dir: runtime_dir.into(),
+        }
+    }
+
+    pub fn runtime_dir(&self) -> &Path {
+        &self.runtime_dir
+    }
+
+    fn servers_dir(&self) -> PathBuf {
+        self.runtime_dir.join(SERVERS_DIR_NAME)
+    }
+
+    pub fn state_path(&self, id: &str) -> PathBuf {
+        self.servers_dir()
+            .join(format!("{}.{}", id, STATE_EXTENSION))
+    }
+
+    pub fn log_path(&self, id: &str) -> PathBuf {
+        self.runtime_dir
+            .join(LOGS_DIR_NAME)
+            .join(format!("{}.{}", id, LOG_EXTENSION))
+    }
+
+    pub fn load(&self, id: &str) -> Result<Option<ServerState>> {
+        let path = self.state_path(id);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+        toml::from_str(&content).map(Some).map_err(|e| {
+            RuntimeError::new(
+                format!("Server state file {} is corrupted: {}", path.display(), e),
+                exitcode::DATAERR as u8,
+            )
+        })
+    }
+
+    /// Loads the state of the server, failing if there's none.
+    pub fn get(&self, id: &str) -> Result<ServerState> {
+        self.load(id)?.ok_or_else(|| {
+            RuntimeError::new(
+                format!("Server '{}' not found, see `llama-mgr server list`", id),
+                exitcode::USAGE as u8,
+            )
+        })
+    }
+
+    /// Saves the state. File is replaced atomically, as it's read by other processes.
+    pub fn save(&self, state: &ServerState) -> Result<()> {
+        let content = toml::to_string(state).map_err(|e| {
+            RuntimeError::new(
+                format!("Failed to serialize server state: {}", e),
+                exitcode::SOFTWARE as u8,
+            )
+        })?;
+
+        let path = self.state_path(&state.id);
+        std::fs::create_dir_all(self.servers_dir())?;
+        let temp_path = path.with_extension(format!("{}.{}", STATE_EXTENSION, std::process::id()));
+        std::fs::write(&temp_path, content)?;
+        std::fs::rename(&temp_path, &path)?;
+        Ok(())
+    }
+
+    /// Locks the registry, waiting while other process holds the lock. Held while the ID and port
+    /// of a server are picked and reserved by saving its state, so concurrent starts never pick the same ones.
+    pub fn lock(&self) -> Result<RegistryLock> {
+        std::fs::create_dir_all(self.servers_dir())?;
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.servers_dir().join(LOCK_FILE_NAME))?;
+        file.lock()?;
+        Ok(RegistryLock { _file: file })
+    }
+
+    /// Removes the state of the server. Logs are kept.
+    pub fn remove(&self, id: &str) -> Result<()> {
+        match std::fs::remove_file(self.state_path(id)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the states of all the servers, sorted by ID. Corrupted entries are skipped.
+    pub fn list(&self) -> Result<Vec<ServerState>> {
+        let servers_dir = self.servers_dir();
+        if !servers_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut states = Vec::new();
+        for entry in std::fs::read_dir(&servers_dir)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_none_or(|extension| extension != STATE_EXTENSION)
+            {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+
+            match self.load(id) {
+                Ok(Some(state)) => states.push(state),
+                Ok(None) => {}
+                Err(e) => log::warn!("{}", e),
+            }
+        }
+
+        states.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(states)
+    }
+
+    /// Removes the entries of the servers whose supervisors died without updating the state,
+    /// like after a crash or reboot. Returns the removed entries.
+    pub fn clean_stale(&self) -> Result<Vec<ServerState>> {
+        let mut stale = Vec::new();
+        for state in self.list()? {
+            if state.is_stale() {
+                self.remove(&state.id)?;
+                stale.push(state);
+            }
+        }
+        Ok(stale)
+    }
+
+    /// Returns `base` or `base-N`, whichever is the first ID not used by an active server.
+    pub fn unique_id(&self, base: &str) -> Result<String> {
+        let active: Vec<String> = self
+            .list()?
+            .into_iter()
+            .filter(|state| state.status.is_active())
+            .map(|state| state.id)
+            .collect();
+
+        let mut id = base.to_string();
+        let mut number = 1;
+        while active.contains(&id) {
+            number += 1;
+            id = format!("{}-{}", base, number);
+        }
+        Ok(id)
+    }
+}
+
+/// Server IDs are used as file names, so they cannot contain path separators.
+pub fn validate_id(id: &str) -> Result<()> {
+    let is_valid = !id.is_empty()
+        && !id.starts_with('.')
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+
+    if !is_valid {
+        return Err(RuntimeError::new(
+            format!(
+                "Invalid server name '{}'. Only letters, digits, '-', '_' and '.' are allowed.",
+                id
+            ),
+            exitcode::USAGE as u8,
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::test_support::state;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_registry() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ServerRegistry::new(temp_dir.path());
+        assert!(registry.list().unwrap().is_empty());
+
+        let running = state("qwen3", ServerStatus::Running, std::process::id());
+        registry.save(&running).unwrap();
+        assert_eq!(registry.get("qwen3").unwrap(), running);
+        assert_eq!(registry.unique_id("qwen3").unwrap(), "qwen3-2");
+        assert_eq!(registry.unique_id("gemma").unwrap(), "gemma");
+
+        registry
+            .save(&state("exited", ServerStatus::Exited, u32::MAX))
+            .unwrap();
+        // PID above `pid_t` range never belongs to a live process
+        registry
+            .save(&state("crashed", ServerStatus::Running, u32::MAX))
+            .unwrap();
+
+        let stale = registry.clean_stale().unwrap();
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].id, "crashed");
+
+        let ids: Vec<String> = registry.list().unwrap().into_iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec!["exited", "qwen3"]);
+
+        registry.remove("qwen3").unwrap();
+        assert!(registry.load("qwen3").unwrap().is_none());
+        assert!(registry.get("qwen3").is_err());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_is_stale_with_reused_pid() {
+        let running = state("qwen3", ServerStatus::Running, std::process::id());
+        assert!(!running.is_stale());
+        assert!(running.is_supervisor_alive());
+
+        // PID of the supervisor now belongs to other process
+        let mut reused = running.clone();
+        reused.supervisor_start_time = reused.supervisor_start_time.map(|time| time + 1);
+        assert!(reused.is_stale());
+        assert!(!reused.is_supervisor_alive());
+
+        reused.supervisor_start_time = None;
+        assert!(reused.is_stale());
+
+        reused.server_pid = Some(std::process::id());
+        assert_eq!(reused.live_server_pid(), None);
+        reused.server_start_time = process::start_time(std::process::id());
+        assert_eq!(reused.live_server_pid(), Some(std::process::id()));
+    }
+
+    #[test]
+    fn test_lock() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ServerRegistry::new(temp_dir.path());
+        let locked = std::sync::atomic::AtomicBool::new(false);
+
+        let lock = registry.lock().unwrap();
+        std::thread::scope(|scope| {
+            let waiter = scope.spawn(|| {
+                let _lock = registry.lock().unwrap();
+                locked.store(true, std::sync::atomic::Ordering::SeqCst);
+            });
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            assert!(!locked.load(std::sync::atomic::Ordering::SeqCst));
+
+            drop(lock);
+            waiter.join().unwrap();
+        });
+        assert!(locked.load(std::sync::atomic::Ordering::SeqCst));
+
+        // Lock file is not a server entry
+        assert!(registry.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_validate_id() {
+        assert!(validate_id("qwen3-8b_q4.k").is_ok());
+        assert!(validate_id("").is_err());
+        assert!(validate_id(".hidden").is_err());
+        assert!(validate_id("../escape").is_err());
+    }
+}
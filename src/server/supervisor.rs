@@ -0,0 +1,587 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{Local, Utc};
+
+use crate::error::{Result, RuntimeError};
+use crate::server::health::{self, HealthMonitor, HealthState};
+use crate::server::logs::{self, RotatingLog};
+use crate::server::process;
+use crate::server::restart;
+use crate::server::state::{ServerRegistry, ServerState, ServerStatus};
+
+/// Time the supervisor has to report its PID after being spawned.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time the server has to shut down after `SIGTERM`, before it's killed.
+pub const STOP_TIMEOUT: Duration = Duration::from_secs(15);
+/// Amount of log lines shown when the server fails to start or to get ready.
+const FAILURE_LOG_LINES: usize = 20;
+
+/// Marks the server as starting and saves its state, which reserves its ID and port
+/// until the supervisor starts. Should be called with the registry locked.
+pub fn reserve(registry: &ServerRegistry, state: &mut ServerState) -> Result<()> {
+    state.status = ServerStatus::Starting;
+    state.supervisor_pid = 0;
+    state.supervisor_start_time = None;
+    state.server_pid = None;
+    state.server_start_time = None;
+    state.exit_code = None;
+    state.health = None;
+    state.started_at = Utc::now();
+    registry.save(state)
+}
+
+/// Starts the supervisor of the server reserved with [`reserve`], in a detached process.
+/// Returns the state saved by the supervisor once it starts the server.
+pub fn spawn(registry: &ServerRegistry, state: ServerState) -> Result<ServerState> {
+    let executable = std::env::current_exe()?;
+    let mut command = Command::new(executable);
+    command
+        .arg("server")
+        .arg("supervise")
+        .arg("--runtime-dir")
+        .arg(std::path::absolute(registry.runtime_dir())?)
+        .arg(&state.id)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    process::detach(&mut command);
+
+    let mut supervisor = command.spawn().map_err(|e| {
+        let _ = registry.remove(&state.id);
+        RuntimeError::new(
+            format!("Failed to start server supervisor - {}", e),
+            exitcode::OSERR as u8,
+        )
+    })?;
+
+    // Supervisor reports its PID in the state once the server process is spawned
+    let deadline = Instant::now() + STARTUP_TIMEOUT;
+    loop {
+        let current = registry.get(&state.id)?;
+        if current.supervisor_pid != 0 && current.status != ServerStatus::Starting {
+            if matches!(current.status, ServerStatus::Exited | ServerStatus::Failed) {
+                return Err(failure_error(
+                    registry,
+                    &current.id,
+                    &format!(
+                        "exited right after start with code {}",
+                        current.exit_code.unwrap_or(-1)
+                    ),
+                    exitcode::SOFTWARE as u8,
+                ));
+            }
+            return Ok(current);
+        }
+
+        if let Some(status) = supervisor.try_wait()? {
+            let _ = registry.remove(&state.id);
+            return Err(RuntimeError::new(
+                format!("Server supervisor exited with {}", status),
+                exitcode::SOFTWARE as u8,
+            ));
+        }
+        if Instant::now() >= deadline {
+            return Err(RuntimeError::new(
+                format!(
+                    "Server supervisor did not start in {}s",
+                    STARTUP_TIMEOUT.as_secs()
+                ),
+                exitcode::TEMPFAIL as u8,
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Error describing the problem of the server with given ID, followed by the tail of its log.
+pub fn failure_error(
+    registry: &ServerRegistry,
+    id: &str,
+    problem: &str,
+    exit_code: u8,
+) -> RuntimeError {
+    let log_path = registry.log_path(id);
+    let tail = logs::tail(&log_path, FAILURE_LOG_LINES)
+        .unwrap_or_default()
+        .join("\n");
+    RuntimeError::new(
+        format!(
+            "Server '{}' {}, log {}:\n{}",
+            id,
+            problem,
+            log_path.display(),
+            tail
+        ),
+        exit_code,
+    )
+}
+
+/// Stops the server - asks the supervisor to terminate and waits for it, killing the processes
+/// that don't terminate in time. Returns `false` if the server had to be killed.
+pub fn stop(state: &ServerState) -> bool {
+    // Processes are identified by their start time too, so other process reusing the PID is never signalled
+    if !state.is_supervisor_alive() {
+        return true;
+    }
+
+    let stopped = process::terminate(state.supervisor_pid, STOP_TIMEOUT);
+    if !stopped && let Some(server_pid) = state.live_server_pid() {
+        process::terminate(server_pid, Duration::ZERO);
+    }
+    stopped
+}
+
+/// Runs the supervisor of the server with given ID: starts the server, copies its output
+/// to the rotated log, monitors its health, restarts it according to its restart policy
+/// and records its status in the state.
+pub fn run(registry: &ServerRegistry, id: &str) -> Result<()> {
+    let state = Mutex::new(registry.get(id)?);
+    let log = RotatingLog::open(
+        registry.log_path(id),
+        logs::DEFAULT_MAX_LOG_SIZE,
+        logs::DEFAULT_KEPT_LOGS,
+    )?;
+    let log = Arc::new(Mutex::new(log));
+
+    let result = supervise(registry, &state, &log);
+    if let Err(e) = &result {
+        write_event(&log, &format!("Supervisor failed: {}", e));
+    }
+    result
+}
+
+fn supervise(
+    registry: &ServerRegistry,
+    state: &Mutex<ServerState>,
+    log: &Arc<Mutex<RotatingLog>>,
+) -> Result<()> {
+    {
+        let mut state = lock(state);
+        state.supervisor_pid = std::process::id();
+        state.supervisor_start_time = process::start_time(state.supervisor_pid);
+    }
+    // Handlers stay installed between the runs, so stopping the server during the backoff works too
+    process::forward_signals(0);
+    let result = supervise_runs(registry, state, log);
+    process::restore_signals();
+    result
+}
+
+/// Runs the server, restarting it according to its restart policy until it's stopped,
+/// exits for good or gets stuck in a crash loop.
+fn supervise_runs(
+    registry: &ServerRegistry,
+    state: &Mutex<ServerState>,
+    log: &Arc<Mutex<RotatingLog>>,
+) -> Result<()> {
+    // Consecutive restarts since the last stable run
+    let mut attempt = 0;
+    loop {
+        let started = Instant::now();
+        let exit_code = run_server(registry, state, log)?;
+        if started.elapsed() >= restart::STABLE_RUN {
+            attempt = 0;
+        }
+
+        let mut current = lock(state);
+        current.exit_code = Some(exit_code);
+        current.server_pid = None;
+        current.server_start_time = None;
+        current.health = Some(HealthState::Exited);
+
+        let policy = current.restart.policy;
+        let (status, message) = if process::termination_requested() {
+            (ServerStatus::Stopped, None)
+        } else if !policy.should_restart(exit_code) {
+            (ServerStatus::Exited, None)
+        } else if policy.limits_restarts() && attempt >= current.restart.max_restarts {
+            let message = format!(
+                "giving up after {} restarts in a row",
+                current.restart.max_restarts
+            );
+            (ServerStatus::Failed, Some(message))
+        } else if is_crash_loop(&current) {
+            (
+                ServerStatus::Failed,
+                Some("crash loop detected".to_string()),
+            )
+        } else {
+            (ServerStatus::Restarting, None)
+        };
+
+        current.status = status;
+        if status != ServerStatus::Restarting {
+            let reason = message.map(|m| format!(", {}", m)).unwrap_or_default();
+            write_event(
+                log,
+                &format!("Server {} with code {}{}", status, exit_code, reason),
+            );
+            return registry.save(&current);
+        }
+
+        attempt += 1;
+        current.restart.record(exit_code);
+        let delay = restart::backoff_delay(attempt);
+        write_event(
+            log,
+            &format!(
+                "Server exited with code {}, restarting in {}s (restart policy {}, attempt {})",
+                exit_code,
+                delay.as_secs(),
+                policy,
+                attempt
+            ),
+        );
+        registry.save(&current)?;
+        drop(current);
+
+        let deadline = Instant::now() + delay;
+        while Instant::now() < deadline && !process::termination_requested() {
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        if process::termination_requested() {
+            let mut current = lock(state);
+            current.status = ServerStatus::Stopped;
+            write_event(log, "Server stopped while waiting for restart");
+            return registry.save(&current);
+        }
+    }
+}
+
+/// Returns `true` if the server is crash looping. Restarts from before the server was started
+/// (or restarted by the user) are not counted.
+fn is_crash_loop(state: &ServerState) -> bool {
+    let history: Vec<_> = state
+        .restart
+        .history
+        .iter()
+        .filter(|record| record.at >= state.started_at)
+        .cloned()
+        .collect();
+    restart::is_crash_loop(&history, Utc::now())
+}
+
+/// Runs the server once, until it exits. Returns its exit code.
+fn run_server(
+    registry: &ServerRegistry,
+    state: &Mutex<ServerState>,
+    log: &Arc<Mutex<RotatingLog>>,
+) -> Result<i32> {
+    let mut command = {
+        let state = lock(state);
+        write_event(
+            log,
+            &format!(
+                "Starting {} {}",
+                state.binary.display(),
+                state.args.join(" ")
+            ),
+        );
+
+        let mut command = Command::new(&state.binary);
+        command
+            .args(&state.args)
+            .current_dir(&state.working_dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        command
+    };
+
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            write_event(log, &format!("Failed to start server: {}", e));
+            return Ok(exitcode::OSERR);
+        }
+    };
+
+    process::forward_signals(child.id());
+    // Stop could have been requested before the server was started
+    if process::termination_requested() {
+        process::request_termination(child.id());
+    }
+
+    {
+        let mut state = lock(state);
+        state.status = ServerStatus::Running;
+        state.server_pid = Some(child.id());
+        state.server_start_time = process::start_time(child.id());
+        state.health = Some(HealthState::Loading);
+        registry.save(&state)?;
+    }
+
+    let copiers: Vec<_> = [
+        child
+            .stdout
+            .take()
+            .map(|out| Box::new(out) as Box<dyn Read + Send>),
+        child
+            .stderr
+            .take()
+            .map(|err| Box::new(err) as Box<dyn Read + Send>),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|output| {
+        let log = Arc::clone(log);
+        std::thread::spawn(move || copy_lines(output, &log))
+    })
+    .collect();
+
+    let server_exited = AtomicBool::new(false);
+    let status = std::thread::scope(|scope| {
+        scope.spawn(|| monitor_health(registry, state, log, &server_exited));
+        let status = child.wait();
+        server_exited.store(true, Ordering::SeqCst);
+        status
+    });
+    process::forward_signals(0);
+    let status = status?;
+    for copier in copiers {
+        let _ = copier.join();
+    }
+
+    write_event(log, &format!("Server process exited ({})", status));
+    Ok(i32::from(process::exit_code(status)))
+}
+
+/// Checks the health of the server until it exits, recording the changes in the state and the log.
+/// Checks are more frequent while the model is loading, so readiness is noticed quickly.
+fn monitor_health(
+    registry: &ServerRegistry,
+    state: &Mutex<ServerState>,
+    log: &Mutex<RotatingLog>,
+    server_exited: &AtomicBool,
+) {
+    let monitor = {
+        let state = lock(state);
+        HealthMonitor::new(&state.address, state.port, None)
+    };
+
+    let mut last_check: Option<Instant> = None;
+    let mut interval = health::LOADING_CHECK_INTERVAL;
+    while !server_exited.load(Ordering::SeqCst) {
+        if last_check.is_some_and(|checked| checked.elapsed() < interval) {
+            std::thread::sleep(Duration::from_millis(100));
+            continue;
+        }
+
+        let report = monitor.check();
+        last_check = Some(Instant::now());
+        interval = match report.state {
+            HealthState::Ready => health::READY_CHECK_INTERVAL,
+            _ => health::LOADING_CHECK_INTERVAL,
+        };
+
+        let mut state = lock(state);
+        // Server might have exited while the request was pending
+        if server_exited.load(Ordering::SeqCst) || state.health == Some(report.state) {
+            continue;
+        }
+
+        state.health = Some(report.state);
+        let message = match (report.state, &report.message) {
+            (HealthState::Error, Some(message)) => {
+                format!("Server health check failed: {}", message)
+            }
+            (health, _) => format!("Server is {}", health),
+        };
+        write_event(log, &message);
+        if let Err(e) = registry.save(&state) {
+            write_event(log, &format!("Failed to save server state: {}", e));
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Copies the output to the log line by line, so lines of stdout and stderr are never mixed.
+fn copy_lines(output: impl Read, log: &Mutex<RotatingLog>) {
+    let mut reader = BufReader::new(output);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                let _ = lock(log).write_all(&line);
+            }
+        }
+    }
+}
+
+/// Writes a message of the supervisor itself to the log.
+fn write_event(log: &Mutex<RotatingLog>, message: &str) {
+    let _ = writeln!(
+        lock(log),
+        "[llama-mgr {}] {}",
+        Local::now().format("%Y-%m-%d %H:%M:%S"),
+        message
+    );
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::server::restart::{RestartPolicy, RestartRecord, RestartState};
+    use crate::server::test_support::scripts::{CRASHING_SERVER, RUNNING_SERVER, write_script};
+    use crate::server::test_support::{READY_RESPONSE, fake_server, state};
+    use serial_test::serial;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    /// Saves the state of a server run by the shell script standing in for `llama-server`.
+    fn save_script_server(
+        dir: &Path,
+        registry: &ServerRegistry,
+        script: &str,
+        restart: RestartState,
+    ) -> ServerState {
+        let binary = dir.join("llama-server");
+        write_script(&binary, script);
+
+        let mut state = state("fake", ServerStatus::Starting, 0);
+        state.binary = binary;
+        state.working_dir = dir.to_path_buf();
+        state.restart = restart;
+        registry.save(&state).unwrap();
+        state
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_stop() {
+        let temp_dir = TempDir::new().unwrap();
+        let script = temp_dir.path().join("supervisor");
+        write_script(&script, RUNNING_SERVER);
+        let mut supervisor = Command::new(&script).spawn().unwrap();
+
+        let state = state("fake", ServerStatus::Running, supervisor.id());
+        assert!(state.is_supervisor_alive());
+        assert!(stop(&state));
+        assert!(supervisor.try_wait().unwrap().is_some());
+        assert!(stop(&state));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_stop_ignores_reused_pid() {
+        let mut unrelated = Command::new("sleep").arg("30").spawn().unwrap();
+
+        // Processes of the server died and their PIDs now belong to an unrelated process
+        let mut state = state("fake", ServerStatus::Running, unrelated.id());
+        state.supervisor_start_time = state.supervisor_start_time.map(|time| time + 1);
+        state.server_pid = Some(unrelated.id());
+        state.server_start_time = state.supervisor_start_time;
+        assert!(stop(&state));
+        assert!(unrelated.try_wait().unwrap().is_none());
+
+        unrelated.kill().unwrap();
+        unrelated.wait().unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn test_run_gives_up_after_max_restarts() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ServerRegistry::new(temp_dir.path().join("run"));
+        let restart = RestartState::new(RestartPolicy::OnFailure, 1);
+        save_script_server(temp_dir.path(), &registry, CRASHING_SERVER, restart);
+
+        run(&registry, "fake").unwrap();
+
+        let state = registry.get("fake").unwrap();
+        assert_eq!(state.status, ServerStatus::Failed);
+        assert_eq!(state.exit_code, Some(3));
+        assert_eq!(state.server_pid, None);
+        assert_eq!(state.restart.count, 1);
+        assert_eq!(state.restart.history.len(), 1);
+        assert_eq!(state.restart.history[0].exit_code, 3);
+
+        let log = std::fs::read_to_string(registry.log_path("fake")).unwrap();
+        assert_eq!(log.matches("error: out of memory").count(), 2);
+        assert!(log.contains("giving up after 1 restarts in a row"));
+    }
+
+    #[test]
+    #[serial]
+    fn test_run_detects_crash_loop() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ServerRegistry::new(temp_dir.path().join("run"));
+        let mut restart = RestartState::new(RestartPolicy::Always, 0);
+        restart.count = 5;
+        restart.history = vec![
+            RestartRecord {
+                at: Utc::now(),
+                exit_code: 3,
+            };
+            5
+        ];
+        let mut state = save_script_server(temp_dir.path(), &registry, CRASHING_SERVER, restart);
+        // Restarts from before the server was started are not counted, so it has to be started earlier
+        state.started_at -= chrono::TimeDelta::minutes(1);
+        registry.save(&state).unwrap();
+
+        run(&registry, "fake").unwrap();
+
+        let state = registry.get("fake").unwrap();
+        assert_eq!(state.status, ServerStatus::Failed);
+        assert_eq!(state.exit_code, Some(3));
+        assert_eq!(state.restart.count, 5);
+        let log = std::fs::read_to_string(registry.log_path("fake")).unwrap();
+        assert!(log.contains("crash loop detected"));
+    }
+
+    #[test]
+    #[serial]
+    fn test_run_monitors_health() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ServerRegistry::new(temp_dir.path().join("run"));
+        let mut state = save_script_server(
+            temp_dir.path(),
+            &registry,
+            RUNNING_SERVER,
+            RestartState::default(),
+        );
+        // Health checks are answered by the test, as the script cannot listen on the port
+        let (port, health_server) = fake_server(&[READY_RESPONSE]);
+        state.port = port;
+        registry.save(&state).unwrap();
+
+        std::thread::scope(|scope| {
+            let supervisor = scope.spawn(|| run(&registry, "fake"));
+
+            let deadline = Instant::now() + Duration::from_secs(10);
+            let running = loop {
+                let state = registry.get("fake").unwrap();
+                if state.health == Some(HealthState::Ready) || Instant::now() >= deadline {
+                    break state;
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            };
+            assert_eq!(running.status, ServerStatus::Running);
+            assert_eq!(running.health, Some(HealthState::Ready));
+            assert_eq!(running.supervisor_pid, std::process::id());
+            health_server.join().unwrap();
+
+            let server_pid = running.live_server_pid().unwrap();
+            process::request_termination(server_pid);
+            supervisor.join().unwrap().unwrap();
+            assert!(!process::is_alive(server_pid));
+        });
+
+        let state = registry.get("fake").unwrap();
+        assert_eq!(state.status, ServerStatus::Exited);
+        assert_eq!(state.exit_code, Some(0));
+        assert_eq!(state.server_pid, None);
+        assert_eq!(state.health, Some(HealthState::Exited));
+        assert_eq!(state.restart.count, 0);
+    }
+}
@@ -0,0 +1,65 @@
+//! Fixtures shared by the tests of the server modules.
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+
+use chrono::Utc;
+
+use crate::server::process;
+use crate::server::restart::RestartState;
+use crate::server::state::{ServerState, ServerStatus};
+
+#[cfg(unix)]
+pub mod scripts;
+
+/// Response of `llama-server` to the health check once the model is loaded.
+pub const READY_RESPONSE: &str =
+    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{\"status\":\"ok\"}";
+
+/// State of a server with given status and supervisor.
+pub fn state(id: &str, status: ServerStatus, supervisor_pid: u32) -> ServerState {
+    ServerState {
+        id: id.to_string(),
+        status,
+        instance: "main".to_string(),
+        model: Some("qwen3:8b-q4_k_m".to_string()),
+        address: "127.0.0.1".to_string(),
+        port: 8080,
+        binary: PathBuf::from("/llama/main/current/bin/llama-server"),
+        args: vec!["--port".to_string(), "8080".to_string()],
+        working_dir: PathBuf::from("/home/user"),
+        supervisor_pid,
+        supervisor_start_time: process::start_time(supervisor_pid),
+        server_pid: None,
+        server_start_time: None,
+        started_at: Utc::now(),
+        exit_code: None,
+        health: None,
+        restart: RestartState::default(),
+        foreground: false,
+    }
+}
+
+/// Fake `llama-server` answering the health checks with given responses, then closing.
+pub fn fake_server(responses: &[&'static str]) -> (u16, std::thread::JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let responses = responses.to_vec();
+
+    let handle = std::thread::spawn(move || {
+        for response in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let length = stream.read(&mut buffer).unwrap();
+                assert!(length > 0, "connection closed before the end of request");
+                request.extend_from_slice(&buffer[..length]);
+            }
+            assert!(String::from_utf8_lossy(&request).starts_with("GET /health "));
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    (port, handle)
+}
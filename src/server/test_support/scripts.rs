@@ -0,0 +1,18 @@
+//! Shell scripts standing in for `llama-server`. Shared with the integration tests,
+//! so it does not depend on the crate.
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+/// Server running until it's asked to terminate.
+pub const RUNNING_SERVER: &str = "trap 'exit 0' TERM\nwhile true; do sleep 0.1; done\n";
+/// Server exiting with code 3 right after start.
+pub const CRASHING_SERVER: &str = "echo 'error: out of memory' >&2\nexit 3\n";
+
+/// Writes the executable shell script with given body. Like `llama-server`, the script
+/// only prints its version when run with `--version`.
+pub fn write_script(path: &Path, script: &str) {
+    let version = "if [ \"$1\" = --version ]; then echo 'version: 0 (fake)'; exit 0; fi\n";
+    std::fs::write(path, format!("#!/bin/sh\n{}{}", version, script)).unwrap();
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+}
@@ -0,0 +1,130 @@
+//! Runs detached servers through the built binary, as the supervisor is the binary itself,
+//! with a shell script standing in for `llama-server`.
+#![cfg(target_os = "linux")]
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+// Not all the scripts are used here
+#[path = "../src/server/test_support/scripts.rs"]
+#[allow(dead_code)]
+mod scripts;
+
+use scripts::{RUNNING_SERVER, write_script};
+
+const CONFIG: &str = r#"schema_version = 2
+
+[config]
+default_profile = "cpu"
+
+[paths]
+llama_dir = "./llama"
+models_dir = "./models"
+runtime_dir = "./run"
+
+[profiles.cpu]
+"#;
+
+/// Configuration with an instance of `llama.cpp` whose `llama-server` is the given shell script.
+struct Fixture {
+    dir: TempDir,
+}
+
+impl Fixture {
+    fn new(script: &str) -> Self {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+
+        let bin_dir = dir.path().join("llama/cpu/current/bin");
+        std::fs::create_dir_all(&bin_dir).unwrap();
+        write_script(&bin_dir.join("llama-server"), script);
+        Self { dir }
+    }
+
+    fn run(&self, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_llama-mgr"))
+            .arg("-c")
+            .arg(self.dir.path().join("config.toml"))
+            .args(args)
+            .current_dir(self.dir.path())
+            .env_remove("RUST_LOG")
+            .output()
+            .unwrap()
+    }
+
+    fn state_path(&self, id: &str) -> PathBuf {
+        self.dir.path().join(format!("run/servers/{}.toml", id))
+    }
+
+    fn state(&self, id: &str) -> toml::Table {
+        toml::from_str(&std::fs::read_to_string(self.state_path(id)).unwrap()).unwrap()
+    }
+
+    /// Waits until the server gets given status, returning its state.
+    fn wait_for_status(&self, id: &str, status: &str) -> toml::Table {
+        let deadline = Instant::now() + Duration::from_secs(20);
+        loop {
+            let state = self.state(id);
+            if state["status"].as_str() == Some(status) {
+                return state;
+            }
+            assert!(
+                Instant::now() < deadline,
+                "server did not get {} status: {:?}",
+                status,
+                state
+            );
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+/// Returns `true` if the process exists and is not a zombie. Detached supervisors outlive
+/// their parent, so they linger as zombies until they're reaped by init.
+fn is_alive(pid: i64) -> bool {
+    std::fs::read_to_string(format!("/proc/{}/stat", pid)).is_ok_and(|stat| {
+        stat.rsplit_once(')')
+            .is_some_and(|(_, rest)| !rest.trim_start().starts_with('Z'))
+    })
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "command failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn pid(state: &toml::Table, key: &str) -> i64 {
+    state[key].as_integer().unwrap()
+}
+
+#[test]
+fn test_start_list_stop() {
+    let fixture = Fixture::new(RUNNING_SERVER);
+
+    let output = fixture.run(&["server", "start", "--port", "auto", "--name", "fake"]);
+    assert!(stdout(&output).contains("Started server 'fake'"));
+    let state = fixture.wait_for_status("fake", "running");
+    let supervisor_pid = pid(&state, "supervisor_pid");
+    let server_pid = pid(&state, "server_pid");
+    assert!(is_alive(supervisor_pid));
+    assert!(is_alive(server_pid));
+
+    let list = stdout(&fixture.run(&["server", "list"]));
+    assert!(list.lines().any(|line| line.starts_with("fake ")));
+
+    let output = fixture.run(&["server", "stop", "fake"]);
+    assert!(stdout(&output).contains("Stopped server 'fake'"));
+    assert!(!fixture.state_path("fake").exists());
+    assert!(!is_alive(supervisor_pid));
+    assert!(!is_alive(server_pid));
+
+    let log = std::fs::read_to_string(fixture.dir.path().join("run/logs/fake.log")).unwrap();
+    assert!(log.contains("Server stopped with code 0"));
+}